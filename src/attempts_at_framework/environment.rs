@@ -0,0 +1,198 @@
+use crate::attempts_at_framework::v1;
+use crate::attempts_at_framework::v2;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// # Environment
+///
+/// The single trait every agent in the framework is written against. Unlike the old v1 and v2
+/// `State` traits, actions and observations are typed, so agents can key their tables on
+/// `(Observation, Action)` pairs instead of formatting and re-parsing strings.
+pub trait Environment: Clone {
+    /// The type of action that can be taken from this environment.
+    type Action: Clone + Eq + Hash + Debug;
+
+    /// What an agent gets to see of the environment, this needs to be unique to the state as it
+    /// is what tabular agents key their values on.
+    type Observation: Clone + Eq + Hash + Debug;
+
+    /// returns the observation of the current state
+    fn get_observation(&self) -> Self::Observation;
+
+    /// returns a list of actions available from this state
+    fn get_actions(&self) -> Vec<Self::Action>;
+
+    /// Is this state terminal, as in, is this the last state of the episode.
+    fn is_terminal(&self) -> bool;
+
+    /// Execute the given action, and return the reward for that action and the next state.
+    fn take_action(&self, action: &Self::Action) -> (f64, Self);
+}
+
+/// # Feature Vector
+///
+/// An optional capability for environments that can describe themselves as a list of values,
+/// needed by anything that approximates values with weights or a
+/// [`Model`](crate::attempts_at_framework::v2::artificial_neural_network::model::Model).
+pub trait FeatureVector: Environment {
+    /// Returns the list of values for the current state
+    fn get_values(&self) -> Vec<f64>;
+}
+
+/// # State Action Feature Vector
+///
+/// For environments that can describe a state together with one of its actions as a fixed size
+/// list of values, this is what action value networks like
+/// [`Dqn`](crate::attempts_at_framework::v2::agent::dqn::Dqn) take as their input. Every action of
+/// every state must produce the same number of values.
+pub trait StateActionFeatureVector: Environment {
+    fn get_state_action_values(&self, action: &Self::Action) -> Vec<f64>;
+}
+
+/// Adapter so every string based [`v1::state::State`] is an [`Environment`], the state id is used
+/// as the observation.
+impl<S: v1::state::State> Environment for S {
+    type Action = String;
+    type Observation = String;
+
+    fn get_observation(&self) -> String {
+        v1::state::State::get_id(self)
+    }
+
+    fn get_actions(&self) -> Vec<String> {
+        v1::state::State::get_actions(self)
+    }
+
+    fn is_terminal(&self) -> bool {
+        v1::state::State::is_terminal(self)
+    }
+
+    fn take_action(&self, action: &String) -> (f64, Self) {
+        v1::state::State::take_action(self, action)
+    }
+}
+
+/// Adapter so every [`v2::state::State`] exposes its values as a [`FeatureVector`].
+impl<S: v2::state::State> FeatureVector for S {
+    fn get_values(&self) -> Vec<f64> {
+        v2::state::State::get_values(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v1::agent::QLearning;
+    use crate::attempts_at_framework::v1::policy::Policy;
+    use std::collections::HashMap;
+
+    #[derive(Clone)]
+    struct CountingState {
+        count: usize,
+    }
+
+    impl v1::state::State for CountingState {
+        fn get_id(&self) -> String {
+            self.count.to_string()
+        }
+
+        fn get_actions(&self) -> Vec<String> {
+            vec!["up".to_string()]
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.count >= 2
+        }
+
+        fn take_action(&self, _action: &str) -> (f64, Self) {
+            (
+                1.0,
+                Self {
+                    count: self.count + 1,
+                },
+            )
+        }
+    }
+
+    impl v2::state::State for CountingState {
+        fn get_values(&self) -> Vec<f64> {
+            vec![self.count as f64]
+        }
+    }
+
+    #[test]
+    fn v1_states_are_environments() {
+        let state = CountingState { count: 0 };
+        let mut visited: HashMap<(String, String), f64> = HashMap::new();
+
+        let mut current = state;
+        while !Environment::is_terminal(&current) {
+            let action = Environment::get_actions(&current)[0].clone();
+            let (reward, next) = Environment::take_action(&current, &action);
+            visited.insert((current.get_observation(), action), reward);
+            current = next;
+        }
+
+        assert_eq!(visited.len(), 2);
+        assert_eq!(
+            visited.get(&("1".to_string(), "up".to_string())),
+            Some(&1.0)
+        );
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Move {
+        Left,
+        Right,
+    }
+
+    #[derive(Clone)]
+    struct Corridor {
+        position: i32,
+    }
+
+    impl Environment for Corridor {
+        type Action = Move;
+        type Observation = i32;
+
+        fn get_observation(&self) -> i32 {
+            self.position
+        }
+
+        fn get_actions(&self) -> Vec<Move> {
+            vec![Move::Left, Move::Right]
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.position >= 3
+        }
+
+        fn take_action(&self, action: &Move) -> (f64, Self) {
+            let position = match action {
+                Move::Left => (self.position - 1).max(0),
+                Move::Right => self.position + 1,
+            };
+            (-1.0, Self { position })
+        }
+    }
+
+    #[test]
+    fn typed_environments_work_with_agents() {
+        let mut agent = QLearning::new(0.1, 0.5, 1.0);
+        agent.learn_for_episode_count(200, vec![Corridor { position: 0 }]);
+
+        let policy = agent.get_policy().to_deterministic_policy();
+        (0..3).for_each(|position| {
+            assert_eq!(
+                policy.select_action_for_state(&position).unwrap(),
+                Move::Right
+            );
+        });
+    }
+
+    #[test]
+    fn v2_states_have_feature_vectors() {
+        let state = CountingState { count: 1 };
+        assert_eq!(FeatureVector::get_values(&state), vec![1.0]);
+    }
+}
@@ -1,2 +1,3 @@
+pub mod environment;
 pub mod v1;
 pub mod v2;
@@ -0,0 +1,487 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+use crate::attempts_at_framework::v2::state::State;
+use crate::chess_state_v2::ChessStateV2;
+use simple_chess::chess_game_state_analyzer::GameState;
+use simple_chess::codec::forsyth_edwards_notation::encode_game_as_string;
+use simple_chess::game_board::Board;
+use simple_chess::piece::{ChessPiece, PieceType};
+use simple_chess::{ChessGame, ChessMoveType, Color};
+
+/// # Chess Evaluation
+///
+/// Scores a position for [`HeuristicSearch`](super::HeuristicSearch) without searching any
+/// further. Scores are in pawns for the player to move, so a position worth 2.0 to white is worth
+/// -2.0 when it's black's move. Keep them well under
+/// [`MATE_SCORE`](super::MATE_SCORE) so they're never mistaken for a mate.
+pub trait ChessEvaluation {
+    fn evaluate(&self, game: &ChessGame) -> f64;
+}
+
+/// A pawn is worth 1, knights and bishops 3, rooks 5 and queens 9, the king isn't counted.
+pub fn get_piece_value(piece_type: PieceType) -> f64 {
+    match piece_type {
+        PieceType::Pawn => 1.0,
+        PieceType::Rook => 5.0,
+        PieceType::Knight => 3.0,
+        PieceType::Bishop => 3.0,
+        PieceType::Queen => 9.0,
+        PieceType::King => 0.0,
+    }
+}
+
+/// Only counts the material each player has.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialEvaluation;
+
+impl ChessEvaluation for MaterialEvaluation {
+    fn evaluate(&self, game: &ChessGame) -> f64 {
+        let board = game.get_board();
+        let white_score = get_pieces(board)
+            .map(|(_, piece)| {
+                get_color_sign(piece.get_color()) * get_piece_value(piece.get_piece_type())
+            })
+            .sum::<f64>();
+        for_player_to_move(game, white_score)
+    }
+}
+
+/*
+Piece square tables in centipawns, from white's side of the board so the first row is rank 8 and
+the last rank 1. Black pieces look them up mirrored.
+ */
+#[rustfmt::skip]
+const PAWN_SQUARES: [i32; 64] = [
+     0,  0,   0,   0,   0,   0,  0,  0,
+    50, 50,  50,  50,  50,  50, 50, 50,
+    10, 10,  20,  30,  30,  20, 10, 10,
+     5,  5,  10,  25,  25,  10,  5,  5,
+     0,  0,   0,  20,  20,   0,  0,  0,
+     5, -5, -10,   0,   0, -10, -5,  5,
+     5, 10,  10, -20, -20,  10, 10,  5,
+     0,  0,   0,   0,   0,   0,  0,  0,
+];
+#[rustfmt::skip]
+const KNIGHT_SQUARES: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+#[rustfmt::skip]
+const BISHOP_SQUARES: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+#[rustfmt::skip]
+const ROOK_SQUARES: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+#[rustfmt::skip]
+const QUEEN_SQUARES: [i32; 64] = [
+    -20, -10, -10, -5, -5, -10, -10, -20,
+    -10,   0,   0,  0,  0,   0,   0, -10,
+    -10,   0,   5,  5,  5,   5,   0, -10,
+     -5,   0,   5,  5,  5,   5,   0,  -5,
+      0,   0,   5,  5,  5,   5,   0,  -5,
+    -10,   5,   5,  5,  5,   5,   0, -10,
+    -10,   0,   5,  0,  0,   0,   0, -10,
+    -20, -10, -10, -5, -5, -10, -10, -20,
+];
+#[rustfmt::skip]
+const KING_SQUARES: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+/// per square a knight, bishop, rook or queen could move to
+const MOBILITY_BONUS: f64 = 0.05;
+/// per pawn on the three squares in front of the king
+const PAWN_SHIELD_BONUS: f64 = 0.15;
+/// per move the other player has onto, or next to, the king
+const KING_ATTACK_PENALTY: f64 = 0.05;
+/// per pawn on a file that already has one of the player's pawns
+const DOUBLED_PAWN_PENALTY: f64 = 0.2;
+/// per pawn with none of the player's pawns on the files either side
+const ISOLATED_PAWN_PENALTY: f64 = 0.15;
+/// per rank a pawn with no other player's pawns in front of it, or either side in front, has
+/// moved up
+const PASSED_PAWN_BONUS: f64 = 0.1;
+
+/// # Positional Evaluation
+///
+/// Material plus where the pieces stand, each term scaled by its weight so terms can be turned
+/// down, or off with 0.0. The default weights are all 1.0.
+///
+/// - `piece_squares` rewards pieces on good squares, knights in the middle, pawns pushed on and
+///   the king tucked away in a corner
+/// - `mobility` counts the squares each knight, bishop, rook and queen can reach
+/// - `king_safety` rewards pawns sheltering the king and penalises the other player's moves
+///   onto the squares around it
+/// - `pawn_structure` penalises doubled and isolated pawns and rewards passed pawns the further
+///   they've gone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionalEvaluation {
+    pub material: f64,
+    pub piece_squares: f64,
+    pub mobility: f64,
+    pub king_safety: f64,
+    pub pawn_structure: f64,
+}
+
+impl Default for PositionalEvaluation {
+    fn default() -> Self {
+        Self {
+            material: 1.0,
+            piece_squares: 1.0,
+            mobility: 1.0,
+            king_safety: 1.0,
+            pawn_structure: 1.0,
+        }
+    }
+}
+
+impl ChessEvaluation for PositionalEvaluation {
+    fn evaluate(&self, game: &ChessGame) -> f64 {
+        let board = game.get_board();
+        let white_score = [Color::White, Color::Black]
+            .into_iter()
+            .map(|color| {
+                let score = self.material * get_material(board, color)
+                    + self.piece_squares * get_piece_square_score(board, color)
+                    + self.mobility * get_mobility(board, color)
+                    + self.king_safety * get_king_safety(board, color)
+                    + self.pawn_structure * get_pawn_structure(board, color);
+                get_color_sign(color) * score
+            })
+            .sum::<f64>();
+        for_player_to_move(game, white_score)
+    }
+}
+
+fn get_material(board: &Board<ChessPiece>, color: Color) -> f64 {
+    get_pieces(board)
+        .filter(|(_, piece)| piece.get_color() == color)
+        .map(|(_, piece)| get_piece_value(piece.get_piece_type()))
+        .sum()
+}
+
+fn get_piece_square_score(board: &Board<ChessPiece>, color: Color) -> f64 {
+    get_pieces(board)
+        .filter(|(_, piece)| piece.get_color() == color)
+        .map(|((col, row), piece)| {
+            let table = match piece.get_piece_type() {
+                PieceType::Pawn => &PAWN_SQUARES,
+                PieceType::Knight => &KNIGHT_SQUARES,
+                PieceType::Bishop => &BISHOP_SQUARES,
+                PieceType::Rook => &ROOK_SQUARES,
+                PieceType::Queen => &QUEEN_SQUARES,
+                PieceType::King => &KING_SQUARES,
+            };
+            // row 0 is rank 1, which is the last row of the tables for white
+            let table_row = match color {
+                Color::White => 7 - row,
+                Color::Black => row,
+            };
+            table[table_row * 8 + col] as f64 / 100.0
+        })
+        .sum()
+}
+
+fn get_mobility(board: &Board<ChessPiece>, color: Color) -> f64 {
+    get_pieces(board)
+        .filter(|(_, piece)| {
+            piece.get_color() == color
+                && !matches!(piece.get_piece_type(), PieceType::Pawn | PieceType::King)
+        })
+        .map(|(position, piece)| piece.possible_moves(position, board, None).len() as f64)
+        .sum::<f64>()
+        * MOBILITY_BONUS
+}
+
+fn get_king_safety(board: &Board<ChessPiece>, color: Color) -> f64 {
+    let king_position = get_pieces(board)
+        .find(|(_, piece)| piece.get_color() == color && piece.get_piece_type() == PieceType::King)
+        .map(|(position, _)| position);
+    let (king_col, king_row) = match king_position {
+        Some(position) => position,
+        None => return 0.0,
+    };
+
+    // a king on the far back rank has no row in front of it to shield it
+    let shield_row = match color {
+        Color::White => Some(king_row + 1),
+        Color::Black => king_row.checked_sub(1),
+    }
+    .filter(|row| *row < board.get_height());
+    let shield = match shield_row {
+        Some(shield_row) => {
+            (king_col.saturating_sub(1)..=(king_col + 1).min(board.get_width() - 1))
+                .filter(|col| {
+                    board
+                        .get_piece_at_space(*col, shield_row)
+                        .is_some_and(|piece| {
+                            piece.get_color() == color && piece.get_piece_type() == PieceType::Pawn
+                        })
+                })
+                .count() as f64
+        }
+        None => 0.0,
+    };
+
+    let attacks = get_pieces(board)
+        .filter(|(_, piece)| piece.get_color() != color)
+        .flat_map(|(position, piece)| piece.possible_moves(position, board, None))
+        .filter_map(|chess_move| match chess_move {
+            ChessMoveType::Move { new_position, .. } => Some(new_position),
+            ChessMoveType::EnPassant { new_position, .. } => Some(new_position),
+            ChessMoveType::Castle { .. } => None,
+        })
+        .filter(|(col, row)| col.abs_diff(king_col) <= 1 && row.abs_diff(king_row) <= 1)
+        .count() as f64;
+
+    shield * PAWN_SHIELD_BONUS - attacks * KING_ATTACK_PENALTY
+}
+
+fn get_pawn_structure(board: &Board<ChessPiece>, color: Color) -> f64 {
+    let pawns_of = |pawn_color: Color| {
+        get_pieces(board)
+            .filter(move |(_, piece)| {
+                piece.get_color() == pawn_color && piece.get_piece_type() == PieceType::Pawn
+            })
+            .map(|(position, _)| position)
+            .collect::<Vec<(usize, usize)>>()
+    };
+    let pawns = pawns_of(color);
+    let other_pawns = pawns_of(color.opposite());
+
+    let mut files = [0usize; 8];
+    pawns.iter().for_each(|(col, _)| files[*col] += 1);
+    let doubled = files
+        .iter()
+        .map(|count| count.saturating_sub(1))
+        .sum::<usize>() as f64;
+    let isolated = pawns
+        .iter()
+        .filter(|(col, _)| {
+            (col.saturating_sub(1)..=(col + 1).min(7)).all(|file| file == *col || files[file] == 0)
+        })
+        .count() as f64;
+
+    // ranks moved up from the pawn's starting rank
+    let advance = |row: usize| match color {
+        Color::White => row.saturating_sub(1),
+        Color::Black => 6usize.saturating_sub(row),
+    };
+    let is_in_front = |row: usize, other_row: usize| match color {
+        Color::White => other_row > row,
+        Color::Black => other_row < row,
+    };
+    let passed = pawns
+        .iter()
+        .filter(|(col, row)| {
+            !other_pawns.iter().any(|(other_col, other_row)| {
+                other_col.abs_diff(*col) <= 1 && is_in_front(*row, *other_row)
+            })
+        })
+        .map(|(_, row)| advance(*row) as f64)
+        .sum::<f64>();
+
+    passed * PASSED_PAWN_BONUS - doubled * DOUBLED_PAWN_PENALTY - isolated * ISOLATED_PAWN_PENALTY
+}
+
+/// # Model Evaluation
+///
+/// Scores positions with a trained [`Model`] that takes [`ChessStateV2`]'s 790 values and
+/// outputs a single value, like the model [`NStepTD`](crate::attempts_at_framework::v2::agent::n_step_td::NStepTD)
+/// learns in the chess app. The model values positions for one player, `color`, and the score is
+/// turned around when it's the other player's move.
+pub struct ModelEvaluation {
+    model: Model,
+    color: Color,
+}
+
+impl ModelEvaluation {
+    pub fn new(model: Model, color: Color) -> Self {
+        Self { model, color }
+    }
+
+    pub fn get_model(&self) -> &Model {
+        &self.model
+    }
+}
+
+impl ChessEvaluation for ModelEvaluation {
+    fn evaluate(&self, game: &ChessGame) -> f64 {
+        let state = ChessStateV2::new(encode_game_as_string(game), pick_first_move);
+        let value = self.model.predict(state.get_values())[0];
+        if game.get_current_players_turn() == self.color {
+            value
+        } else {
+            -value
+        }
+    }
+}
+
+/// The state only needs some way to pick the other player's moves, none are ever taken.
+fn pick_first_move(game: &mut ChessGame) -> ChessMoveType {
+    match game.get_game_state() {
+        GameState::InProgress { legal_moves, .. } => legal_moves[0],
+        GameState::Check { legal_moves, .. } => legal_moves[0],
+        GameState::Checkmate { .. } | GameState::Stalemate => {
+            panic!("the game is over, there is no move to pick")
+        }
+    }
+}
+
+/// Every piece on the board with its `(column, row)`.
+fn get_pieces(board: &Board<ChessPiece>) -> impl Iterator<Item = ((usize, usize), &ChessPiece)> {
+    (0..board.get_height())
+        .flat_map(move |row| (0..board.get_width()).map(move |col| (col, row)))
+        .filter_map(move |(col, row)| {
+            board
+                .get_piece_at_space(col, row)
+                .map(|piece| ((col, row), piece))
+        })
+}
+
+fn get_color_sign(color: Color) -> f64 {
+    match color {
+        Color::White => 1.0,
+        Color::Black => -1.0,
+    }
+}
+
+fn for_player_to_move(game: &ChessGame, white_score: f64) -> f64 {
+    get_color_sign(game.get_current_players_turn()) * white_score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v1::agent::{HeuristicSearch, SearchBudget};
+    use crate::attempts_at_framework::v2::artificial_neural_network::initialization::Initialization;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+    use simple_chess::codec::forsyth_edwards_notation::build_game_from_string;
+
+    fn evaluate(evaluation: &dyn ChessEvaluation, fen: &str) -> f64 {
+        evaluation.evaluate(&build_game_from_string(fen).unwrap())
+    }
+
+    #[test]
+    fn the_starting_position_is_even() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(evaluate(&MaterialEvaluation, start), 0.0);
+        assert!(evaluate(&PositionalEvaluation::default(), start).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scores_are_for_the_player_to_move() {
+        // white is a rook up
+        let white_to_move = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1";
+        let black_to_move = "4k3/8/8/8/8/8/8/R3K3 b - - 0 1";
+        assert_eq!(evaluate(&MaterialEvaluation, white_to_move), 5.0);
+        assert_eq!(evaluate(&MaterialEvaluation, black_to_move), -5.0);
+        let positional = PositionalEvaluation::default();
+        assert_eq!(
+            evaluate(&positional, white_to_move),
+            -evaluate(&positional, black_to_move)
+        );
+    }
+
+    #[test]
+    fn each_term_prefers_the_better_position() {
+        let only = |set: fn(&mut PositionalEvaluation)| {
+            let mut evaluation = PositionalEvaluation {
+                material: 0.0,
+                piece_squares: 0.0,
+                mobility: 0.0,
+                king_safety: 0.0,
+                pawn_structure: 0.0,
+            };
+            set(&mut evaluation);
+            evaluation
+        };
+
+        // a knight in the middle against one in the corner
+        let knights = "n3k3/8/8/8/3N4/8/8/4K3 w - - 0 1";
+        assert!(evaluate(&only(|e| e.piece_squares = 1.0), knights) > 0.0);
+        assert!(evaluate(&only(|e| e.mobility = 1.0), knights) > 0.0);
+
+        // white's king behind its pawns, black's out in the open facing a rook
+        let kings = "r5k1/8/8/8/8/8/5PPP/6K1 w - - 0 1";
+        assert!(evaluate(&only(|e| e.king_safety = 1.0), kings) > 0.0);
+
+        // white's pawn on e5 is passed, black's are doubled and isolated
+        let pawns = "4k3/2p5/2p5/4P3/8/8/8/4K3 w - - 0 1";
+        assert!(evaluate(&only(|e| e.pawn_structure = 1.0), pawns) > 0.0);
+    }
+
+    #[test]
+    fn kings_on_the_far_back_rank() {
+        // each king on the rank the other side started on, so nothing is in front of them
+        let white_to_move = "4K3/8/8/8/8/8/8/4k3 w - - 0 1";
+        let black_to_move = "4K3/8/8/8/8/8/8/4k3 b - - 0 1";
+        assert_eq!(
+            evaluate(&PositionalEvaluation::default(), white_to_move),
+            0.0
+        );
+        assert_eq!(
+            evaluate(&PositionalEvaluation::default(), black_to_move),
+            0.0
+        );
+        [white_to_move, black_to_move].iter().for_each(|fen| {
+            let game = build_game_from_string(fen).unwrap();
+            assert!(HeuristicSearch::new(SearchBudget::new(1))
+                .search(&game)
+                .is_some());
+        });
+    }
+
+    #[test]
+    fn model_scores_for_its_color() {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(790)
+            .add_layer(
+                LayerBuilder::new(LayerType::LINEAR, 1).set_initialization(Initialization::Zeros),
+            );
+        let mut model = builder.build().unwrap();
+        let mut step = vec![0.0; 791];
+        step[790] = 0.5;
+        model.add_to_parameters(&step);
+
+        let evaluation = ModelEvaluation::new(model, Color::White);
+        assert_eq!(evaluate(&evaluation, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1"), 0.5);
+        assert_eq!(
+            evaluate(&evaluation, "4k3/8/8/8/8/8/8/R3K3 b - - 0 1"),
+            -0.5
+        );
+    }
+}
@@ -1,100 +1,584 @@
-use rand::prelude::IteratorRandom;
+use super::chess_evaluation::{get_piece_value, ChessEvaluation, PositionalEvaluation};
+use crate::chess_state::get_state_id_from_fen_string;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{RngCore, SeedableRng};
 use simple_chess::chess_game_state_analyzer::GameState;
-use simple_chess::piece::{ChessPiece, PieceType};
+use simple_chess::codec::forsyth_edwards_notation::encode_game_as_string;
+use simple_chess::codec::long_algebraic_notation::encode_move_as_long_algebraic_notation;
 use simple_chess::{ChessGame, ChessMoveType, Color};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The score of being checkmated, less a point for every move it takes so quicker mates score
+/// higher. Anything within 100 of this is a forced mate.
+pub const MATE_SCORE: f64 = 1000.0;
+const MAX_SEARCH_PLY: usize = 100;
+/// positions, a few hundred bytes each
+pub const DEFAULT_TABLE_SIZE_LIMIT: usize = 1 << 20;
 
 pub fn get_best_action_heuristic_search(game: &mut ChessGame, depth: usize) -> String {
-    println!("selecting move with a heuristic depth of: {}", depth);
+    get_best_action_heuristic_search_with_rng(game, depth, &mut rand::rng())
+}
 
-    let player_color = game.get_current_players_turn();
+/// Same as [`get_best_action_heuristic_search`] but ties between equally good moves are broken
+/// using `rng`.
+pub fn get_best_action_heuristic_search_with_rng(
+    game: &mut ChessGame,
+    depth: usize,
+    rng: &mut dyn RngCore,
+) -> String {
+    let mut search = HeuristicSearch::new(SearchBudget::new(depth));
+    search.set_seed(rng.next_u64());
+    search
+        .search(game)
+        .expect("the game is already over")
+        .best_move
+}
 
-    let idk = idk(game, depth, player_color);
-    println!("moves and values: {:?}", idk);
+/// How much searching [`HeuristicSearch::search`] is allowed to do. Each depth is searched in
+/// turn, and once the time or node limit runs out the deepest finished search is used. The first
+/// depth always finishes, however small the limits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchBudget {
+    /// in plies, one move by one player
+    pub max_depth: usize,
+    pub time_limit: Option<Duration>,
+    pub node_limit: Option<usize>,
+}
 
-    let max_value = idk.iter().map(|x| x.0).fold(f64::MIN, f64::max);
-    let best_moves: Vec<_> = idk.iter().filter(|x| x.0 == max_value).cloned().collect();
+impl SearchBudget {
+    /// Searches to `max_depth` however long it takes.
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            time_limit: None,
+            node_limit: None,
+        }
+    }
+}
 
-    best_moves
-        .iter()
-        .choose(&mut rand::rng())
-        .unwrap()
-        .1
-        .clone()
+/// What [`HeuristicSearch::search`] found, scores are in pawns for the player to move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// in long algebraic notation, the same as [`ChessStateV2`](crate::chess_state_v2::ChessStateV2)'s actions
+    pub best_move: String,
+    pub score: f64,
+    /// the deepest search that finished
+    pub depth: usize,
+    /// The moves both players are expected to make, starting with `best_move`. Can be shorter
+    /// than `depth` when the game ends sooner.
+    pub principal_variation: Vec<String>,
+    /// positions visited across every depth
+    pub nodes: usize,
 }
 
-fn idk(game: &mut ChessGame, depth: usize, player_color: Color) -> Vec<(f64, String)> {
-    if depth == 0 {
-        return vec![(0.0, String::new())];
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    Exact,
+    /// the score is at least this, the search was cut off
+    Lower,
+    /// the score is at most this, no move reached alpha
+    Upper,
+}
+
+#[derive(Debug, Clone)]
+struct TranspositionEntry {
+    depth: usize,
+    score: f64,
+    bound: Bound,
+    best_move: Option<String>,
+}
+
+/// # Heuristic Search
+///
+/// Negamax with alpha-beta pruning, searched one depth deeper at a time until the
+/// [`SearchBudget`] runs out. Captures are searched first, most valuable victim then least
+/// valuable attacker, after the best move from the last depth. Positions at the end of the search
+/// are scored by a [`ChessEvaluation`], [`PositionalEvaluation`] unless
+/// [`HeuristicSearch::set_evaluation`] says otherwise.
+///
+/// Positions already searched are kept in a transposition table keyed by the state id from
+/// [`get_state_id_from_fen_string`], so a position reached by a different order of moves isn't
+/// searched again. The table lasts between searches, call [`HeuristicSearch::clear`] to start
+/// fresh. It holds at most [`DEFAULT_TABLE_SIZE_LIMIT`] positions, or whatever
+/// [`HeuristicSearch::set_table_size_limit`] says, and is emptied when it fills up.
+pub struct HeuristicSearch {
+    budget: SearchBudget,
+    transposition_table: HashMap<String, TranspositionEntry>,
+    table_size_limit: usize,
+    nodes: usize,
+    deadline: Option<Instant>,
+    /// off while searching the first depth, so there's always a move
+    enforce_budget: bool,
+    out_of_budget: bool,
+    rng: StdRng,
+    evaluation: Box<dyn ChessEvaluation>,
+}
+
+impl HeuristicSearch {
+    pub fn new(budget: SearchBudget) -> Self {
+        Self {
+            budget,
+            transposition_table: HashMap::new(),
+            table_size_limit: DEFAULT_TABLE_SIZE_LIMIT,
+            nodes: 0,
+            deadline: None,
+            enforce_budget: false,
+            out_of_budget: false,
+            rng: StdRng::from_os_rng(),
+            evaluation: Box::new(PositionalEvaluation::default()),
+        }
     }
 
-    let moves = match game.get_game_state() {
-        GameState::InProgress { legal_moves, .. } => legal_moves,
-        GameState::Check { legal_moves, .. } => legal_moves,
-        GameState::Checkmate { .. } => Vec::new(),
-        GameState::Stalemate => Vec::new(),
-    };
+    /// Seeds how ties between equally good moves are broken.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
 
-    moves.iter().map(|m| {
-        let move_as_string = simple_chess::codec::long_algebraic_notation::encode_move_as_long_algebraic_notation(&m);
-        let mut take_reward= match m {
-            ChessMoveType::Move { taken_piece, .. } => get_taken_piece_reward(taken_piece),
-            ChessMoveType::EnPassant { .. } => 1.0,
-            ChessMoveType::Castle { .. } => 0.0,
-        };
+    /// Scores in the transposition table came from the old evaluation, so it's cleared.
+    pub fn set_evaluation(&mut self, evaluation: Box<dyn ChessEvaluation>) {
+        self.evaluation = evaluation;
+        self.clear();
+    }
+
+    pub fn set_budget(&mut self, budget: SearchBudget) {
+        self.budget = budget;
+    }
+
+    pub fn get_budget(&self) -> SearchBudget {
+        self.budget
+    }
+
+    /// The most positions the transposition table keeps, at least 1.
+    pub fn set_table_size_limit(&mut self, table_size_limit: usize) {
+        self.table_size_limit = table_size_limit.max(1);
+        if self.transposition_table.len() > self.table_size_limit {
+            self.clear();
+        }
+    }
+
+    pub fn get_table_size_limit(&self) -> usize {
+        self.table_size_limit
+    }
+
+    /// Forgets every position searched so far.
+    pub fn clear(&mut self) {
+        self.transposition_table.clear();
+    }
 
-        if game.get_current_players_turn() != player_color {
-            take_reward *= -1.0;
+    /// Finds the best move for the player to move, `None` when the game is already over.
+    pub fn search(&mut self, game: &ChessGame) -> Option<SearchResult> {
+        let mut game = game.clone();
+        let game = &mut game;
+        let root_moves = get_legal_moves(game);
+        if root_moves.is_empty() {
+            return None;
         }
 
-        game.make_move(*m);
-
-        let continuing_reward = match game.get_game_state() {
-            GameState::InProgress { .. } => {
-                let v = idk(game, depth - 1, player_color);
-                let x = if player_color == game.get_current_players_turn() {
-                    f64::max
-                } else {
-                    f64::min
-                };
-                v.iter().map(|a|a.0).reduce(x).unwrap()
+        self.nodes = 0;
+        self.out_of_budget = false;
+        self.deadline = self
+            .budget
+            .time_limit
+            .map(|time_limit| Instant::now() + time_limit);
+
+        let mut result = None;
+        for depth in 1..=self.budget.max_depth.max(1) {
+            self.enforce_budget = depth > 1;
+            let score = self.search_root(game, depth);
+            // a depth that was cut short is thrown away
+            if self.out_of_budget {
+                break;
             }
-            GameState::Check { .. } => {
-                let mut v = idk(game, depth - 1, player_color);
-                let x = if player_color == game.get_current_players_turn() {
-                    f64::max
-                } else {
-                    f64::min
-                };
-                v.iter().map(|a|a.0).reduce(x).unwrap()
+            let principal_variation = self.get_principal_variation(game, depth);
+            let best_move = principal_variation.first().cloned().unwrap_or_else(|| {
+                encode_move_as_long_algebraic_notation(&order_moves(root_moves.clone(), None)[0])
+            });
+            result = Some(SearchResult {
+                best_move,
+                score,
+                depth,
+                principal_variation,
+                nodes: self.nodes,
+            });
+            if score.abs() >= MATE_SCORE - MAX_SEARCH_PLY as f64 {
+                break;
+            }
+        }
+        result.map(|result| SearchResult {
+            nodes: self.nodes,
+            ..result
+        })
+    }
+
+    /// Searches every root move, in a random order before sorting so ties are broken randomly,
+    /// and stores the best in the transposition table.
+    fn search_root(&mut self, game: &mut ChessGame, depth: usize) -> f64 {
+        let key = get_position_key(game);
+        let mut moves = get_legal_moves(game);
+        moves.shuffle(&mut self.rng);
+        let previous_best = self
+            .transposition_table
+            .get(&key)
+            .and_then(|entry| entry.best_move.clone());
+        let moves = order_moves(moves, previous_best.as_deref());
+
+        let mut alpha = f64::NEG_INFINITY;
+        let mut best_move = None;
+        for chess_move in moves {
+            let score = -self.negamax(
+                &mut make_move(game, chess_move),
+                depth - 1,
+                1,
+                f64::NEG_INFINITY,
+                -alpha,
+            );
+            if self.out_of_budget {
+                break;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(encode_move_as_long_algebraic_notation(&chess_move));
+            }
+        }
+
+        if !self.out_of_budget {
+            self.store(
+                key,
+                TranspositionEntry {
+                    depth,
+                    score: alpha,
+                    bound: Bound::Exact,
+                    best_move,
+                },
+            );
+        } else if let Some(best_move) = best_move {
+            // only the move is kept, so the next search still tries it first
+            match self.transposition_table.get_mut(&key) {
+                Some(entry) => entry.best_move = Some(best_move),
+                None => self.store(
+                    key,
+                    TranspositionEntry {
+                        depth: 0,
+                        score: alpha,
+                        bound: Bound::Exact,
+                        best_move: Some(best_move),
+                    },
+                ),
             }
-            GameState::Checkmate { .. } => {
-                if player_color == game.get_current_players_turn() {
-                    -1000.0
-                } else {
-                    1000.0
+        }
+        alpha
+    }
+
+    fn negamax(
+        &mut self,
+        game: &mut ChessGame,
+        depth: usize,
+        ply: usize,
+        mut alpha: f64,
+        beta: f64,
+    ) -> f64 {
+        self.nodes += 1;
+        if self.is_over_budget() {
+            self.out_of_budget = true;
+            return 0.0;
+        }
+
+        let moves = match game.get_game_state() {
+            GameState::InProgress { legal_moves, .. } => legal_moves,
+            GameState::Check { legal_moves, .. } => legal_moves,
+            GameState::Checkmate { .. } => return -(MATE_SCORE - ply as f64),
+            GameState::Stalemate => return 0.0,
+        };
+        if depth == 0 || ply >= MAX_SEARCH_PLY {
+            return self.evaluation.evaluate(game);
+        }
+
+        let key = get_position_key(game);
+        let original_alpha = alpha;
+        let previous_best = match self.transposition_table.get(&key) {
+            Some(entry) => {
+                if entry.depth >= depth {
+                    let score = score_from_table(entry.score, ply);
+                    match entry.bound {
+                        Bound::Exact => return score,
+                        Bound::Lower if score >= beta => return score,
+                        Bound::Upper if score <= alpha => return score,
+                        _ => {}
+                    }
                 }
-            },
-            GameState::Stalemate => 0.0
+                entry.best_move.clone()
+            }
+            None => None,
         };
 
-        game.undo_last_move();
+        let mut best_score = f64::NEG_INFINITY;
+        let mut best_move = None;
+        for chess_move in order_moves(moves, previous_best.as_deref()) {
+            let score = -self.negamax(
+                &mut make_move(game, chess_move),
+                depth - 1,
+                ply + 1,
+                -beta,
+                -alpha,
+            );
+            if self.out_of_budget {
+                return 0.0;
+            }
 
-        let total_reward = take_reward + continuing_reward;
-        (total_reward, move_as_string)
-    }).collect()
-}
+            if score > best_score {
+                best_score = score;
+                best_move = Some(encode_move_as_long_algebraic_notation(&chess_move));
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.store(
+            key,
+            TranspositionEntry {
+                depth,
+                score: score_to_table(best_score, ply),
+                bound,
+                best_move,
+            },
+        );
+        best_score
+    }
+
+    /// Empties the table once it's full rather than picking entries to drop, so storing stays
+    /// cheap.
+    fn store(&mut self, key: String, entry: TranspositionEntry) {
+        if self.transposition_table.len() >= self.table_size_limit
+            && !self.transposition_table.contains_key(&key)
+        {
+            self.clear();
+        }
+        self.transposition_table.insert(key, entry);
+    }
 
-fn get_taken_piece_reward(taken_piece: &Option<ChessPiece>) -> f64 {
-    if let Some(taken_piece) = taken_piece {
-        match taken_piece.get_piece_type() {
-            PieceType::Pawn => 1.0,
-            PieceType::Rook => 5.0,
-            PieceType::Knight => 3.0,
-            PieceType::Bishop => 3.0,
-            PieceType::Queen => 9.0,
-            _ => 0.0,
+    fn is_over_budget(&self) -> bool {
+        if !self.enforce_budget {
+            return false;
         }
+        self.out_of_budget
+            || self
+                .budget
+                .node_limit
+                .is_some_and(|node_limit| self.nodes > node_limit)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Follows the best moves kept in the transposition table from the current position.
+    fn get_principal_variation(&self, game: &ChessGame, depth: usize) -> Vec<String> {
+        let mut game = game.clone();
+        let game = &mut game;
+        let mut principal_variation = Vec::new();
+        while principal_variation.len() < depth {
+            let best_move = self
+                .transposition_table
+                .get(&get_position_key(game))
+                .and_then(|entry| entry.best_move.clone());
+            let chess_move = best_move.and_then(|best_move| {
+                get_legal_moves(game)
+                    .into_iter()
+                    .find(|m| encode_move_as_long_algebraic_notation(m) == best_move)
+            });
+            match chess_move {
+                Some(chess_move) => {
+                    principal_variation.push(encode_move_as_long_algebraic_notation(&chess_move));
+                    game.make_move(chess_move);
+                }
+                None => break,
+            }
+        }
+        principal_variation
+    }
+}
+
+/// A copy of `game` after `chess_move`. `ChessGame::undo_last_move` doesn't put back castling
+/// rights or the fifty move counter, so every move is made on a copy instead.
+fn make_move(game: &ChessGame, chess_move: ChessMoveType) -> ChessGame {
+    let mut game = game.clone();
+    game.make_move(chess_move);
+    game
+}
+
+/// Mate scores count moves from the root, but the table is shared between positions reached at
+/// different depths, so they're kept counting from the position itself.
+fn score_to_table(score: f64, ply: usize) -> f64 {
+    if score >= MATE_SCORE - MAX_SEARCH_PLY as f64 {
+        score + ply as f64
+    } else if score <= -(MATE_SCORE - MAX_SEARCH_PLY as f64) {
+        score - ply as f64
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: f64, ply: usize) -> f64 {
+    if score >= MATE_SCORE - MAX_SEARCH_PLY as f64 {
+        score - ply as f64
+    } else if score <= -(MATE_SCORE - MAX_SEARCH_PLY as f64) {
+        score + ply as f64
     } else {
-        0.0
+        score
+    }
+}
+
+/// The state id leaves out whose turn it is, which the score depends on.
+fn get_position_key(game: &ChessGame) -> String {
+    let turn = match game.get_current_players_turn() {
+        Color::White => "w",
+        Color::Black => "b",
+    };
+    format!(
+        "{}_{}",
+        get_state_id_from_fen_string(&encode_game_as_string(game)),
+        turn
+    )
+}
+
+fn get_legal_moves(game: &mut ChessGame) -> Vec<ChessMoveType> {
+    match game.get_game_state() {
+        GameState::InProgress { legal_moves, .. } => legal_moves,
+        GameState::Check { legal_moves, .. } => legal_moves,
+        GameState::Checkmate { .. } => Vec::new(),
+        GameState::Stalemate => Vec::new(),
+    }
+}
+
+/// `first` goes first, then captures and promotions, then everything else. The sort is stable so
+/// moves that are as good as each other keep their order.
+fn order_moves(mut moves: Vec<ChessMoveType>, first: Option<&str>) -> Vec<ChessMoveType> {
+    moves.sort_by_cached_key(|chess_move| {
+        if first.is_some_and(|first| encode_move_as_long_algebraic_notation(chess_move) == first) {
+            return i64::MIN;
+        }
+        let score = match chess_move {
+            ChessMoveType::Move {
+                piece,
+                taken_piece,
+                promotion,
+                ..
+            } => {
+                let capture = taken_piece.map_or(0.0, |taken_piece| {
+                    10.0 * get_piece_value(taken_piece.get_piece_type())
+                        - get_piece_value(piece.get_piece_type())
+                });
+                capture
+                    + promotion.map_or(0.0, |promotion| {
+                        10.0 * get_piece_value(promotion.get_piece_type())
+                    })
+            }
+            ChessMoveType::EnPassant { .. } => 9.0,
+            ChessMoveType::Castle { .. } => 0.0,
+        };
+        -(score as i64)
+    });
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v1::agent::MaterialEvaluation;
+    use simple_chess::codec::forsyth_edwards_notation::build_game_from_string;
+
+    fn search_position(fen: &str, budget: SearchBudget) -> SearchResult {
+        let game = build_game_from_string(fen).unwrap();
+        let mut search = HeuristicSearch::new(budget);
+        search.set_seed(1);
+        search.set_evaluation(Box::new(MaterialEvaluation));
+        search.search(&game).unwrap()
+    }
+
+    #[test]
+    fn takes_a_hanging_queen() {
+        let result = search_position("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", SearchBudget::new(2));
+        assert_eq!(result.best_move, "Rd2xd5");
+        assert_eq!(result.score, 5.0);
+        assert_eq!(result.depth, 2);
+        assert_eq!(result.principal_variation.len(), 2);
+        assert_eq!(result.principal_variation[0], "Rd2xd5");
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        // back rank mate
+        let result = search_position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", SearchBudget::new(3));
+        assert_eq!(result.best_move, "Ra1a8");
+        assert_eq!(result.score, MATE_SCORE - 1.0);
+        // the search stops once a mate is found
+        assert_eq!(result.depth, 1);
+    }
+
+    #[test]
+    fn sees_the_recapture() {
+        // the knight is defended, so taking it with the queen loses the queen for a knight
+        let fen = "4k3/8/4p3/3n4/8/8/8/3QK3 w - - 0 1";
+        let shallow = search_position(fen, SearchBudget::new(1));
+        assert_eq!(shallow.best_move, "Qd1xd5");
+        let deeper = search_position(fen, SearchBudget::new(2));
+        assert_ne!(deeper.best_move, "Qd1xd5");
+    }
+
+    #[test]
+    fn stops_at_the_node_limit() {
+        let budget = SearchBudget {
+            max_depth: 10,
+            time_limit: None,
+            node_limit: Some(500),
+        };
+        let result = search_position(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            budget,
+        );
+        assert!(result.depth < 10);
+        assert!(!result.best_move.is_empty());
+    }
+
+    #[test]
+    fn positional_evaluation_breaks_material_ties() {
+        // nothing to take, so the knight should head for the middle rather than the edge
+        let game = build_game_from_string("4k3/pppppppp/8/8/8/8/8/4K1N1 w - - 0 1").unwrap();
+        let mut search = HeuristicSearch::new(SearchBudget::new(1));
+        search.set_seed(1);
+        let result = search.search(&game).unwrap();
+        assert_eq!(result.best_move, "Ng1f3");
+    }
+
+    #[test]
+    fn transposition_table_stays_under_its_limit() {
+        let game = build_game_from_string("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut search = HeuristicSearch::new(SearchBudget::new(3));
+        search.set_seed(1);
+        search.set_evaluation(Box::new(MaterialEvaluation));
+        search.set_table_size_limit(50);
+        for _ in 0..3 {
+            let result = search.search(&game).unwrap();
+            assert_eq!(result.best_move, "Rd2xd5");
+            assert!(search.transposition_table.len() <= 50);
+        }
+    }
+
+    #[test]
+    fn finished_games_have_no_move() {
+        let game = build_game_from_string("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+        assert!(HeuristicSearch::new(SearchBudget::new(2))
+            .search(&game)
+            .is_none());
     }
 }
@@ -1,9 +1,17 @@
+mod chess_evaluation;
 mod heuristic_search;
+mod monte_carlo_tree_search;
 mod n_step_sarsa;
 mod q_learning;
 mod sarsa_0;
 
-pub use heuristic_search::get_best_action_heuristic_search;
+pub use chess_evaluation::{
+    get_piece_value, ChessEvaluation, MaterialEvaluation, ModelEvaluation, PositionalEvaluation,
+};
+pub use heuristic_search::{
+    get_best_action_heuristic_search, HeuristicSearch, SearchBudget, SearchResult, MATE_SCORE,
+};
+pub use monte_carlo_tree_search::{ActionStatistics, MonteCarloTreeSearch, RolloutPolicy};
 pub use n_step_sarsa::NStepSarsa;
 pub use q_learning::QLearning;
 pub use sarsa_0::SarsaZero;
@@ -0,0 +1,498 @@
+use crate::attempts_at_framework::v1::state::State;
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+use rand::distr::weighted::WeightedIndex;
+use rand::distr::Distribution;
+use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::HashMap;
+
+/// How the value of a newly expanded state is estimated when there's no value network.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RolloutPolicy {
+    /// every action picked uniformly at random
+    Random,
+    /// Actions sampled from the policy network's probabilities, random when there's no policy
+    /// network.
+    PolicyNetwork,
+}
+
+/// What [`MonteCarloTreeSearch::search`] learnt about one of the starting state's actions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionStatistics {
+    pub action: String,
+    pub visits: usize,
+    /// the average return of the simulations that started with this action
+    pub value: f64,
+    /// from the policy network, 1 / the number of actions without one
+    pub prior: f64,
+}
+
+struct Edge {
+    action: String,
+    prior: f64,
+    visits: usize,
+    total_value: f64,
+    /// the node of every state taking the action has led to, by state id, more than one when
+    /// the state's `take_action` is random
+    children: HashMap<String, usize>,
+}
+
+impl Edge {
+    fn get_value(&self) -> f64 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.total_value / self.visits as f64
+        }
+    }
+}
+
+#[derive(Default)]
+struct Node {
+    visits: usize,
+    /// empty until the node is expanded, or when the state has no actions
+    edges: Vec<Edge>,
+    is_expanded: bool,
+}
+
+/// A [`Model`] and the function that turns a state into its inputs.
+struct Network<S> {
+    model: Model,
+    get_features: fn(&S) -> Vec<f64>,
+}
+
+/// # Monte Carlo Tree Search
+///
+/// Plans from a single state by running simulations, each one:
+///
+/// 1. selects actions down the tree so far with UCT, or PUCT when there's a policy network
+/// 2. expands the first state it reaches that isn't in the tree yet
+/// 3. estimates that state's value with the value network, or by playing it out with the
+///    [`RolloutPolicy`] for up to `max_rollout_depth` actions
+/// 4. backs the discounted return up through every action it took
+///
+/// Works for any v1 [`State`], including ones where taking an action doesn't always lead to the
+/// same state, like a [`ChessState`](crate::chess_state::ChessState) with its random opponent.
+/// Each state an action has led to gets its own node. Returns are from the point of view of
+/// whoever is taking the actions, so higher is always better.
+///
+/// The networks are optional. The value network has a single output, the expected return from
+/// a state. The policy network has an output per action, in the order of `get_actions`, like a
+/// [`MultiHead`](crate::attempts_at_framework::v2::agent::action_values::ActionValueModel::MultiHead)
+/// model, softmaxed into the prior probability of picking each action. v1 states don't describe
+/// themselves as values, so each network comes with a function that does, for a v2 state that's
+/// just its `get_values`.
+pub struct MonteCarloTreeSearch<S: State> {
+    simulation_count: usize,
+    exploration_constant: f64,
+    discount_rate: f64,
+    max_rollout_depth: usize,
+    rollout_policy: RolloutPolicy,
+    value_network: Option<Network<S>>,
+    policy_network: Option<Network<S>>,
+    nodes: Vec<Node>,
+    rng: StdRng,
+}
+
+impl<S: State> MonteCarloTreeSearch<S> {
+    pub fn new(simulation_count: usize) -> Self {
+        if simulation_count == 0 {
+            panic!("Simulation count must be at least 1")
+        }
+
+        Self {
+            simulation_count,
+            exploration_constant: 2.0_f64.sqrt(),
+            discount_rate: 1.0,
+            max_rollout_depth: 200,
+            rollout_policy: RolloutPolicy::Random,
+            value_network: None,
+            policy_network: None,
+            nodes: Vec::new(),
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// Seeds every random choice the search makes, the states' own randomness isn't covered.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn set_simulation_count(&mut self, simulation_count: usize) {
+        if simulation_count == 0 {
+            panic!("Simulation count must be at least 1")
+        }
+        self.simulation_count = simulation_count;
+    }
+
+    pub fn get_simulation_count(&self) -> usize {
+        self.simulation_count
+    }
+
+    /// How much less visited actions are favoured, the default is √2.
+    pub fn set_exploration_constant(&mut self, exploration_constant: f64) {
+        self.exploration_constant = exploration_constant;
+    }
+
+    pub fn set_discount_rate(&mut self, discount_rate: f64) {
+        if !(0.0..=1.0).contains(&discount_rate) {
+            panic!("Discount rate must be between 0.0 and 1.0")
+        }
+        self.discount_rate = discount_rate;
+    }
+
+    /// Rollouts that haven't finished after this many actions count as returning nothing more.
+    pub fn set_max_rollout_depth(&mut self, max_rollout_depth: usize) {
+        self.max_rollout_depth = max_rollout_depth;
+    }
+
+    pub fn set_rollout_policy(&mut self, rollout_policy: RolloutPolicy) {
+        self.rollout_policy = rollout_policy;
+    }
+
+    /// Newly expanded states are valued by `model` instead of a rollout.
+    pub fn set_value_network(&mut self, model: Model, get_features: fn(&S) -> Vec<f64>) {
+        self.value_network = Some(Network {
+            model,
+            get_features,
+        });
+    }
+
+    pub fn remove_value_network(&mut self) {
+        self.value_network = None;
+    }
+
+    pub fn set_policy_network(&mut self, model: Model, get_features: fn(&S) -> Vec<f64>) {
+        self.policy_network = Some(Network {
+            model,
+            get_features,
+        });
+    }
+
+    pub fn remove_policy_network(&mut self) {
+        self.policy_network = None;
+    }
+
+    /// The most visited action of `state`, `None` when it's terminal or has no actions.
+    pub fn select_action(&mut self, state: &S) -> Option<String> {
+        self.search(state)
+            .into_iter()
+            .next()
+            .map(|statistics| statistics.action)
+    }
+
+    /// Runs the simulations from `state` and returns every action's statistics, most visited
+    /// first. A new tree is grown for every search.
+    pub fn search(&mut self, state: &S) -> Vec<ActionStatistics> {
+        self.nodes = vec![Node::default()];
+        if state.is_terminal() || state.get_actions().is_empty() {
+            return Vec::new();
+        }
+
+        (0..self.simulation_count).for_each(|_| {
+            self.simulate(0, state.clone());
+        });
+
+        let mut statistics = self.nodes[0]
+            .edges
+            .iter()
+            .map(|edge| ActionStatistics {
+                action: edge.action.clone(),
+                visits: edge.visits,
+                value: edge.get_value(),
+                prior: edge.prior,
+            })
+            .collect::<Vec<ActionStatistics>>();
+        statistics.sort_by(|a, b| b.visits.cmp(&a.visits).then(b.value.total_cmp(&a.value)));
+        statistics
+    }
+
+    /// Runs one simulation on from `node_index`, which `state` is the state of, and returns the
+    /// discounted return from `state`.
+    fn simulate(&mut self, node_index: usize, state: S) -> f64 {
+        if state.is_terminal() {
+            self.nodes[node_index].visits += 1;
+            return 0.0;
+        }
+
+        if !self.nodes[node_index].is_expanded {
+            self.expand(node_index, &state);
+            self.nodes[node_index].visits += 1;
+            return self.estimate_value(&state);
+        }
+
+        let edge_index = match self.select_edge(node_index) {
+            Some(edge_index) => edge_index,
+            None => {
+                // a state with no actions that isn't terminal, nothing more can happen
+                self.nodes[node_index].visits += 1;
+                return 0.0;
+            }
+        };
+
+        let action = self.nodes[node_index].edges[edge_index].action.clone();
+        let (reward, next_state) = state.take_action(&action);
+        let next_node_index = match self.nodes[node_index].edges[edge_index]
+            .children
+            .get(&next_state.get_id())
+        {
+            Some(next_node_index) => *next_node_index,
+            None => {
+                self.nodes.push(Node::default());
+                let next_node_index = self.nodes.len() - 1;
+                self.nodes[node_index].edges[edge_index]
+                    .children
+                    .insert(next_state.get_id(), next_node_index);
+                next_node_index
+            }
+        };
+
+        let value = reward + self.discount_rate * self.simulate(next_node_index, next_state);
+
+        let node = &mut self.nodes[node_index];
+        node.visits += 1;
+        node.edges[edge_index].visits += 1;
+        node.edges[edge_index].total_value += value;
+        value
+    }
+
+    fn expand(&mut self, node_index: usize, state: &S) {
+        let mut actions = state.get_actions();
+        let priors = self.get_priors(state, actions.len());
+        let mut edges = actions
+            .drain(..)
+            .zip(priors)
+            .map(|(action, prior)| Edge {
+                action,
+                prior,
+                visits: 0,
+                total_value: 0.0,
+                children: HashMap::new(),
+            })
+            .collect::<Vec<Edge>>();
+        // so ties between actions that haven't been tried yet are broken at random
+        edges.shuffle(&mut self.rng);
+
+        let node = &mut self.nodes[node_index];
+        node.edges = edges;
+        node.is_expanded = true;
+    }
+
+    /// UCT picks every action once before using `Q + c √(ln N / n)`. With a policy network
+    /// it's PUCT, `Q + c P √N / (1 + n)`, so actions the network likes are tried first.
+    fn select_edge(&self, node_index: usize) -> Option<usize> {
+        let node = &self.nodes[node_index];
+        let parent_visits = node.visits.max(1) as f64;
+        let score = |edge: &Edge| {
+            if self.policy_network.is_some() {
+                edge.get_value()
+                    + self.exploration_constant * edge.prior * parent_visits.sqrt()
+                        / (1.0 + edge.visits as f64)
+            } else if edge.visits == 0 {
+                f64::INFINITY
+            } else {
+                edge.get_value()
+                    + self.exploration_constant * (parent_visits.ln() / edge.visits as f64).sqrt()
+            }
+        };
+
+        node.edges
+            .iter()
+            .enumerate()
+            .map(|(edge_index, edge)| (edge_index, score(edge)))
+            .fold(
+                None,
+                |best: Option<(usize, f64)>, (edge_index, score)| match best {
+                    Some((_, best_score)) if best_score >= score => best,
+                    _ => Some((edge_index, score)),
+                },
+            )
+            .map(|(edge_index, _)| edge_index)
+    }
+
+    /// The policy network's probabilities for the first `action_count` outputs, the same for
+    /// every action without one.
+    fn get_priors(&self, state: &S, action_count: usize) -> Vec<f64> {
+        let network = match &self.policy_network {
+            Some(network) => network,
+            None => return vec![1.0 / action_count as f64; action_count],
+        };
+
+        let outputs = network.model.predict((network.get_features)(state));
+        if action_count > outputs.len() {
+            panic!(
+                "state has {} actions but the policy network only has {} outputs",
+                action_count,
+                outputs.len()
+            );
+        }
+        let outputs = &outputs[..action_count];
+        let max_output = outputs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exponents = outputs
+            .iter()
+            .map(|output| (output - max_output).exp())
+            .collect::<Vec<f64>>();
+        let total = exponents.iter().sum::<f64>();
+        exponents.iter().map(|exponent| exponent / total).collect()
+    }
+
+    fn estimate_value(&mut self, state: &S) -> f64 {
+        match &self.value_network {
+            Some(network) => network.model.predict((network.get_features)(state))[0],
+            None => self.rollout(state.clone()),
+        }
+    }
+
+    fn rollout(&mut self, mut state: S) -> f64 {
+        let mut value = 0.0;
+        let mut discount = 1.0;
+        for _ in 0..self.max_rollout_depth {
+            if state.is_terminal() {
+                break;
+            }
+            let actions = state.get_actions();
+            let action = match self.rollout_policy {
+                RolloutPolicy::PolicyNetwork if self.policy_network.is_some() => {
+                    let priors = self.get_priors(&state, actions.len());
+                    match WeightedIndex::new(&priors) {
+                        Ok(distribution) => actions.get(distribution.sample(&mut self.rng)),
+                        Err(_) => actions.choose(&mut self.rng),
+                    }
+                }
+                _ => actions.choose(&mut self.rng),
+            };
+            let action = match action {
+                Some(action) => action,
+                None => break,
+            };
+
+            let (reward, next_state) = state.take_action(action);
+            value += discount * reward;
+            discount *= self.discount_rate;
+            state = next_state;
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::initialization::Initialization;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+    use crate::chess_state::ChessState;
+
+    /// Walk right to the end of the corridor for 1.0, walking off the left end gets -1.0.
+    #[derive(Debug, Clone)]
+    struct Corridor {
+        position: i32,
+    }
+
+    impl State for Corridor {
+        fn get_id(&self) -> String {
+            self.position.to_string()
+        }
+
+        fn get_actions(&self) -> Vec<String> {
+            vec!["left".to_string(), "right".to_string()]
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.position < 0 || self.position > 4
+        }
+
+        fn take_action(&self, action: &str) -> (f64, Self) {
+            let position = match action {
+                "left" => self.position - 1,
+                _ => self.position + 1,
+            };
+            let reward = match position {
+                -1 => -1.0,
+                5 => 1.0,
+                _ => 0.0,
+            };
+            (reward, Self { position })
+        }
+    }
+
+    fn get_corridor_features(state: &Corridor) -> Vec<f64> {
+        vec![state.position as f64]
+    }
+
+    /// a single linear layer with its weights and biases set to `parameters`
+    fn build_model(input_size: usize, parameters: &[f64]) -> Model {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(input_size)
+            .add_layer(
+                LayerBuilder::new(LayerType::LINEAR, parameters.len() / (input_size + 1))
+                    .set_initialization(Initialization::Zeros),
+            );
+        let mut model = builder.build().unwrap();
+        model.add_to_parameters(parameters);
+        model
+    }
+
+    #[test]
+    fn walks_down_the_corridor() {
+        let mut search = MonteCarloTreeSearch::new(200);
+        search.set_seed(1);
+        search.set_discount_rate(0.9);
+        let statistics = search.search(&Corridor { position: 1 });
+
+        assert_eq!(statistics.len(), 2);
+        assert_eq!(statistics[0].action, "right");
+        assert!(statistics[0].value > statistics[1].value);
+        assert_eq!(statistics.iter().map(|s| s.visits).sum::<usize>(), 200 - 1);
+    }
+
+    #[test]
+    fn terminal_states_have_no_action() {
+        let mut search = MonteCarloTreeSearch::new(10);
+        assert_eq!(search.select_action(&Corridor { position: 5 }), None);
+    }
+
+    #[test]
+    fn value_network_replaces_rollouts() {
+        // the value network says left is better, which is wrong, but with no rollouts the search
+        // only finds out once it reaches the ends
+        let mut search = MonteCarloTreeSearch::new(3);
+        search.set_seed(1);
+        search.set_value_network(build_model(1, &[-1.0, 0.0]), get_corridor_features);
+        let statistics = search.search(&Corridor { position: 2 });
+        let left = statistics.iter().find(|s| s.action == "left").unwrap();
+        let right = statistics.iter().find(|s| s.action == "right").unwrap();
+        assert_eq!(left.value, -1.0);
+        assert_eq!(right.value, -3.0);
+    }
+
+    #[test]
+    fn policy_network_sets_the_priors() {
+        // the bias makes right e times as likely as left
+        let mut search = MonteCarloTreeSearch::new(50);
+        search.set_seed(1);
+        search.set_policy_network(build_model(1, &[0.0, 0.0, 0.0, 1.0]), get_corridor_features);
+        search.set_rollout_policy(RolloutPolicy::PolicyNetwork);
+        let statistics = search.search(&Corridor { position: 2 });
+        let right = statistics.iter().find(|s| s.action == "right").unwrap();
+        let e = 1.0_f64.exp();
+        assert!((right.prior - e / (1.0 + e)).abs() < 1e-9);
+        assert_eq!(statistics[0].action, "right");
+    }
+
+    #[test]
+    fn takes_the_mate_in_one() {
+        // back rank mate against a random opponent
+        let state = ChessState::with_seed("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string(), 1);
+        let mut search = MonteCarloTreeSearch::new(300);
+        search.set_seed(1);
+        search.set_max_rollout_depth(10);
+        assert_eq!(search.select_action(&state), Some("Ra1a8".to_string()));
+    }
+}
@@ -1,19 +1,33 @@
+use crate::attempts_at_framework::environment::Environment;
+use crate::attempts_at_framework::v1::persistence::{
+    read_action_values, write_action_values, Persist, PersistenceError, TableReader, TableWriter,
+};
 use crate::attempts_at_framework::v1::policy::{EGreedyPolicy, Policy};
-use crate::attempts_at_framework::v1::state::State;
-use rand::prelude::{IndexedRandom, ThreadRng};
+use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
 
-pub struct NStepSarsa {
+pub struct NStepSarsa<S = String, A = String> {
     n: usize,
-    policy: EGreedyPolicy,
+    policy: EGreedyPolicy<S, A>,
     default_state_value: f64,
     step_size_parameter: f64,
     discount_rate: f64,
-    state_action_values: HashMap<String, f64>,
+    state_action_values: HashMap<(S, A), f64>,
     num_of_episodes_learned_for: usize,
+    rng: StdRng,
 }
 
-impl NStepSarsa {
+impl<S, A> NStepSarsa<S, A>
+where
+    S: Clone + Eq + Hash + Debug,
+    A: Clone + Eq + Hash + Debug,
+{
     pub fn new(n: usize, e: f64, step_size_parameter: f64, discount_rate: f64) -> Self {
         if step_size_parameter < 0.0 || step_size_parameter > 1.0 {
             panic!("Step size parameter must be between 0.0 and 1.0")
@@ -30,10 +44,17 @@ impl NStepSarsa {
             discount_rate,
             state_action_values: HashMap::new(),
             num_of_episodes_learned_for: 0,
+            rng: StdRng::from_os_rng(),
         }
     }
 
-    pub fn get_policy(&self) -> &EGreedyPolicy {
+    /// Seeds every random choice the agent makes, two agents with the same seed learning from
+    /// the same environments end up with the same values.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn get_policy(&self) -> &EGreedyPolicy<S, A> {
         &self.policy
     }
 
@@ -41,26 +62,27 @@ impl NStepSarsa {
         self.num_of_episodes_learned_for
     }
 
-    pub fn learn_for_episode_count<S: State>(
+    pub fn learn_for_episode_count<E: Environment<Observation = S, Action = A>>(
         &mut self,
         episode_count: usize,
-        starting_states: Vec<S>,
+        starting_states: Vec<E>,
     ) {
-        let mut rng = rand::rng();
-
         (0..episode_count).for_each(|_| {
-            let starting_state = starting_states.choose(&mut rng).unwrap().clone();
+            let starting_state = starting_states.choose(&mut self.rng).unwrap().clone();
             self.learn_from_episode(starting_state);
         });
     }
 
-    pub fn learn_from_episode<S: State>(&mut self, starting_state: S) {
+    pub fn learn_from_episode<E: Environment<Observation = S, Action = A>>(
+        &mut self,
+        starting_state: E,
+    ) {
         let mut terminal_time: Option<usize> = None;
         let mut current_state = starting_state;
         let mut current_action = self.pick_action_for_state_based_on_policy(&current_state);
-        let mut next_state: Option<S> = None;
-        let mut next_action: Option<String> = None;
-        let mut states_actions_and_rewards: Vec<(S, String, f64)> = Vec::new();
+        let mut next_state: Option<E> = None;
+        let mut next_action: Option<A> = None;
+        let mut states_actions_and_rewards: Vec<(E, A, f64)> = Vec::new();
         states_actions_and_rewards.push((current_state.clone(), current_action.clone(), 0.0));
 
         for time_step in 0..usize::MAX {
@@ -100,11 +122,7 @@ impl NStepSarsa {
                     let adjusted_discount_rate = self.discount_rate.powi(self.n as i32);
                     let (s, a, _) =
                         &states_actions_and_rewards[time_step_to_update as usize + self.n];
-                    let state_action_id = Self::get_state_action_id(&s.get_id(), &a);
-                    let state_value = self
-                        .state_action_values
-                        .get(&state_action_id)
-                        .unwrap_or(&self.default_state_value);
+                    let state_value = self.get_state_action_value(&s.get_observation(), a);
                     state_value * adjusted_discount_rate
                 } else {
                     0.0
@@ -114,39 +132,36 @@ impl NStepSarsa {
 
                 let (state_to_update, action_chosen, _) =
                     &states_actions_and_rewards[time_step_to_update as usize];
-                let state_id_to_update = state_to_update.get_id();
-                let state_action_id =
-                    Self::get_state_action_id(&state_id_to_update, &action_chosen);
-                let existing_value = self
-                    .state_action_values
-                    .get(&state_action_id)
-                    .unwrap_or(&self.default_state_value);
+                let observation_to_update = state_to_update.get_observation();
+                let existing_value =
+                    self.get_state_action_value(&observation_to_update, action_chosen);
                 let new_value =
                     existing_value + (self.step_size_parameter * (total_reward - existing_value));
-                self.state_action_values
-                    .insert(state_action_id.clone(), new_value);
+                self.state_action_values.insert(
+                    (observation_to_update.clone(), action_chosen.clone()),
+                    new_value,
+                );
 
-                let (_, best_action) = state_to_update.get_actions().iter().fold(
-                    (f64::MIN, String::new()),
+                let possible_actions = state_to_update.get_actions();
+                let (_, best_action) = possible_actions.iter().fold(
+                    (f64::MIN, None),
                     |(best_reward, best_action), action| {
-                        let possible_state_action_id =
-                            Self::get_state_action_id(&state_id_to_update, action);
-                        let possible_state_action_value = self
-                            .state_action_values
-                            .get(&possible_state_action_id)
-                            .unwrap_or(&self.default_state_value);
-                        if *possible_state_action_value > best_reward {
-                            (*possible_state_action_value, action.clone())
+                        let possible_state_action_value =
+                            self.get_state_action_value(&observation_to_update, action);
+                        if possible_state_action_value > best_reward {
+                            (possible_state_action_value, Some(action.clone()))
                         } else {
                             (best_reward, best_action)
                         }
                     },
                 );
-                self.policy.set_actions_for_state(
-                    state_id_to_update.clone(),
-                    state_to_update.get_actions().clone(),
-                    best_action,
-                );
+                if let Some(best_action) = best_action {
+                    self.policy.set_actions_for_state(
+                        observation_to_update,
+                        possible_actions,
+                        best_action,
+                    );
+                }
             }
 
             let terminal_time_as_i32 = match terminal_time {
@@ -165,17 +180,60 @@ impl NStepSarsa {
         self.num_of_episodes_learned_for += 1;
     }
 
-    fn get_state_action_id(state_id: &str, action: &str) -> String {
-        format!("{}_{}", state_id, action)
+    fn get_state_action_value(&self, observation: &S, action: &A) -> f64 {
+        *self
+            .state_action_values
+            .get(&(observation.clone(), action.clone()))
+            .unwrap_or(&self.default_state_value)
     }
 
-    fn pick_action_for_state_based_on_policy<S: State>(&self, current_state: &S) -> String {
-        match self.policy.select_action_for_state(&current_state.get_id()) {
+    fn pick_action_for_state_based_on_policy<E: Environment<Observation = S, Action = A>>(
+        &mut self,
+        current_state: &E,
+    ) -> A {
+        match self
+            .policy
+            .select_action_for_state_with_rng(&current_state.get_observation(), &mut self.rng)
+        {
             Ok(action) => action,
             Err(_) => {
                 let actions = current_state.get_actions();
-                actions.choose(&mut rand::rng()).unwrap().clone()
+                actions.choose(&mut self.rng).unwrap().clone()
             }
         }
     }
 }
+
+impl<S, A> Persist for NStepSarsa<S, A>
+where
+    S: Clone + Eq + Hash + Debug + Display + FromStr,
+    A: Clone + Eq + Hash + Debug + Display + FromStr,
+{
+    fn write_table<W: Write>(&self, writer: &mut TableWriter<W>) -> std::io::Result<()> {
+        writer.write_header("type", "n_step_sarsa")?;
+        writer.write_header("n", self.n)?;
+        writer.write_header("default_state_value", self.default_state_value)?;
+        writer.write_header("step_size_parameter", self.step_size_parameter)?;
+        writer.write_header("discount_rate", self.discount_rate)?;
+        writer.write_header(
+            "num_of_episodes_learned_for",
+            self.num_of_episodes_learned_for,
+        )?;
+        write_action_values(writer, &self.state_action_values)?;
+        self.policy.write_table(writer)
+    }
+
+    fn read_table<R: BufRead>(reader: &mut TableReader<R>) -> Result<Self, PersistenceError> {
+        reader.expect_type("n_step_sarsa")?;
+        Ok(Self {
+            n: reader.read_header("n")?,
+            default_state_value: reader.read_header("default_state_value")?,
+            step_size_parameter: reader.read_header("step_size_parameter")?,
+            discount_rate: reader.read_header("discount_rate")?,
+            num_of_episodes_learned_for: reader.read_header("num_of_episodes_learned_for")?,
+            state_action_values: read_action_values(reader)?,
+            policy: EGreedyPolicy::read_table(reader)?,
+            rng: StdRng::from_os_rng(),
+        })
+    }
+}
@@ -1,17 +1,31 @@
+use crate::attempts_at_framework::environment::Environment;
+use crate::attempts_at_framework::v1::persistence::{
+    read_action_values, write_action_values, Persist, PersistenceError, TableReader, TableWriter,
+};
 use crate::attempts_at_framework::v1::policy::{EGreedyPolicy, Policy};
-use crate::attempts_at_framework::v1::state::State;
-use rand::prelude::{IndexedRandom, ThreadRng};
+use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
 
-pub struct QLearning {
-    action_values: HashMap<String, f64>,
-    policy: EGreedyPolicy,
+pub struct QLearning<S = String, A = String> {
+    action_values: HashMap<(S, A), f64>,
+    policy: EGreedyPolicy<S, A>,
     default_action_value: f64,
     step_size_parameter: f64,
     discount_rate: f64,
+    rng: StdRng,
 }
 
-impl QLearning {
+impl<S, A> QLearning<S, A>
+where
+    S: Clone + Eq + Hash + Debug,
+    A: Clone + Eq + Hash + Debug,
+{
     pub fn new(e: f64, step_size_parameter: f64, discount_rate: f64) -> Self {
         if step_size_parameter < 0.0 || step_size_parameter > 1.0 {
             panic!("Step size parameter must be between 0.0 and 1.0")
@@ -26,22 +40,27 @@ impl QLearning {
             default_action_value: 0.0,
             step_size_parameter,
             discount_rate,
+            rng: StdRng::from_os_rng(),
         }
     }
 
-    pub fn get_policy(&self) -> &EGreedyPolicy {
+    /// Seeds every random choice the agent makes, two agents with the same seed learning from
+    /// the same environments end up with the same values.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn get_policy(&self) -> &EGreedyPolicy<S, A> {
         &self.policy
     }
 
-    pub fn learn_for_episode_count<S: State>(
+    pub fn learn_for_episode_count<E: Environment<Observation = S, Action = A>>(
         &mut self,
         episode_count: usize,
-        starting_states: Vec<S>,
+        starting_states: Vec<E>,
     ) {
-        let mut rng = rand::rng();
-
         (0..episode_count).for_each(|_| {
-            let mut state = starting_states.choose(&mut rng).unwrap().clone();
+            let mut state = starting_states.choose(&mut self.rng).unwrap().clone();
 
             while !state.is_terminal() {
                 let next_state = self.learn_for_single_state(&mut state);
@@ -50,12 +69,18 @@ impl QLearning {
         });
     }
 
-    pub fn learn_for_single_state<S: State>(&mut self, mut state: &mut S) -> S {
-        let mut rng = rand::rng();
+    pub fn learn_for_single_state<E: Environment<Observation = S, Action = A>>(
+        &mut self,
+        state: &mut E,
+    ) -> E {
+        let observation = state.get_observation();
 
-        let action = match self.policy.select_action_for_state(&state.get_id()) {
+        let action = match self
+            .policy
+            .select_action_for_state_with_rng(&observation, &mut self.rng)
+        {
             Ok(action) => action,
-            Err(_) => state.get_actions().choose(&mut rng).unwrap().clone(),
+            Err(_) => state.get_actions().choose(&mut self.rng).unwrap().clone(),
         };
 
         let (reward, next_state) = state.take_action(&action);
@@ -63,57 +88,125 @@ impl QLearning {
         let max_next_state_action_value = if next_state.is_terminal() {
             0.0
         } else {
+            let next_observation = next_state.get_observation();
             next_state
                 .get_actions()
-                .iter()
-                .map(|a| {
-                    let action_id = format!("{}_{}", next_state.get_id(), a);
-                    *self
-                        .action_values
-                        .get(&action_id)
-                        .unwrap_or(&self.default_action_value)
-                })
+                .into_iter()
+                .map(|a| self.get_action_value(&next_observation, a))
                 .max_by(|a, b| a.partial_cmp(b).unwrap())
                 .unwrap()
         };
-        let current_state_action_value = self
-            .action_values
-            .get(&format!("{}_{}", state.get_id(), action))
-            .unwrap_or(&self.default_action_value);
+        let current_state_action_value = self.get_action_value(&observation, action.clone());
 
         let new_state_action_value = current_state_action_value
             + (self.step_size_parameter
                 * (reward + (self.discount_rate * max_next_state_action_value)
                     - current_state_action_value));
 
-        self.action_values.insert(
-            format!("{}_{}", state.get_id(), action),
-            new_state_action_value,
-        );
+        self.action_values
+            .insert((observation.clone(), action), new_state_action_value);
 
         if new_state_action_value != self.default_action_value {
             let best_action = state
                 .get_actions()
-                .iter()
+                .into_iter()
                 .map(|a| {
-                    let action_id = format!("{}_{}", state.get_id(), a);
-                    (
-                        a.clone(),
-                        self.action_values
-                            .get(&action_id)
-                            .unwrap_or(&self.default_action_value),
-                    )
+                    let value = self.get_action_value(&observation, a.clone());
+                    (a, value)
                 })
-                .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
                 .unwrap()
                 .0;
 
-            self.policy.set_actions_for_state(
-                state.get_id().clone(),
-                state.get_actions().clone(),
-                best_action.clone(),
-            );
+            self.policy
+                .set_actions_for_state(observation, state.get_actions(), best_action);
         }
         next_state
     }
+
+    fn get_action_value(&self, observation: &S, action: A) -> f64 {
+        *self
+            .action_values
+            .get(&(observation.clone(), action))
+            .unwrap_or(&self.default_action_value)
+    }
+}
+
+impl<S, A> Persist for QLearning<S, A>
+where
+    S: Clone + Eq + Hash + Debug + Display + FromStr,
+    A: Clone + Eq + Hash + Debug + Display + FromStr,
+{
+    fn write_table<W: Write>(&self, writer: &mut TableWriter<W>) -> std::io::Result<()> {
+        writer.write_header("type", "q_learning")?;
+        writer.write_header("default_action_value", self.default_action_value)?;
+        writer.write_header("step_size_parameter", self.step_size_parameter)?;
+        writer.write_header("discount_rate", self.discount_rate)?;
+        write_action_values(writer, &self.action_values)?;
+        self.policy.write_table(writer)
+    }
+
+    fn read_table<R: BufRead>(reader: &mut TableReader<R>) -> Result<Self, PersistenceError> {
+        reader.expect_type("q_learning")?;
+        Ok(Self {
+            default_action_value: reader.read_header("default_action_value")?,
+            step_size_parameter: reader.read_header("step_size_parameter")?,
+            discount_rate: reader.read_header("discount_rate")?,
+            action_values: read_action_values(reader)?,
+            policy: EGreedyPolicy::read_table(reader)?,
+            rng: StdRng::from_os_rng(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v1::state::State;
+
+    #[derive(Clone)]
+    struct CorridorState {
+        position: usize,
+    }
+
+    impl State for CorridorState {
+        fn get_id(&self) -> String {
+            self.position.to_string()
+        }
+
+        fn get_actions(&self) -> Vec<String> {
+            vec!["left".to_string(), "right".to_string()]
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.position >= 4
+        }
+
+        fn take_action(&self, action: &str) -> (f64, Self) {
+            let position = match action {
+                "left" => self.position.saturating_sub(1),
+                _ => self.position + 1,
+            };
+            (-1.0, Self { position })
+        }
+    }
+
+    #[test]
+    fn seeded_agents_learn_the_same_values() {
+        let learn = |seed: u64| {
+            let mut agent = QLearning::new(0.3, 0.5, 1.0);
+            agent.set_seed(seed);
+            agent.learn_for_episode_count(30, vec![CorridorState { position: 0 }]);
+            (0..4)
+                .flat_map(|position| {
+                    ["left", "right"].map(|action| {
+                        agent.get_action_value(&position.to_string(), action.to_string())
+                    })
+                })
+                .collect::<Vec<f64>>()
+        };
+
+        assert_eq!(learn(11), learn(11));
+        assert_ne!(learn(11), learn(12));
+    }
 }
@@ -1,18 +1,32 @@
+use crate::attempts_at_framework::environment::Environment;
+use crate::attempts_at_framework::v1::persistence::{
+    read_action_values, write_action_values, Persist, PersistenceError, TableReader, TableWriter,
+};
 use crate::attempts_at_framework::v1::policy::EGreedyPolicy;
 use crate::attempts_at_framework::v1::policy::Policy;
-use crate::attempts_at_framework::v1::state::State;
 use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::collections::HashMap;
-
-pub struct SarsaZero {
-    action_values: HashMap<String, f64>,
-    policy: EGreedyPolicy,
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+pub struct SarsaZero<S = String, A = String> {
+    action_values: HashMap<(S, A), f64>,
+    policy: EGreedyPolicy<S, A>,
     default_state_action_value: f64,
     step_size_parameter: f64,
     discount_rate: f64,
+    rng: StdRng,
 }
 
-impl SarsaZero {
+impl<S, A> SarsaZero<S, A>
+where
+    S: Clone + Eq + Hash + Debug,
+    A: Clone + Eq + Hash + Debug,
+{
     pub fn new(e: f64, step_size_parameter: f64, discount_rate: f64) -> Self {
         if step_size_parameter < 0.0 || step_size_parameter > 1.0 {
             panic!("step size parameter must be between 0.0 and 1.0"); // Dose it?
@@ -28,71 +42,75 @@ impl SarsaZero {
             default_state_action_value: 0.0,
             step_size_parameter,
             discount_rate,
+            rng: StdRng::from_os_rng(),
         }
     }
 
-    pub fn get_policy(&self) -> &EGreedyPolicy {
+    /// Seeds every random choice the agent makes, two agents with the same seed learning from
+    /// the same environments end up with the same values.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn get_policy(&self) -> &EGreedyPolicy<S, A> {
         &self.policy
     }
 
-    pub fn lear_for_episode_count<S: State>(
+    pub fn lear_for_episode_count<E: Environment<Observation = S, Action = A>>(
         &mut self,
         episode_count: usize,
-        starting_states: Vec<S>,
+        starting_states: Vec<E>,
     ) {
-        let mut rng = rand::rng();
-
         (0..episode_count).for_each(|_| {
-            let mut state = starting_states.choose(&mut rng).unwrap().clone();
+            let mut state = starting_states.choose(&mut self.rng).unwrap().clone();
             let mut action = self.get_action_for_state(&state);
 
             while !state.is_terminal() {
-                let state_action_id = format!("{}_{}", state.get_id(), action);
+                // a non terminal state always has an action
+                let current_action = action.clone().unwrap();
+                let observation = state.get_observation();
 
                 let current_state_action_value =
-                    *self.get_state_action_value_or_default(&state_action_id);
+                    self.get_state_action_value_or_default(&observation, &current_action);
 
-                let (reward, next_state) = state.take_action(&action);
+                let (reward, next_state) = state.take_action(&current_action);
 
                 let next_state_action = self.get_action_for_state(&next_state);
 
-                let next_state_action_id = format!("{}_{}", next_state.get_id(), next_state_action);
+                let next_state_action_value = match &next_state_action {
+                    None => self.default_state_action_value,
+                    Some(next_action) => self.get_state_action_value_or_default(
+                        &next_state.get_observation(),
+                        next_action,
+                    ),
+                };
 
                 let new_state_action_value = current_state_action_value
                     + self.step_size_parameter
-                        * (reward
-                            + (self.discount_rate
-                                * self.get_state_action_value_or_default(&next_state_action_id))
+                        * (reward + (self.discount_rate * next_state_action_value)
                             - current_state_action_value);
 
-                self.action_values
-                    .insert(state_action_id, new_state_action_value);
+                self.action_values.insert(
+                    (observation.clone(), current_action),
+                    new_state_action_value,
+                );
 
-                let possible_actions = &state.get_actions();
+                let possible_actions = state.get_actions();
                 let mut best_action = possible_actions[0].clone();
-                let mut best_action_value = *self.get_state_action_value_or_default(&format!(
-                    "{}_{}",
-                    state.get_id(),
-                    best_action
-                ));
-                (1..possible_actions.len()).for_each(|i| {
-                    let action_value = self.get_state_action_value_or_default(&format!(
-                        "{}_{}",
-                        state.get_id(),
-                        possible_actions[i]
-                    ));
-                    if action_value > &best_action_value {
-                        best_action = possible_actions[i].clone();
-                        best_action_value = *action_value;
+                let mut best_action_value =
+                    self.get_state_action_value_or_default(&observation, &best_action);
+                possible_actions.iter().skip(1).for_each(|possible_action| {
+                    let action_value =
+                        self.get_state_action_value_or_default(&observation, possible_action);
+                    if action_value > best_action_value {
+                        best_action = possible_action.clone();
+                        best_action_value = action_value;
                     }
                 });
 
                 if best_action_value != self.default_state_action_value {
-                    self.policy.set_actions_for_state(
-                        state.get_id().clone(),
-                        possible_actions.clone(),
-                        best_action,
-                    );
+                    self.policy
+                        .set_actions_for_state(observation, possible_actions, best_action);
                 }
 
                 action = next_state_action;
@@ -101,26 +119,60 @@ impl SarsaZero {
         })
     }
 
-    fn get_state_action_value_or_default(&mut self, state_action_id: &str) -> &f64 {
-        self.action_values
-            .get(state_action_id)
+    fn get_state_action_value_or_default(&self, observation: &S, action: &A) -> f64 {
+        *self
+            .action_values
+            .get(&(observation.clone(), action.clone()))
             .unwrap_or(&self.default_state_action_value)
     }
 
-    fn get_action_for_state<S: State>(&self, state: &S) -> String {
-        match self.policy.select_action_for_state(&state.get_id()) {
-            Ok(action) => action,
+    /// Terminal states have no actions to pick from, so `None` is returned for them.
+    fn get_action_for_state<E: Environment<Observation = S, Action = A>>(
+        &mut self,
+        state: &E,
+    ) -> Option<A> {
+        match self
+            .policy
+            .select_action_for_state_with_rng(&state.get_observation(), &mut self.rng)
+        {
+            Ok(action) => Some(action),
             Err(_) => {
                 if state.is_terminal() {
-                    String::new()
+                    None
                 } else {
-                    state
-                        .get_actions()
-                        .choose(&mut rand::rng())
-                        .unwrap()
-                        .clone()
+                    state.get_actions().choose(&mut self.rng).cloned()
                 }
             }
         }
     }
 }
+
+impl<S, A> Persist for SarsaZero<S, A>
+where
+    S: Clone + Eq + Hash + Debug + Display + FromStr,
+    A: Clone + Eq + Hash + Debug + Display + FromStr,
+{
+    fn write_table<W: Write>(&self, writer: &mut TableWriter<W>) -> std::io::Result<()> {
+        writer.write_header("type", "sarsa_zero")?;
+        writer.write_header(
+            "default_state_action_value",
+            self.default_state_action_value,
+        )?;
+        writer.write_header("step_size_parameter", self.step_size_parameter)?;
+        writer.write_header("discount_rate", self.discount_rate)?;
+        write_action_values(writer, &self.action_values)?;
+        self.policy.write_table(writer)
+    }
+
+    fn read_table<R: BufRead>(reader: &mut TableReader<R>) -> Result<Self, PersistenceError> {
+        reader.expect_type("sarsa_zero")?;
+        Ok(Self {
+            default_state_action_value: reader.read_header("default_state_action_value")?,
+            step_size_parameter: reader.read_header("step_size_parameter")?,
+            discount_rate: reader.read_header("discount_rate")?,
+            action_values: read_action_values(reader)?,
+            policy: EGreedyPolicy::read_table(reader)?,
+            rng: StdRng::from_os_rng(),
+        })
+    }
+}
@@ -1,3 +1,4 @@
 pub mod agent;
+pub mod persistence;
 pub mod policy;
 pub mod state;
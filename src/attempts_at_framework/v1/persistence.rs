@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// The version of the on disk format written by [`Persist::save`], bump this whenever the layout
+/// changes so old checkpoints are rejected instead of being read wrong.
+pub const TABLE_FILE_FORMAT_VERSION: u32 = 1;
+
+/*
+Saved agents and policies are plain text, made of `key: value` headers and tab separated tables:
+
+    format_version: 1
+    type: q_learning
+    step_size_parameter: 0.5
+    [action_values] 2
+    <state>\t<action>\t<value>
+    <state>\t<action>\t<value>
+    type: e_greedy_policy
+    e: 0.1
+    [actions] 1
+    <state>\t<action>\t<probability>
+
+Tabs, new lines and backslashes inside a state or action are escaped, so any state id or action
+that can be written with `Display` and read back with `FromStr` can be saved.
+ */
+
+/// # Persist
+///
+/// Anything that can be checkpointed to disk and read back, so long learning runs can be resumed
+/// or shared.
+pub trait Persist: Sized {
+    /// writes the headers and tables that make up this value
+    fn write_table<W: Write>(&self, writer: &mut TableWriter<W>) -> std::io::Result<()>;
+
+    /// reads back what was written by [`Persist::write_table`]
+    fn read_table<R: BufRead>(reader: &mut TableReader<R>) -> Result<Self, PersistenceError>;
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let mut table_writer = TableWriter::new(writer);
+        table_writer.write_header("format_version", TABLE_FILE_FORMAT_VERSION)?;
+        self.write_table(&mut table_writer)
+    }
+
+    fn read_from<R: BufRead>(reader: R) -> Result<Self, PersistenceError> {
+        let mut table_reader = TableReader::new(reader);
+        let format_version: u32 = table_reader.read_header("format_version")?;
+        if format_version != TABLE_FILE_FORMAT_VERSION {
+            return Err(PersistenceError::new(format!(
+                "unsupported file format version {}, only version {} can be read",
+                format_version, TABLE_FILE_FORMAT_VERSION
+            )));
+        }
+        Self::read_table(&mut table_reader)
+    }
+
+    fn save(&self, path: &Path) -> Result<(), PersistenceError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                PersistenceError::new(format!("could not create {}: {}", parent.display(), e))
+            })?;
+        }
+        let file = File::create(path).map_err(|e| {
+            PersistenceError::new(format!("could not create {}: {}", path.display(), e))
+        })?;
+        let mut writer = BufWriter::new(file);
+        self.write_to(&mut writer)
+            .and_then(|_| writer.flush())
+            .map_err(|e| {
+                PersistenceError::new(format!("could not write {}: {}", path.display(), e))
+            })
+    }
+
+    fn load(path: &Path) -> Result<Self, PersistenceError> {
+        let file = File::open(path).map_err(|e| {
+            PersistenceError::new(format!("could not open {}: {}", path.display(), e))
+        })?;
+        Self::read_from(BufReader::new(file))
+    }
+}
+
+pub struct TableWriter<'a, W: Write> {
+    writer: &'a mut W,
+}
+
+impl<'a, W: Write> TableWriter<'a, W> {
+    pub fn new(writer: &'a mut W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write_header<V: Display>(&mut self, key: &str, value: V) -> std::io::Result<()> {
+        writeln!(self.writer, "{}: {}", key, value)
+    }
+
+    /// Writes a named table, every row is written as tab separated escaped fields.
+    pub fn write_section(&mut self, name: &str, rows: &[Vec<String>]) -> std::io::Result<()> {
+        writeln!(self.writer, "[{}] {}", name, rows.len())?;
+        for row in rows {
+            let fields = row
+                .iter()
+                .map(|field| escape(field))
+                .collect::<Vec<String>>();
+            writeln!(self.writer, "{}", fields.join("\t"))?;
+        }
+        Ok(())
+    }
+}
+
+pub struct TableReader<R: BufRead> {
+    lines: std::iter::Enumerate<std::io::Lines<R>>,
+}
+
+impl<R: BufRead> TableReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines().enumerate(),
+        }
+    }
+
+    fn next_line(&mut self, expected: &str) -> Result<(usize, String), PersistenceError> {
+        match self.lines.next() {
+            None => Err(PersistenceError::new(format!(
+                "unexpected end of file, expected `{}`",
+                expected
+            ))),
+            Some((index, line)) => {
+                let line = line.map_err(|e| {
+                    PersistenceError::new(format!("could not read line {}: {}", index + 1, e))
+                })?;
+                Ok((index + 1, line))
+            }
+        }
+    }
+
+    pub fn read_header<T: FromStr>(&mut self, key: &str) -> Result<T, PersistenceError> {
+        let (line_number, line) = self.next_line(key)?;
+        match line.split_once(": ") {
+            Some((found_key, value)) if found_key == key => parse_field(value, line_number),
+            _ => Err(PersistenceError::new(format!(
+                "line {}: expected `{}` but found `{}`",
+                line_number, key, line
+            ))),
+        }
+    }
+
+    /// Reads the `type` header and checks it is the one expected, so loading the wrong kind of
+    /// checkpoint gives a useful error.
+    pub fn expect_type(&mut self, expected_type: &str) -> Result<(), PersistenceError> {
+        let found_type: String = self.read_header("type")?;
+        if found_type != expected_type {
+            return Err(PersistenceError::new(format!(
+                "expected a saved {} but found a saved {}",
+                expected_type, found_type
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reads a table written with [`TableWriter::write_section`], each row must have `columns`
+    /// fields. Every row is returned with the line number it was read from.
+    pub fn read_section(
+        &mut self,
+        name: &str,
+        columns: usize,
+    ) -> Result<Vec<(usize, Vec<String>)>, PersistenceError> {
+        let section_header = format!("[{}]", name);
+        let (line_number, line) = self.next_line(&section_header)?;
+        let row_count = match line.split_once(' ') {
+            Some((found, count)) if found == section_header => parse_field(count, line_number)?,
+            _ => {
+                return Err(PersistenceError::new(format!(
+                    "line {}: expected `{}` but found `{}`",
+                    line_number, section_header, line
+                )))
+            }
+        };
+
+        (0..row_count)
+            .map(|_| {
+                let (line_number, line) = self.next_line("a table row")?;
+                let fields = line.split('\t').map(unescape).collect::<Vec<String>>();
+                if fields.len() != columns {
+                    return Err(PersistenceError::new(format!(
+                        "line {}: expected {} fields in the {} table but found {}",
+                        line_number,
+                        columns,
+                        name,
+                        fields.len()
+                    )));
+                }
+                Ok((line_number, fields))
+            })
+            .collect()
+    }
+}
+
+/// Writes the `(state, action) -> value` table every tabular agent keeps.
+pub(crate) fn write_action_values<W, S, A>(
+    writer: &mut TableWriter<W>,
+    action_values: &HashMap<(S, A), f64>,
+) -> std::io::Result<()>
+where
+    W: Write,
+    S: Display,
+    A: Display,
+{
+    let rows = action_values
+        .iter()
+        .map(|((state, action), value)| {
+            vec![state.to_string(), action.to_string(), value.to_string()]
+        })
+        .collect::<Vec<Vec<String>>>();
+    writer.write_section("action_values", &rows)
+}
+
+pub(crate) fn read_action_values<R, S, A>(
+    reader: &mut TableReader<R>,
+) -> Result<HashMap<(S, A), f64>, PersistenceError>
+where
+    R: BufRead,
+    S: Eq + Hash + FromStr,
+    A: Eq + Hash + FromStr,
+{
+    reader
+        .read_section("action_values", 3)?
+        .into_iter()
+        .map(|(line_number, row)| {
+            Ok((
+                (
+                    parse_field(&row[0], line_number)?,
+                    parse_field(&row[1], line_number)?,
+                ),
+                parse_field(&row[2], line_number)?,
+            ))
+        })
+        .collect()
+}
+
+/// Parses a single value read from a file, `line_number` is only used for the error message.
+pub fn parse_field<T: FromStr>(value: &str, line_number: usize) -> Result<T, PersistenceError> {
+    value.parse::<T>().map_err(|_| {
+        PersistenceError::new(format!(
+            "line {}: could not parse `{}` as a {}",
+            line_number,
+            value,
+            std::any::type_name::<T>()
+        ))
+    })
+}
+
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('t') => result.push('\t'),
+                Some('n') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+pub struct PersistenceError {
+    message: String,
+}
+
+impl PersistenceError {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl Debug for PersistenceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PersistenceError: {}", self.message)
+    }
+}
+
+impl Display for PersistenceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PersistenceError: {}", self.message)
+    }
+}
+
+impl Error for PersistenceError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v1::agent::{NStepSarsa, QLearning, SarsaZero};
+    use crate::attempts_at_framework::v1::policy::{DeterministicPolicy, EGreedyPolicy, Policy};
+    use crate::attempts_at_framework::v1::state::State;
+
+    #[derive(Clone)]
+    struct LineState {
+        position: usize,
+    }
+
+    impl State for LineState {
+        fn get_id(&self) -> String {
+            format!("line\t{}", self.position)
+        }
+
+        fn get_actions(&self) -> Vec<String> {
+            vec!["left".to_string(), "right".to_string()]
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.position >= 4
+        }
+
+        fn take_action(&self, action: &str) -> (f64, Self) {
+            let position = match action {
+                "left" => self.position.saturating_sub(1),
+                _ => self.position + 1,
+            };
+            (-1.0, Self { position })
+        }
+    }
+
+    fn round_trip<P: Persist>(value: &P) -> (Vec<u8>, P) {
+        let mut buffer = Vec::new();
+        value.write_to(&mut buffer).unwrap();
+        let loaded = P::read_from(buffer.as_slice()).unwrap();
+        (buffer, loaded)
+    }
+
+    /// Rows are written in hash map order, so they're compared sorted.
+    fn saved_rows<P: Persist>(value: &P) -> Vec<String> {
+        let mut buffer = Vec::new();
+        value.write_to(&mut buffer).unwrap();
+        let mut rows = String::from_utf8(buffer)
+            .unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect::<Vec<String>>();
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn escaping_round_trips() {
+        let field = "a\tb\\c\nd";
+        assert_eq!(unescape(&escape(field)), field);
+    }
+
+    #[test]
+    fn deterministic_policy_round_trip() {
+        let mut policy = DeterministicPolicy::new();
+        policy.set_actions_for_state("with\ttab".to_string(), "go".to_string());
+        policy.set_actions_for_state("plain".to_string(), "stop".to_string());
+
+        let (_, loaded) = round_trip(&policy);
+
+        assert_eq!(loaded.select_action_for_state("with\ttab").unwrap(), "go");
+        assert_eq!(loaded.select_action_for_state("plain").unwrap(), "stop");
+    }
+
+    #[test]
+    fn e_greedy_policy_round_trip() {
+        let mut policy = EGreedyPolicy::new(0.2);
+        policy.set_actions_for_state(
+            "s".to_string(),
+            vec!["a".to_string(), "b".to_string()],
+            "b".to_string(),
+        );
+
+        let (_, loaded) = round_trip(&policy);
+
+        assert_eq!(loaded.get_e(), 0.2);
+        assert_eq!(
+            loaded
+                .to_deterministic_policy()
+                .select_action_for_state("s")
+                .unwrap(),
+            "b"
+        );
+    }
+
+    #[test]
+    fn agents_round_trip() {
+        let starting_states = vec![LineState { position: 0 }];
+
+        let mut q_learning = QLearning::new(0.1, 0.5, 1.0);
+        q_learning.learn_for_episode_count(20, starting_states.clone());
+        let (_, loaded) = round_trip(&q_learning);
+        assert_eq!(saved_rows(&loaded), saved_rows(&q_learning));
+
+        let mut sarsa = SarsaZero::new(0.1, 0.5, 1.0);
+        sarsa.lear_for_episode_count(20, starting_states.clone());
+        let (_, loaded) = round_trip(&sarsa);
+        assert_eq!(saved_rows(&loaded), saved_rows(&sarsa));
+
+        let mut n_step_sarsa = NStepSarsa::new(2, 0.1, 0.5, 1.0);
+        n_step_sarsa.learn_for_episode_count(20, starting_states.clone());
+        let (_, mut loaded) = round_trip(&n_step_sarsa);
+        assert_eq!(saved_rows(&loaded), saved_rows(&n_step_sarsa));
+        assert_eq!(loaded.get_num_of_episodes_learned_for(), 20);
+
+        // a loaded agent can keep learning where it left off
+        loaded.learn_for_episode_count(5, starting_states);
+        assert_eq!(loaded.get_num_of_episodes_learned_for(), 25);
+    }
+
+    #[test]
+    fn loading_the_wrong_type_is_an_error() {
+        let mut buffer = Vec::new();
+        DeterministicPolicy::<String, String>::new()
+            .write_to(&mut buffer)
+            .unwrap();
+
+        let error = QLearning::<String, String>::read_from(buffer.as_slice())
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .contains("expected a saved q_learning but found a saved deterministic_policy"));
+    }
+}
@@ -1,31 +1,72 @@
+use crate::attempts_at_framework::v1::persistence::{
+    parse_field, Persist, PersistenceError, TableReader, TableWriter,
+};
 use crate::attempts_at_framework::v1::policy::policy::{Policy, PolicyError};
+use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
-pub struct DeterministicPolicy {
-    state_action_map: HashMap<String, String>,
+pub struct DeterministicPolicy<S = String, A = String> {
+    state_action_map: HashMap<S, A>,
 }
 
-impl DeterministicPolicy {
+impl<S: Eq + Hash, A> DeterministicPolicy<S, A> {
     pub fn new() -> Self {
         Self {
             state_action_map: HashMap::new(),
         }
     }
 
-    pub fn set_actions_for_state(&mut self, state_id: String, action: String) {
+    pub fn set_actions_for_state(&mut self, state_id: S, action: A) {
         self.state_action_map.insert(state_id, action);
     }
 }
 
-impl Policy for DeterministicPolicy {
-    fn select_action_for_state(&self, state_id: &str) -> Result<String, Box<PolicyError>> {
+impl<S, A, Q> Policy<Q, A> for DeterministicPolicy<S, A>
+where
+    S: Eq + Hash + Borrow<Q>,
+    A: Clone,
+    Q: Eq + Hash + Debug + ?Sized,
+{
+    fn select_action_for_state(&self, state_id: &Q) -> Result<A, Box<PolicyError>> {
         match self.state_action_map.get(state_id) {
             None => Err(Box::new(PolicyError::new(format!(
-                "state id {} not found",
+                "state id {:?} not found",
                 state_id
             )))),
             Some(action) => Ok(action.clone()),
         }
     }
 }
+
+impl<S, A> Persist for DeterministicPolicy<S, A>
+where
+    S: Eq + Hash + Display + FromStr,
+    A: Display + FromStr,
+{
+    fn write_table<W: Write>(&self, writer: &mut TableWriter<W>) -> std::io::Result<()> {
+        writer.write_header("type", "deterministic_policy")?;
+        let rows = self
+            .state_action_map
+            .iter()
+            .map(|(state_id, action)| vec![state_id.to_string(), action.to_string()])
+            .collect::<Vec<Vec<String>>>();
+        writer.write_section("actions", &rows)
+    }
+
+    fn read_table<R: BufRead>(reader: &mut TableReader<R>) -> Result<Self, PersistenceError> {
+        reader.expect_type("deterministic_policy")?;
+        let mut policy = Self::new();
+        for (line_number, row) in reader.read_section("actions", 2)? {
+            policy.set_actions_for_state(
+                parse_field(&row[0], line_number)?,
+                parse_field(&row[1], line_number)?,
+            );
+        }
+        Ok(policy)
+    }
+}
@@ -1,13 +1,26 @@
+use crate::attempts_at_framework::v1::persistence::{
+    Persist, PersistenceError, TableReader, TableWriter,
+};
 use crate::attempts_at_framework::v1::policy::policy::{Policy, PolicyError};
 use crate::attempts_at_framework::v1::policy::{DeterministicPolicy, StochasticPolicy};
+use rand::RngCore;
+use std::borrow::Borrow;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
-pub struct EGreedyPolicy {
-    policy: StochasticPolicy,
+pub struct EGreedyPolicy<S = String, A = String> {
+    policy: StochasticPolicy<S, A>,
     e: f64,
 }
 
-impl EGreedyPolicy {
+impl<S, A> EGreedyPolicy<S, A>
+where
+    S: Clone + Eq + Hash + Debug,
+    A: Clone + PartialEq + Debug,
+{
     pub fn new(e: f64) -> Self {
         if e < 0.0 || e > 1.0 {
             panic!("e must be between 0.0 and 1.0");
@@ -19,19 +32,18 @@ impl EGreedyPolicy {
         }
     }
 
-    pub fn set_actions_for_state(
-        &mut self,
-        state_id: String,
-        actions: Vec<String>,
-        best_action: String,
-    ) {
+    pub fn get_e(&self) -> f64 {
+        self.e
+    }
+
+    pub fn set_actions_for_state(&mut self, state_id: S, actions: Vec<A>, best_action: A) {
         if actions.is_empty() {
-            panic!("attempting to set state {} to have no actions", state_id);
+            panic!("attempting to set state {:?} to have no actions", state_id);
         }
 
         if !actions.contains(&best_action) {
             panic!(
-                "attempting to set state {} to have best action {} not in actions",
+                "attempting to set state {:?} to have best action {:?} not in actions",
                 state_id, best_action
             );
         }
@@ -52,13 +64,51 @@ impl EGreedyPolicy {
             .set_actions_for_state(state_id, actions_and_odds.collect());
     }
 
-    pub fn to_deterministic_policy(&self) -> DeterministicPolicy {
+    pub fn to_deterministic_policy(&self) -> DeterministicPolicy<S, A> {
         self.policy.to_deterministic_policy()
     }
 }
 
-impl Policy for EGreedyPolicy {
-    fn select_action_for_state(&self, state_id: &str) -> Result<String, Box<PolicyError>> {
+impl<S, A, Q> Policy<Q, A> for EGreedyPolicy<S, A>
+where
+    S: Eq + Hash + Borrow<Q>,
+    A: Clone,
+    Q: Eq + Hash + Debug + ?Sized,
+{
+    fn select_action_for_state(&self, state_id: &Q) -> Result<A, Box<PolicyError>> {
         self.policy.select_action_for_state(state_id)
     }
+
+    fn select_action_for_state_with_rng(
+        &self,
+        state_id: &Q,
+        rng: &mut dyn RngCore,
+    ) -> Result<A, Box<PolicyError>> {
+        self.policy.select_action_for_state_with_rng(state_id, rng)
+    }
+}
+
+impl<S, A> Persist for EGreedyPolicy<S, A>
+where
+    S: Clone + Eq + Hash + Display + FromStr,
+    A: Clone + Display + FromStr,
+{
+    fn write_table<W: Write>(&self, writer: &mut TableWriter<W>) -> std::io::Result<()> {
+        writer.write_header("type", "e_greedy_policy")?;
+        writer.write_header("e", self.e)?;
+        self.policy.write_table(writer)
+    }
+
+    fn read_table<R: BufRead>(reader: &mut TableReader<R>) -> Result<Self, PersistenceError> {
+        reader.expect_type("e_greedy_policy")?;
+        let e: f64 = reader.read_header("e")?;
+        if !(0.0..=1.0).contains(&e) {
+            return Err(PersistenceError::new(format!(
+                "e must be between 0.0 and 1.0 but was {}",
+                e
+            )));
+        }
+        let policy = StochasticPolicy::read_table(reader)?;
+        Ok(Self { policy, e })
+    }
 }
@@ -1,8 +1,24 @@
+use rand::RngCore;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 
-pub trait Policy {
-    fn select_action_for_state(&self, state_id: &str) -> Result<String, Box<PolicyError>>;
+/// # Policy
+///
+/// Picks an action for a state. Defaults to string state ids and actions, but any
+/// [`Environment`](crate::attempts_at_framework::environment::Environment) observation and action
+/// types can be used.
+pub trait Policy<S: ?Sized = str, A = String> {
+    fn select_action_for_state(&self, state_id: &S) -> Result<A, Box<PolicyError>>;
+
+    /// Same as [`Policy::select_action_for_state`], but any randomness comes from `rng`, so runs
+    /// with a seeded rng are repeatable. Policies that never pick at random can leave this as is.
+    fn select_action_for_state_with_rng(
+        &self,
+        state_id: &S,
+        _rng: &mut dyn RngCore,
+    ) -> Result<A, Box<PolicyError>> {
+        self.select_action_for_state(state_id)
+    }
 }
 
 pub struct PolicyError {
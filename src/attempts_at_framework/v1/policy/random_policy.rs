@@ -1,5 +1,6 @@
 use crate::attempts_at_framework::v1::policy::policy::{Policy, PolicyError};
 use rand::prelude::IndexedRandom;
+use rand::RngCore;
 use std::collections::HashMap;
 
 pub struct RandomPolicy {
@@ -20,6 +21,14 @@ impl RandomPolicy {
 
 impl Policy for RandomPolicy {
     fn select_action_for_state(&self, state_id: &str) -> Result<String, Box<PolicyError>> {
+        self.select_action_for_state_with_rng(state_id, &mut rand::rng())
+    }
+
+    fn select_action_for_state_with_rng(
+        &self,
+        state_id: &str,
+        rng: &mut dyn RngCore,
+    ) -> Result<String, Box<PolicyError>> {
         match self.state_actions.get(state_id) {
             None => Err(Box::new(PolicyError::new(format!(
                 "state id {} not found",
@@ -33,8 +42,7 @@ impl Policy for RandomPolicy {
                     ))));
                 }
 
-                let mut rng = rand::rng();
-                let action = actions.choose(&mut rng).unwrap().clone();
+                let action = actions.choose(rng).unwrap().clone();
 
                 Ok(action)
             }
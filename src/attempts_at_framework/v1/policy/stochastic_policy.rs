@@ -1,33 +1,42 @@
+use crate::attempts_at_framework::v1::persistence::{
+    parse_field, Persist, PersistenceError, TableReader, TableWriter,
+};
 use crate::attempts_at_framework::v1::policy::policy::{Policy, PolicyError};
 use crate::attempts_at_framework::v1::policy::DeterministicPolicy;
 use rand::prelude::IndexedRandom;
+use rand::RngCore;
+use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
-pub struct StochasticPolicy {
-    state_action_odds: HashMap<String, Vec<(String, f64)>>,
+pub struct StochasticPolicy<S = String, A = String> {
+    state_action_odds: HashMap<S, Vec<(A, f64)>>,
 }
 
-impl StochasticPolicy {
+impl<S: Clone + Eq + Hash, A: Clone> StochasticPolicy<S, A> {
     pub fn new() -> Self {
         Self {
             state_action_odds: HashMap::new(),
         }
     }
 
-    pub fn set_actions_for_state(
-        &mut self,
-        state_id: String,
-        actions_and_odds: Vec<(String, f64)>,
-    ) {
+    pub fn set_actions_for_state(&mut self, state_id: S, actions_and_odds: Vec<(A, f64)>) {
         self.state_action_odds.insert(state_id, actions_and_odds);
     }
 
-    pub fn get_actions_for_state(&self, state_id: &str) -> Option<&Vec<(String, f64)>> {
+    pub fn get_actions_for_state<Q>(&self, state_id: &Q) -> Option<&Vec<(A, f64)>>
+    where
+        S: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
         self.state_action_odds.get(state_id)
     }
 
-    pub fn to_deterministic_policy(&self) -> DeterministicPolicy {
+    pub fn to_deterministic_policy(&self) -> DeterministicPolicy<S, A> {
         let mut deterministic_policy = DeterministicPolicy::new();
         self.state_action_odds
             .iter()
@@ -48,30 +57,41 @@ impl StochasticPolicy {
     }
 }
 
-impl Policy for StochasticPolicy {
-    fn select_action_for_state(&self, state_id: &str) -> Result<String, Box<PolicyError>> {
+impl<S, A, Q> Policy<Q, A> for StochasticPolicy<S, A>
+where
+    S: Eq + Hash + Borrow<Q>,
+    A: Clone,
+    Q: Eq + Hash + Debug + ?Sized,
+{
+    fn select_action_for_state(&self, state_id: &Q) -> Result<A, Box<PolicyError>> {
+        self.select_action_for_state_with_rng(state_id, &mut rand::rng())
+    }
+
+    fn select_action_for_state_with_rng(
+        &self,
+        state_id: &Q,
+        rng: &mut dyn RngCore,
+    ) -> Result<A, Box<PolicyError>> {
         match self.state_action_odds.get(state_id) {
             None => Err(Box::new(PolicyError::new(format!(
-                "state id {} not found",
+                "state id {:?} not found",
                 state_id
             )))),
             Some(actions_and_odds) => {
                 if actions_and_odds.is_empty() {
                     return Err(Box::new(PolicyError::new(format!(
-                        "state id {} has no actions",
+                        "state id {:?} has no actions",
                         state_id
                     ))));
                 }
 
-                let mut rng = rand::rng();
-
                 match actions_and_odds
-                    .choose_weighted(&mut rng, |a| a.1)
+                    .choose_weighted(rng, |a| a.1)
                     .map(|a| a.0.clone())
                 {
                     Ok(a) => Ok(a),
                     Err(_) => Err(Box::new(PolicyError::new(format!(
-                        "state id {} not found",
+                        "state id {:?} not found",
                         state_id
                     )))),
                 }
@@ -79,3 +99,39 @@ impl Policy for StochasticPolicy {
         }
     }
 }
+
+impl<S, A> Persist for StochasticPolicy<S, A>
+where
+    S: Clone + Eq + Hash + Display + FromStr,
+    A: Clone + Display + FromStr,
+{
+    fn write_table<W: Write>(&self, writer: &mut TableWriter<W>) -> std::io::Result<()> {
+        writer.write_header("type", "stochastic_policy")?;
+        let rows = self
+            .state_action_odds
+            .iter()
+            .flat_map(|(state_id, actions_and_odds)| {
+                actions_and_odds.iter().map(move |(action, odds)| {
+                    vec![state_id.to_string(), action.to_string(), odds.to_string()]
+                })
+            })
+            .collect::<Vec<Vec<String>>>();
+        writer.write_section("actions", &rows)
+    }
+
+    fn read_table<R: BufRead>(reader: &mut TableReader<R>) -> Result<Self, PersistenceError> {
+        reader.expect_type("stochastic_policy")?;
+        let mut policy = Self::new();
+        for (line_number, row) in reader.read_section("actions", 3)? {
+            let state_id: S = parse_field(&row[0], line_number)?;
+            let action: A = parse_field(&row[1], line_number)?;
+            let odds: f64 = parse_field(&row[2], line_number)?;
+            policy
+                .state_action_odds
+                .entry(state_id)
+                .or_default()
+                .push((action, odds));
+        }
+        Ok(policy)
+    }
+}
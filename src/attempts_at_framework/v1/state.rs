@@ -1,6 +1,8 @@
 /// # State
 ///
-/// defines simple functions that any state should be able to implement.
+/// defines simple functions that any state should be able to implement. Every state is also an
+/// [`Environment`](crate::attempts_at_framework::environment::Environment) with `String` actions
+/// and its id as the observation.
 pub trait State: Clone {
     /// returns the id of the state, this needs to be unique to the state
     fn get_id(&self) -> String;
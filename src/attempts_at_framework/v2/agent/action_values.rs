@@ -0,0 +1,209 @@
+use crate::attempts_at_framework::environment::FeatureVector;
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+
+/// How an agent's [`Model`] estimates the value of each action of a state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionValueModel {
+    /// The state's values copied into the block for the action's position in `get_actions`, every
+    /// other block left at 0.0, with a single output. The model needs `action_count` times as many
+    /// inputs as the state has values and is run once per action.
+    ActionBlocks,
+    /// Just the state's values, with one output per action in the order of `get_actions`. Every
+    /// action is valued in a single forward pass and training only backpropagates through the
+    /// output of the action being learnt about.
+    MultiHead,
+}
+
+impl ActionValueModel {
+    /// The estimated value of every action of `state`, in the order of `get_actions`.
+    pub fn get_action_values<E: FeatureVector>(&self, model: &Model, state: &E) -> Vec<f64> {
+        let action_count = state.get_actions().len();
+        match self {
+            ActionValueModel::ActionBlocks => {
+                let inputs = (0..action_count)
+                    .map(|action_index| Self::get_action_block(state, action_index, action_count))
+                    .collect::<Vec<Vec<f64>>>();
+                model
+                    .predict_batch(&inputs)
+                    .into_iter()
+                    .map(|output| output[0])
+                    .collect()
+            }
+            ActionValueModel::MultiHead => {
+                let mut values = model.predict(state.get_values());
+                if action_count > values.len() {
+                    panic!(
+                        "state has {} actions but the model only has {} outputs",
+                        action_count,
+                        values.len()
+                    );
+                }
+                values.truncate(action_count);
+                values
+            }
+        }
+    }
+
+    pub fn get_action_value<E: FeatureVector>(
+        &self,
+        model: &Model,
+        state: &E,
+        action_index: usize,
+    ) -> f64 {
+        match self {
+            ActionValueModel::ActionBlocks => {
+                let action_count = state.get_actions().len();
+                model.predict(Self::get_action_block(state, action_index, action_count))[0]
+            }
+            ActionValueModel::MultiHead => model.predict(state.get_values())[action_index],
+        }
+    }
+
+    /// Moves the estimate for the action at `action_index` towards `target`, returns the loss.
+    pub fn train<E: FeatureVector>(
+        &self,
+        model: &mut Model,
+        state: &E,
+        action_index: usize,
+        target: f64,
+        learning_rate: f64,
+    ) -> f64 {
+        match self {
+            ActionValueModel::ActionBlocks => {
+                let action_count = state.get_actions().len();
+                model.train(
+                    Self::get_action_block(state, action_index, action_count),
+                    vec![target],
+                    learning_rate,
+                )
+            }
+            ActionValueModel::MultiHead => {
+                model.train_output(state.get_values(), action_index, target, learning_rate)
+            }
+        }
+    }
+
+    fn get_action_block<E: FeatureVector>(
+        state: &E,
+        action_index: usize,
+        action_count: usize,
+    ) -> Vec<f64> {
+        let values = state.get_values();
+        let mut input = vec![0.0; values.len() * action_count];
+        let start_index = action_index * values.len();
+        input[start_index..start_index + values.len()].copy_from_slice(&values);
+        input
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::attempts_at_framework::environment::Environment;
+    use crate::attempts_at_framework::v2::artificial_neural_network::initialization::Initialization;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+
+    pub(crate) const CORRIDOR_LENGTH: usize = 5;
+
+    /// Walk right to the end of the corridor, every step costs 1.
+    #[derive(Debug, Clone)]
+    pub(crate) struct Corridor {
+        pub(crate) position: usize,
+    }
+
+    impl Environment for Corridor {
+        type Action = String;
+        type Observation = usize;
+
+        fn get_observation(&self) -> usize {
+            self.position
+        }
+
+        fn get_actions(&self) -> Vec<String> {
+            vec!["left".to_string(), "right".to_string()]
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.position == CORRIDOR_LENGTH
+        }
+
+        fn take_action(&self, action: &String) -> (f64, Self) {
+            let position = match action.as_str() {
+                "left" => self.position.saturating_sub(1),
+                _ => self.position + 1,
+            };
+            (-1.0, Self { position })
+        }
+    }
+
+    impl FeatureVector for Corridor {
+        fn get_values(&self) -> Vec<f64> {
+            let mut values = vec![0.0; CORRIDOR_LENGTH];
+            values[self.position] = 1.0;
+            values
+        }
+    }
+
+    /// a linear model with `output_count` outputs, starting at all zeros
+    pub(crate) fn build_model(input_size: usize, output_count: usize) -> Model {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(input_size)
+            .add_layer(
+                LayerBuilder::new(LayerType::LINEAR, output_count)
+                    .set_initialization(Initialization::Zeros),
+            );
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn both_models_value_actions_the_same_way() {
+        // the multi-head model's weights are the action blocks' weights one output after another
+        let weights = (0..CORRIDOR_LENGTH * 2)
+            .map(|i| i as f64 / 4.0 - 1.0)
+            .collect::<Vec<f64>>();
+        let mut blocks = build_model(CORRIDOR_LENGTH * 2, 1);
+        blocks.add_to_parameters(&[weights.clone(), vec![0.5]].concat());
+        let mut multi_head = build_model(CORRIDOR_LENGTH, 2);
+        multi_head.add_to_parameters(&[weights, vec![0.5, 0.5]].concat());
+
+        (0..CORRIDOR_LENGTH).for_each(|position| {
+            let state = Corridor { position };
+            let block_values = ActionValueModel::ActionBlocks.get_action_values(&blocks, &state);
+            assert_eq!(
+                block_values,
+                ActionValueModel::MultiHead.get_action_values(&multi_head, &state)
+            );
+            assert_eq!(
+                block_values[1],
+                ActionValueModel::MultiHead.get_action_value(&multi_head, &state, 1)
+            );
+        });
+    }
+
+    #[test]
+    fn multi_head_training_only_moves_the_action_trained() {
+        let mut model = build_model(CORRIDOR_LENGTH, 2);
+        model.add_to_parameters(&[0.1; CORRIDOR_LENGTH * 2 + 2]);
+        let state = Corridor { position: 2 };
+        let before = ActionValueModel::MultiHead.get_action_values(&model, &state);
+        (0..100).for_each(|_| {
+            ActionValueModel::MultiHead.train(&mut model, &state, 1, -3.0, 0.1);
+        });
+        let after = ActionValueModel::MultiHead.get_action_values(&model, &state);
+
+        assert_eq!(before[0], after[0]);
+        assert!((after[1] + 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn multi_head_needs_an_output_per_action() {
+        let model = build_model(CORRIDOR_LENGTH, 1);
+        ActionValueModel::MultiHead.get_action_values(&model, &Corridor { position: 0 });
+    }
+}
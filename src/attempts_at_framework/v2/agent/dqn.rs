@@ -0,0 +1,370 @@
+use crate::attempts_at_framework::environment::StateActionFeatureVector;
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+use crate::attempts_at_framework::v2::replay_buffer::ReplayBuffer;
+use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// A single step of experience, stored as the values the network sees so the replay buffer does
+/// not need to hold on to whole environments.
+pub struct Transition {
+    state_action_values: Vec<f64>,
+    reward: f64,
+    /// the values of every action from the next state, empty when the next state is terminal
+    next_state_action_values: Vec<Vec<f64>>,
+}
+
+/// # DQN
+///
+/// Q learning with a neural network, the model takes the values from
+/// [`StateActionFeatureVector::get_state_action_values`] and estimates a single action value.
+///
+/// Every step is stored in a replay buffer and the model learns from batches sampled from it.
+/// Targets are worked out with a separate target network that is only brought up to date with
+/// the model every `target_update_interval` steps, which keeps the targets from chasing the
+/// model. Use [`Model::set_optimizer`] on the model to control how it learns.
+pub struct Dqn {
+    discount_rate: f64,
+    explore_rate: f64,
+    batch_size: usize,
+    target_update_interval: usize,
+    steps_learned_for: usize,
+    episodes_learned_for: usize,
+    model: Model,
+    target_model: Model,
+    replay_buffer: ReplayBuffer<Transition>,
+    rng: StdRng,
+}
+
+impl Dqn {
+    pub fn new(
+        model: Model,
+        replay_buffer: ReplayBuffer<Transition>,
+        discount_rate: f64,
+        explore_rate: f64,
+    ) -> Self {
+        if !(0.0..=1.0).contains(&discount_rate) {
+            panic!("Discount rate must be between 0.0 and 1.0")
+        }
+        if !(0.0..=1.0).contains(&explore_rate) {
+            panic!("Explore rate must be between 0.0 and 1.0")
+        }
+
+        Self {
+            discount_rate,
+            explore_rate,
+            batch_size: 32,
+            target_update_interval: 500,
+            steps_learned_for: 0,
+            episodes_learned_for: 0,
+            target_model: model.clone_weights(),
+            model,
+            replay_buffer,
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// Seeds exploring, picking starting states and sampling the replay buffer. Together with a
+    /// model built with [`ModelBuilder::set_seed`](crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::ModelBuilder::set_seed)
+    /// the agent learns the same way every run.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// How many experiences are sampled from the replay buffer for each update, defaults to 32.
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// How many steps pass between copying the model into the target network, defaults to 500.
+    pub fn set_target_update_interval(&mut self, target_update_interval: usize) {
+        self.target_update_interval = target_update_interval.max(1);
+    }
+
+    pub fn get_model(&self) -> &Model {
+        &self.model
+    }
+
+    pub fn get_episodes_learned_for(&self) -> usize {
+        self.episodes_learned_for
+    }
+
+    pub fn get_steps_learned_for(&self) -> usize {
+        self.steps_learned_for
+    }
+
+    pub fn learn_for_episode_count<E: StateActionFeatureVector>(
+        &mut self,
+        episode_count: usize,
+        starting_states: Vec<E>,
+    ) {
+        (0..episode_count).for_each(|_| {
+            let starting_state = starting_states.choose(&mut self.rng).unwrap().clone();
+            self.learn_from_episode(starting_state);
+        });
+    }
+
+    pub fn learn_from_episode<E: StateActionFeatureVector>(&mut self, starting_state: E) {
+        let mut state = starting_state;
+        while !state.is_terminal() {
+            let action = self.select_action(&state);
+            let (reward, next_state) = state.take_action(&action);
+
+            let next_state_action_values = if next_state.is_terminal() {
+                Vec::new()
+            } else {
+                next_state
+                    .get_actions()
+                    .iter()
+                    .map(|a| next_state.get_state_action_values(a))
+                    .collect()
+            };
+            self.replay_buffer.push(Transition {
+                state_action_values: state.get_state_action_values(&action),
+                reward,
+                next_state_action_values,
+            });
+
+            self.learn_from_replay();
+
+            self.steps_learned_for += 1;
+            if self
+                .steps_learned_for
+                .is_multiple_of(self.target_update_interval)
+            {
+                self.target_model.copy_weights_from(&self.model);
+            }
+            state = next_state;
+        }
+        self.episodes_learned_for += 1;
+    }
+
+    fn learn_from_replay(&mut self) {
+        if self.replay_buffer.len() < self.batch_size {
+            return;
+        }
+
+        let mut inputs = Vec::with_capacity(self.batch_size);
+        let mut expected = Vec::with_capacity(self.batch_size);
+        let mut weights = Vec::with_capacity(self.batch_size);
+        let mut errors = Vec::with_capacity(self.batch_size);
+
+        for sample in self
+            .replay_buffer
+            .sample_with_rng(self.batch_size, &mut self.rng)
+        {
+            let transition = sample.experience;
+            let best_next_value = self
+                .target_model
+                .predict_batch(&transition.next_state_action_values)
+                .into_iter()
+                .map(|output| output[0])
+                .fold(None, |best: Option<f64>, value| {
+                    Some(best.map_or(value, |b| b.max(value)))
+                })
+                .unwrap_or(0.0);
+            let target = transition.reward + self.discount_rate * best_next_value;
+
+            let predicted = self.model.predict(transition.state_action_values.clone())[0];
+            let error = target - predicted;
+
+            inputs.push(transition.state_action_values.clone());
+            expected.push(vec![target]);
+            weights.push(sample.weight);
+            errors.push((sample.index, error));
+        }
+
+        errors
+            .into_iter()
+            .for_each(|(index, error)| self.replay_buffer.update_priority(index, error));
+        self.model
+            .train_weighted_batch(&inputs, &expected, &weights);
+    }
+
+    fn select_action<E: StateActionFeatureVector>(&mut self, state: &E) -> E::Action {
+        if self.rng.random::<f64>() < self.explore_rate {
+            let actions = state.get_actions();
+            return actions.choose(&mut self.rng).unwrap().clone();
+        }
+
+        self.get_best_action_for_state(state)
+    }
+
+    pub fn get_best_action_for_state<E: StateActionFeatureVector>(&self, state: &E) -> E::Action {
+        let actions = state.get_actions();
+        let inputs = actions
+            .iter()
+            .map(|action| state.get_state_action_values(action))
+            .collect::<Vec<Vec<f64>>>();
+        actions
+            .into_iter()
+            .zip(self.model.predict_batch(&inputs))
+            .map(|(action, output)| (action, output[0]))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .unwrap()
+            .0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::environment::Environment;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+    use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::adam::Adam;
+    use crate::chapter_10::mountain_car::MountainCar;
+    use crate::chess_state_v2::ChessStateV2;
+    use simple_chess::chess_game_state_analyzer::GameState;
+    use simple_chess::{ChessGame, ChessMoveType};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Move {
+        Left,
+        Right,
+    }
+
+    #[derive(Clone)]
+    struct Corridor {
+        position: usize,
+    }
+
+    const CORRIDOR_LENGTH: usize = 5;
+
+    impl Environment for Corridor {
+        type Action = Move;
+        type Observation = usize;
+
+        fn get_observation(&self) -> usize {
+            self.position
+        }
+
+        fn get_actions(&self) -> Vec<Move> {
+            vec![Move::Left, Move::Right]
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.position == CORRIDOR_LENGTH
+        }
+
+        fn take_action(&self, action: &Move) -> (f64, Self) {
+            let position = match action {
+                Move::Left => self.position.saturating_sub(1),
+                Move::Right => self.position + 1,
+            };
+            (-1.0, Self { position })
+        }
+    }
+
+    impl StateActionFeatureVector for Corridor {
+        fn get_state_action_values(&self, action: &Move) -> Vec<f64> {
+            let mut values = vec![0.0; CORRIDOR_LENGTH * 2];
+            let offset = match action {
+                Move::Left => 0,
+                Move::Right => CORRIDOR_LENGTH,
+            };
+            values[offset + self.position] = 1.0;
+            values
+        }
+    }
+
+    fn build_model(input_size: usize) -> Model {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_seed(1)
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_optimizer(Box::new(Adam::new(0.05)))
+            .set_input_size(input_size)
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn learns_to_walk_down_the_corridor() {
+        [
+            ReplayBuffer::uniform(500),
+            ReplayBuffer::prioritized(500, 0.6, 0.4),
+        ]
+        .into_iter()
+        .for_each(|replay_buffer| {
+            let mut agent = Dqn::new(build_model(CORRIDOR_LENGTH * 2), replay_buffer, 1.0, 0.2);
+            agent.set_batch_size(8);
+            agent.set_target_update_interval(20);
+            agent.learn_for_episode_count(150, vec![Corridor { position: 0 }]);
+
+            (0..CORRIDOR_LENGTH).for_each(|position| {
+                assert_eq!(
+                    agent.get_best_action_for_state(&Corridor { position }),
+                    Move::Right,
+                    "wrong action at position {}",
+                    position
+                );
+            });
+        });
+    }
+
+    #[test]
+    fn same_seed_learns_the_same_model() {
+        let learn = |seed: u64| {
+            let mut agent = Dqn::new(
+                build_model(CORRIDOR_LENGTH * 2),
+                ReplayBuffer::prioritized(100, 0.6, 0.4),
+                1.0,
+                0.3,
+            );
+            agent.set_seed(seed);
+            agent.set_batch_size(4);
+            agent.learn_for_episode_count(10, vec![Corridor { position: 0 }]);
+            (0..CORRIDOR_LENGTH)
+                .map(|position| {
+                    let state = Corridor { position };
+                    agent
+                        .get_model()
+                        .predict(state.get_state_action_values(&Move::Right))[0]
+                })
+                .collect::<Vec<f64>>()
+        };
+
+        assert_eq!(learn(3), learn(3));
+        assert_ne!(learn(3), learn(4));
+    }
+
+    #[test]
+    fn works_with_mountain_car() {
+        let mut agent = Dqn::new(build_model(48), ReplayBuffer::uniform(1000), 1.0, 0.1);
+        agent.set_batch_size(4);
+        // starting close to the top moving forwards, so episodes are short
+        agent.learn_for_episode_count(3, vec![MountainCar::new(0.4, 0.05)]);
+
+        assert_eq!(agent.get_episodes_learned_for(), 3);
+        assert!(agent.get_steps_learned_for() >= 3);
+    }
+
+    #[test]
+    fn chess_states_have_fixed_size_action_values() {
+        let pick_first_move = |game: &mut ChessGame| -> ChessMoveType {
+            match game.get_game_state() {
+                GameState::InProgress { legal_moves, .. } => legal_moves[0],
+                GameState::Check { legal_moves, .. } => legal_moves[0],
+                _ => panic!("no moves to make"),
+            }
+        };
+        let state = ChessStateV2::new(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+            pick_first_move,
+        );
+
+        let actions = Environment::get_actions(&state);
+        assert_eq!(actions.len(), 20);
+        actions.iter().for_each(|action| {
+            let values = state.get_state_action_values(action);
+            assert_eq!(values.len(), 790 + 130);
+            assert_eq!(values[790..].iter().sum::<f64>(), 2.0);
+        });
+
+        let agent = Dqn::new(build_model(920), ReplayBuffer::uniform(10), 1.0, 0.1);
+        assert!(actions.contains(&agent.get_best_action_for_state(&state)));
+    }
+}
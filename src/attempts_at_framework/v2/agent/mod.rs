@@ -1,2 +1,4 @@
+pub mod action_values;
+pub mod dqn;
 pub mod n_step_sarsa;
 pub mod n_step_td;
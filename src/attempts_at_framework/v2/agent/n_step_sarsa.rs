@@ -1,7 +1,9 @@
+use crate::attempts_at_framework::environment::FeatureVector;
+use crate::attempts_at_framework::v2::agent::action_values::ActionValueModel;
 use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
-use crate::attempts_at_framework::v2::state::State;
 use rand::prelude::IndexedRandom;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 pub struct NStepSarsa {
     n: usize,
@@ -10,6 +12,8 @@ pub struct NStepSarsa {
     explore_rate: f64,
     episodes_learned_for: usize,
     model: Model,
+    action_value_model: ActionValueModel,
+    rng: StdRng,
 }
 
 impl NStepSarsa {
@@ -27,16 +31,30 @@ impl NStepSarsa {
             explore_rate,
             episodes_learned_for: 0,
             model,
+            action_value_model: ActionValueModel::ActionBlocks,
+            rng: StdRng::from_os_rng(),
         }
     }
 
-    pub fn learn_from_episode<S: State>(&mut self, starting_state: S) {
+    /// Seeds the exploring moves, with the same seed and model the agent learns the same way
+    /// every run.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// How the model values actions, defaults to [`ActionValueModel::ActionBlocks`]. Use
+    /// [`ActionValueModel::MultiHead`] with a model that has one output per action.
+    pub fn set_action_value_model(&mut self, action_value_model: ActionValueModel) {
+        self.action_value_model = action_value_model;
+    }
+
+    pub fn learn_from_episode<E: FeatureVector>(&mut self, starting_state: E) {
         let mut terminal_time: Option<usize> = None;
         let mut current_state = starting_state;
         let mut current_action = self.select_action(&current_state);
-        let mut next_state: Option<S> = None;
-        let mut next_action: Option<String> = None;
-        let mut states_actions_and_rewards: Vec<(S, String, f64)> = Vec::new();
+        let mut next_state: Option<E> = None;
+        let mut next_action: Option<E::Action> = None;
+        let mut states_actions_and_rewards: Vec<(E, E::Action, f64)> = Vec::new();
         states_actions_and_rewards.push((current_state.clone(), current_action.clone(), 0.0));
 
         for time_step in 0..usize::MAX {
@@ -76,8 +94,11 @@ impl NStepSarsa {
                     let adjusted_discount_rate = self.discount_rate.powi(self.n as i32);
                     let (s, a, _) =
                         &states_actions_and_rewards[time_step_to_update as usize + self.n];
-                    let s_values = self.adjust_values(s, a.clone());
-                    let state_value = self.model.predict(s_values)[0];
+                    let state_value = self.action_value_model.get_action_value(
+                        &self.model,
+                        s,
+                        Self::get_action_index(s, a),
+                    );
                     state_value * adjusted_discount_rate
                 } else {
                     0.0
@@ -86,11 +107,11 @@ impl NStepSarsa {
                 let total_reward = sum_of_rewards + state_value_at_r_plus_n;
                 let (state_to_update, action_chosen, _) =
                     &states_actions_and_rewards[(time_step_to_update - 1).max(0) as usize];
-                let adjusted_values_of_state =
-                    self.adjust_values(state_to_update, action_chosen.clone());
-                self.model.train(
-                    adjusted_values_of_state,
-                    vec![total_reward],
+                self.action_value_model.train(
+                    &mut self.model,
+                    state_to_update,
+                    Self::get_action_index(state_to_update, action_chosen),
+                    total_reward,
                     self.learning_rate,
                 );
             }
@@ -113,52 +134,75 @@ impl NStepSarsa {
         self.episodes_learned_for += 1;
     }
 
-    fn select_action<S: State>(&self, state: &S) -> String {
-        let mut rng = rand::rng();
-        if rng.random::<f64>() < self.explore_rate {
+    fn select_action<E: FeatureVector>(&mut self, state: &E) -> E::Action {
+        if self.rng.random::<f64>() < self.explore_rate {
             let actions = state.get_actions();
-            return actions.choose(&mut rng).unwrap().clone();
+            return actions.choose(&mut self.rng).unwrap().clone();
         }
 
         self.get_best_action_for_state(state)
     }
 
-    pub fn get_best_action_for_state<S: State>(&self, state: &S) -> String {
-        let actions: Vec<(String, f64)> = state
-            .get_actions()
-            .iter()
-            .map(|action| {
-                let adjusted_values = self.adjust_values(state, action.clone());
-                let estimated_value = self.model.predict(adjusted_values)[0];
-                (action.clone(), estimated_value)
-            })
-            .collect();
-
-        let mut best_action = actions[0].clone();
-        for action in actions {
-            if action.1 > best_action.1 {
-                best_action = action;
+    pub fn get_best_action_for_state<E: FeatureVector>(&self, state: &E) -> E::Action {
+        let actions = state.get_actions();
+        let values = self
+            .action_value_model
+            .get_action_values(&self.model, state);
+
+        let mut best_index = 0;
+        for (index, value) in values.iter().enumerate() {
+            if *value > values[best_index] {
+                best_index = index;
             }
         }
-        best_action.0
+        actions[best_index].clone()
     }
 
-    fn adjust_values<S: State>(&self, state: &S, action: String) -> Vec<f64> {
-        let values = state.get_values();
-        let action_count = state.get_actions().len();
-        let action_index = state
+    fn get_action_index<E: FeatureVector>(state: &E, action: &E::Action) -> usize {
+        state
             .get_actions()
             .iter()
-            .position(|a| a == &action)
-            .unwrap();
-
-        let mut result = vec![0.0; values.len() * action_count];
-        let start_index = action_index * values.len();
-        result[start_index..start_index + values.len()].copy_from_slice(&values);
-        result
+            .position(|a| a == action)
+            .unwrap()
     }
 
     pub fn print_weights(&self) {
         self.model.print_weights();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::agent::action_values::tests::{
+        build_model, Corridor, CORRIDOR_LENGTH,
+    };
+
+    #[test]
+    fn learns_the_corridor_with_either_model() {
+        [
+            (
+                ActionValueModel::ActionBlocks,
+                build_model(CORRIDOR_LENGTH * 2, 1),
+            ),
+            (ActionValueModel::MultiHead, build_model(CORRIDOR_LENGTH, 2)),
+        ]
+        .into_iter()
+        .for_each(|(action_value_model, model)| {
+            let mut agent = NStepSarsa::new(2, 1.0, 0.2, 0.1, model);
+            agent.set_action_value_model(action_value_model);
+            agent.set_seed(5);
+            (0..200).for_each(|_| agent.learn_from_episode(Corridor { position: 0 }));
+
+            (0..CORRIDOR_LENGTH).for_each(|position| {
+                assert_eq!(
+                    agent.get_best_action_for_state(&Corridor { position }),
+                    "right",
+                    "{:?} picked the wrong action at position {}",
+                    action_value_model,
+                    position
+                );
+            });
+        });
+    }
+}
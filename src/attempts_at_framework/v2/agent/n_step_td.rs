@@ -1,6 +1,8 @@
+use crate::attempts_at_framework::environment::FeatureVector;
+use crate::attempts_at_framework::v2::agent::action_values::ActionValueModel;
 use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
-use crate::attempts_at_framework::v2::state::State;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::collections::VecDeque;
 
 pub struct NStepTD {
@@ -10,6 +12,9 @@ pub struct NStepTD {
     explore_rate: f64,
     episodes_learned_for: usize,
     model: Model,
+    /// `None` when the model values states rather than actions
+    action_value_model: Option<ActionValueModel>,
+    rng: StdRng,
 }
 
 impl NStepTD {
@@ -21,22 +26,39 @@ impl NStepTD {
             explore_rate: 0.1,
             discount_rate: 1.0,
             episodes_learned_for: 0,
+            action_value_model: None,
+            rng: StdRng::from_os_rng(),
         }
     }
 
-    pub fn learn_from_episode<S: State>(&mut self, starting_state: S) {
+    /// Seeds the exploring moves, with the same seed and model the agent learns the same way
+    /// every run.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Learns action values rather than state values, so a model like a multi-head Q-network
+    /// with one output per action can be used. The value of a state is then the value of its best
+    /// action, and actions are picked by their values rather than by the states they lead to.
+    pub fn set_action_value_model(&mut self, action_value_model: ActionValueModel) {
+        self.action_value_model = Some(action_value_model);
+    }
+
+    /// Plays an episode from `starting_state`, learning as it goes, and returns the terminal state
+    /// it ended in.
+    pub fn learn_from_episode<E: FeatureVector>(&mut self, starting_state: E) -> E {
         let mut current_state = starting_state;
-        let mut states_queue: VecDeque<S> = VecDeque::new();
+        let mut states_queue: VecDeque<(E, E::Action)> = VecDeque::new();
         let mut rewards_queue: VecDeque<f64> = VecDeque::new();
 
         while !current_state.is_terminal() {
-            let action: String = self.select_next_action(&current_state);
+            let action = self.select_next_action(&current_state);
             let (reward, next_state) = current_state.take_action(&action);
-            states_queue.push_back(current_state);
+            states_queue.push_back((current_state, action));
             rewards_queue.push_back(reward);
 
             if states_queue.len() >= self.n {
-                let old_state = states_queue.pop_front().unwrap();
+                let (old_state, old_action) = states_queue.pop_front().unwrap();
                 let mut n_step_return = 0.0;
 
                 for (i, r) in rewards_queue.iter().enumerate() {
@@ -44,15 +66,11 @@ impl NStepTD {
                 }
 
                 if !next_state.is_terminal() {
-                    n_step_return += self.discount_rate.powi(self.n as i32)
-                        * self.model.predict(next_state.get_values())[0];
+                    n_step_return +=
+                        self.discount_rate.powi(self.n as i32) * self.get_state_value(&next_state);
                 }
 
-                self.model.train(
-                    old_state.get_values(),
-                    vec![n_step_return],
-                    self.learning_rate,
-                );
+                self.train_towards(&old_state, &old_action, n_step_return);
 
                 rewards_queue.pop_front();
             }
@@ -61,21 +79,52 @@ impl NStepTD {
         }
 
         while !states_queue.is_empty() {
-            let old_state = states_queue.pop_front().unwrap();
+            let (old_state, old_action) = states_queue.pop_front().unwrap();
             let mut n_step_return = 0.0;
 
             for (i, r) in rewards_queue.iter().enumerate() {
                 n_step_return += r * self.discount_rate.powi(i as i32);
             }
 
-            self.model.train(
-                old_state.get_values(),
-                vec![n_step_return],
-                self.learning_rate,
-            );
+            self.train_towards(&old_state, &old_action, n_step_return);
 
             rewards_queue.pop_front();
         }
+
+        current_state
+    }
+
+    fn get_state_value<E: FeatureVector>(&self, state: &E) -> f64 {
+        match &self.action_value_model {
+            None => self.model.predict(state.get_values())[0],
+            Some(action_value_model) => action_value_model
+                .get_action_values(&self.model, state)
+                .into_iter()
+                .fold(f64::MIN, f64::max),
+        }
+    }
+
+    fn train_towards<E: FeatureVector>(&mut self, state: &E, action: &E::Action, target: f64) {
+        match &self.action_value_model {
+            None => {
+                self.model
+                    .train(state.get_values(), vec![target], self.learning_rate);
+            }
+            Some(action_value_model) => {
+                let action_index = state
+                    .get_actions()
+                    .iter()
+                    .position(|a| a == action)
+                    .unwrap();
+                action_value_model.train(
+                    &mut self.model,
+                    state,
+                    action_index,
+                    target,
+                    self.learning_rate,
+                );
+            }
+        }
     }
 
     /// TODO fix this, this is the real problem
@@ -94,27 +143,45 @@ impl NStepTD {
     /// Ugh according to DeepSeek its best to use a Policy Gradient method
     /// for this type of task, that's covered in chapter 13. So time to get
     /// back to reading, but ill be back!c
-    fn select_next_action<S: State>(&self, state: &S) -> String {
-        if rand::rng().random::<f64>() < self.explore_rate {
+    fn select_next_action<E: FeatureVector>(&mut self, state: &E) -> E::Action {
+        if self.rng.random::<f64>() < self.explore_rate {
             let actions = state.get_actions();
-            let random_index = rand::rng().random_range(0..actions.len());
+            let random_index = self.rng.random_range(0..actions.len());
             return actions[random_index].clone();
         }
         self.select_best_action_for_state(state)
     }
 
-    pub fn select_best_action_for_state<S: State>(&self, state: &S) -> String {
+    pub fn select_best_action_for_state<E: FeatureVector>(&self, state: &E) -> E::Action {
         let actions = state.get_actions();
+        if let Some(action_value_model) = &self.action_value_model {
+            let values = action_value_model.get_action_values(&self.model, state);
+            let mut best_index = 0;
+            for (index, value) in values.iter().enumerate() {
+                if *value > values[best_index] {
+                    best_index = index;
+                }
+            }
+            return actions[best_index].clone();
+        }
+
         let mut best_value = f64::MIN;
         let mut best_action = actions.first().unwrap().clone();
 
-        for action in actions {
-            let state_clone = state.clone();
-            let (reward, next_state) = state_clone.take_action(&action);
-            let value = self.model.predict(next_state.get_values())[0];
+        let (rewards, next_state_values): (Vec<f64>, Vec<Vec<f64>>) = actions
+            .iter()
+            .map(|action| {
+                let (reward, next_state) = state.take_action(action);
+                (reward, next_state.get_values())
+            })
+            .unzip();
+        let values = self.model.predict_batch(&next_state_values);
+
+        for ((action, reward), value) in actions.iter().zip(rewards).zip(values) {
+            let value = value[0];
             if (value + reward) > best_value {
                 best_action = action.clone();
-                best_value = value;
+                best_value = value + reward;
             }
         }
 
@@ -152,4 +219,89 @@ impl NStepTD {
     pub fn get_model(&self) -> &Model {
         &self.model
     }
+
+    pub fn set_model(&mut self, model: Model) {
+        self.model = model;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::environment::Environment;
+    use crate::attempts_at_framework::v2::agent::action_values::tests::{
+        build_model, Corridor, CORRIDOR_LENGTH,
+    };
+
+    /// A single choice between two rewards that both end the episode.
+    #[derive(Debug, Clone)]
+    struct Fork {
+        position: usize,
+    }
+
+    impl Environment for Fork {
+        type Action = String;
+        type Observation = usize;
+
+        fn get_observation(&self) -> usize {
+            self.position
+        }
+
+        fn get_actions(&self) -> Vec<String> {
+            vec!["cash".to_string(), "wait".to_string()]
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.position != 0
+        }
+
+        fn take_action(&self, action: &String) -> (f64, Self) {
+            match action.as_str() {
+                "cash" => (10.0, Self { position: 1 }),
+                _ => (5.0, Self { position: 2 }),
+            }
+        }
+    }
+
+    impl FeatureVector for Fork {
+        fn get_values(&self) -> Vec<f64> {
+            let mut values = vec![0.0; 3];
+            values[self.position] = 1.0;
+            values
+        }
+    }
+
+    #[test]
+    fn picks_the_best_reward_plus_value() {
+        // the model values every state at 0, so the rewards alone pick the action
+        let agent = NStepTD::new(1, build_model(3, 1), 0.1);
+        assert_eq!(
+            agent.select_best_action_for_state(&Fork { position: 0 }),
+            "cash"
+        );
+    }
+
+    #[test]
+    fn learns_action_values_with_a_multi_head_model() {
+        let mut agent = NStepTD::new(2, build_model(CORRIDOR_LENGTH, 2), 0.2);
+        agent.set_action_value_model(ActionValueModel::MultiHead);
+        agent.set_seed(5);
+        (0..200).for_each(|_| {
+            agent.learn_from_episode(Corridor { position: 0 });
+        });
+
+        (0..CORRIDOR_LENGTH).for_each(|position| {
+            let state = Corridor { position };
+            assert_eq!(agent.select_best_action_for_state(&state), "right");
+            // every step costs 1, so going right from here is worth minus the steps left
+            let value = ActionValueModel::MultiHead.get_action_value(agent.get_model(), &state, 1);
+            let steps_left = (CORRIDOR_LENGTH - position) as f64;
+            assert!(
+                (value + steps_left).abs() < 0.5,
+                "right from {} was worth {}",
+                position,
+                value
+            );
+        });
+    }
 }
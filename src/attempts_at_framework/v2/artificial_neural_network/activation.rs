@@ -0,0 +1,267 @@
+/// the slope of [`Activation::LeakyRelu`] below zero
+pub const LEAKY_RELU_SLOPE: f64 = 0.01;
+/// how far below zero [`Activation::Elu`] can go
+pub const ELU_ALPHA: f64 = 1.0;
+
+/// # Activation
+///
+/// The function applied to every output of a [`Layer`](super::layer::Layer), a layer has exactly
+/// one activation rather than every neuron carrying its own.
+///
+/// Softmax is the odd one out, each of its outputs depends on every weighted sum of the layer, so
+/// layers go through [`Activation::apply_to_outputs`] and [`Activation::backwards`] rather than
+/// the single value methods.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Activation {
+    Linear,
+    Relu,
+    Sigmoid,
+    Softmax,
+    Tanh,
+    /// relu that lets [`LEAKY_RELU_SLOPE`] of negative values through, so units can't die
+    LeakyRelu,
+    /// smooth below zero, heading to `-ELU_ALPHA`
+    Elu,
+}
+
+impl Activation {
+    pub fn apply(&self, value: f64) -> f64 {
+        match self {
+            Activation::Linear => value,
+            Activation::Relu => value.max(0.0),
+            Activation::Sigmoid => 1.0 / (1.0 + (-value).exp()),
+            // the softmax of a lone value
+            Activation::Softmax => 1.0,
+            Activation::Tanh => value.tanh(),
+            Activation::LeakyRelu => {
+                if value > 0.0 {
+                    value
+                } else {
+                    LEAKY_RELU_SLOPE * value
+                }
+            }
+            Activation::Elu => {
+                if value > 0.0 {
+                    value
+                } else {
+                    ELU_ALPHA * (value.exp() - 1.0)
+                }
+            }
+        }
+    }
+
+    /// Applies the activation to all the weighted sums of one sample in place.
+    pub fn apply_to_outputs(&self, values: &mut [f64]) {
+        match self {
+            Activation::Softmax => {
+                // subtracting the largest value keeps exp from overflowing without changing the result
+                let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                values
+                    .iter_mut()
+                    .for_each(|value| *value = (*value - max).exp());
+                let total = values.iter().sum::<f64>();
+                values.iter_mut().for_each(|value| *value /= total);
+            }
+            _ => values
+                .iter_mut()
+                .for_each(|value| *value = self.apply(*value)),
+        }
+    }
+
+    /// The derivative of the activation, worked out from the value it output rather than its
+    /// input, so the backwards pass only needs what the forward pass already produced.
+    pub fn derivative(&self, output: f64) -> f64 {
+        match self {
+            Activation::Linear => 1.0,
+            Activation::Relu => {
+                if output > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Activation::Sigmoid => output * (1.0 - output),
+            // only the diagonal of the jacobian, see `backwards`
+            Activation::Softmax => output * (1.0 - output),
+            Activation::Tanh => 1.0 - output.powi(2),
+            // both only output positive values for positive inputs
+            Activation::LeakyRelu => {
+                if output > 0.0 {
+                    1.0
+                } else {
+                    LEAKY_RELU_SLOPE
+                }
+            }
+            Activation::Elu => {
+                if output > 0.0 {
+                    1.0
+                } else {
+                    output + ELU_ALPHA
+                }
+            }
+        }
+    }
+
+    /// Takes how the loss changes with each output of one sample and returns how it changes with
+    /// each weighted sum. For softmax that is `y_i * (g_i - Σ g_j y_j)`, every other activation
+    /// only needs its own derivative.
+    pub fn backwards(&self, outputs: &[f64], gradient: &[f64]) -> Vec<f64> {
+        match self {
+            Activation::Softmax => {
+                let weighted_total = outputs
+                    .iter()
+                    .zip(gradient)
+                    .map(|(y, g)| y * g)
+                    .sum::<f64>();
+                outputs
+                    .iter()
+                    .zip(gradient)
+                    .map(|(y, g)| y * (g - weighted_total))
+                    .collect()
+            }
+            _ => outputs
+                .iter()
+                .zip(gradient)
+                .map(|(y, g)| g * self.derivative(*y))
+                .collect(),
+        }
+    }
+
+    /// the name written to, and read from, saved model files for this activation
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Activation::Linear => "linear",
+            Activation::Relu => "relu",
+            Activation::Sigmoid => "sigmoid",
+            Activation::Softmax => "softmax",
+            Activation::Tanh => "tanh",
+            Activation::LeakyRelu => "leaky_relu",
+            Activation::Elu => "elu",
+        }
+    }
+
+    /// Looks up an activation by the name returned from [`Activation::get_name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Activation::Linear),
+            "relu" => Some(Activation::Relu),
+            "sigmoid" => Some(Activation::Sigmoid),
+            "softmax" => Some(Activation::Softmax),
+            "tanh" => Some(Activation::Tanh),
+            "leaky_relu" => Some(Activation::LeakyRelu),
+            "elu" => Some(Activation::Elu),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_activation() {
+        assert_eq!(Activation::Sigmoid.apply(0.0), 0.5);
+        assert_eq!(Activation::Relu.apply(-1.5), 0.0);
+        assert_eq!(Activation::Relu.apply(0.5), 0.5);
+        assert_eq!(Activation::Linear.apply(-3.0), -3.0);
+        assert_eq!(Activation::Tanh.apply(0.0), 0.0);
+        assert_eq!(Activation::LeakyRelu.apply(-2.0), -0.02);
+        assert_eq!(Activation::Elu.apply(2.0), 2.0);
+        assert!(Activation::Elu.apply(-100.0) > -ELU_ALPHA - 1e-12);
+    }
+
+    #[test]
+    fn derivatives_match_the_change_in_output() {
+        let epsilon = 1e-6;
+        [
+            Activation::Linear,
+            Activation::Relu,
+            Activation::Sigmoid,
+            Activation::Tanh,
+            Activation::LeakyRelu,
+            Activation::Elu,
+        ]
+        .iter()
+        .for_each(|activation| {
+            // steering clear of 0.0, where relu and its kin have a kink
+            [-2.5, -0.7, -0.1, 0.2, 1.3, 4.0].iter().for_each(|value| {
+                let numerical =
+                    (activation.apply(value + epsilon) - activation.apply(*value)) / epsilon;
+                let derivative = activation.derivative(activation.apply(*value));
+                assert!(
+                    (numerical - derivative).abs() < 1e-4,
+                    "{} at {} had a derivative of {} but expected {}",
+                    activation.get_name(),
+                    value,
+                    derivative,
+                    numerical
+                );
+            });
+        });
+    }
+
+    #[test]
+    fn names_round_trip() {
+        [
+            Activation::Linear,
+            Activation::Relu,
+            Activation::Sigmoid,
+            Activation::Softmax,
+            Activation::Tanh,
+            Activation::LeakyRelu,
+            Activation::Elu,
+        ]
+        .iter()
+        .for_each(|activation| {
+            assert_eq!(
+                Activation::from_name(activation.get_name()),
+                Some(*activation)
+            );
+        });
+    }
+
+    #[test]
+    fn softmax_outputs_sum_to_one() {
+        let mut values = vec![1.0, 2.0, 3.0, 1000.0];
+        Activation::Softmax.apply_to_outputs(&mut values);
+        assert!((values.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(values[3] > 0.99);
+
+        let mut even = vec![0.5, 0.5];
+        Activation::Softmax.apply_to_outputs(&mut even);
+        assert_eq!(even, vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn softmax_backwards_matches_the_change_in_outputs() {
+        let sums = vec![0.3, -1.2, 0.8];
+        let gradient = vec![1.0, -0.5, 2.0];
+        let softmax = |sums: &[f64]| {
+            let mut outputs = sums.to_vec();
+            Activation::Softmax.apply_to_outputs(&mut outputs);
+            outputs
+        };
+        let outputs = softmax(&sums);
+        let backwards = Activation::Softmax.backwards(&outputs, &gradient);
+
+        let epsilon = 1e-6;
+        (0..sums.len()).for_each(|index| {
+            let mut nudged = sums.clone();
+            nudged[index] += epsilon;
+            let numerical = softmax(&nudged)
+                .iter()
+                .zip(outputs.iter())
+                .zip(gradient.iter())
+                .map(|((after, before), g)| g * (after - before) / epsilon)
+                .sum::<f64>();
+            assert!(
+                (numerical - backwards[index]).abs() < 1e-4,
+                "sum {} had a gradient of {} but expected {}",
+                index,
+                backwards[index],
+                numerical
+            );
+        });
+    }
+}
@@ -0,0 +1,246 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::activation::Activation;
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+use rand::{Rng, RngCore};
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::PathBuf;
+
+/// # Initialization
+///
+/// How a layer's starting weights and biases are picked. Only [`Initialization::Constant`] and
+/// [`Initialization::FromFile`] start the biases anywhere other than 0.0.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Initialization {
+    /// What layers have always started with, random weights between 0.1 and 0.9 for relu and its
+    /// kin and 1.0 for everything else. All positive weights are prone to dead or saturated units.
+    Default,
+    /// Xavier/Glorot uniform, weights between `±sqrt(6 / (inputs + outputs))`, suits sigmoid and
+    /// tanh layers.
+    Xavier,
+    /// He uniform, weights between `±sqrt(6 / inputs)`, suits relu layers.
+    He,
+    Uniform {
+        min: f64,
+        max: f64,
+    },
+    Zeros,
+    /// every weight and bias set to the value
+    Constant(f64),
+    /// The weights and biases of layer `layer_index` of a model saved with [`Model::save`], which
+    /// must be the same shape, handy for starting a new model from part of an old one.
+    FromFile {
+        path: PathBuf,
+        layer_index: usize,
+    },
+}
+
+impl Initialization {
+    /// Returns the weights, row major with a row of `input_count` weights per output, and the
+    /// biases of a new layer. For a convolution each output channel is an output, with a kernel's
+    /// worth of inputs.
+    pub fn initialize(
+        &self,
+        activation: Activation,
+        output_count: usize,
+        input_count: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<(Vec<f64>, Vec<f64>), InitializationError> {
+        let weight_count = output_count * input_count;
+        let mut uniform = |limit: f64| -> Vec<f64> {
+            (0..weight_count)
+                .map(|_| rng.random_range(-limit..=limit))
+                .collect()
+        };
+        let weights = match self {
+            Initialization::Default => match activation {
+                Activation::Relu | Activation::LeakyRelu | Activation::Elu => (0..weight_count)
+                    .map(|_| rng.random_range(0.1..0.9))
+                    .collect(),
+                Activation::Linear
+                | Activation::Sigmoid
+                | Activation::Softmax
+                | Activation::Tanh => vec![1.0; weight_count],
+            },
+            Initialization::Xavier => {
+                uniform((6.0 / (input_count + output_count).max(1) as f64).sqrt())
+            }
+            Initialization::He => uniform((6.0 / input_count.max(1) as f64).sqrt()),
+            Initialization::Uniform { min, max } => {
+                if min > max {
+                    return Err(InitializationError::new(format!(
+                        "uniform range {}..{} is empty",
+                        min, max
+                    )));
+                }
+                (0..weight_count)
+                    .map(|_| rng.random_range(*min..=*max))
+                    .collect()
+            }
+            Initialization::Zeros => vec![0.0; weight_count],
+            Initialization::Constant(value) => {
+                return Ok((vec![*value; weight_count], vec![*value; output_count]))
+            }
+            Initialization::FromFile { path, layer_index } => {
+                let model = Model::load(path).map_err(|e| {
+                    InitializationError::new(format!(
+                        "could not load layer {} from {}: {}",
+                        layer_index,
+                        path.display(),
+                        e
+                    ))
+                })?;
+                let layer = model.get_layers().get(*layer_index).ok_or_else(|| {
+                    InitializationError::new(format!(
+                        "{} only has {} layers, there is no layer {}",
+                        path.display(),
+                        model.get_layers().len(),
+                        layer_index
+                    ))
+                })?;
+                if layer.get_weights().len() != weight_count
+                    || layer.get_biases().len() != output_count
+                {
+                    return Err(InitializationError::new(format!(
+                        "layer {} of {} has {} weights and {} biases, but {} weights and {} biases are needed",
+                        layer_index,
+                        path.display(),
+                        layer.get_weights().len(),
+                        layer.get_biases().len(),
+                        weight_count,
+                        output_count
+                    )));
+                }
+                return Ok((layer.get_weights().to_vec(), layer.get_biases().to_vec()));
+            }
+        };
+        Ok((weights, vec![0.0; output_count]))
+    }
+}
+
+pub struct InitializationError {
+    message: String,
+}
+
+impl InitializationError {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl Debug for InitializationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "InitializationError: {}", self.message)
+    }
+}
+
+impl Display for InitializationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "InitializationError: {}", self.message)
+    }
+}
+
+impl Error for InitializationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn initialize(initialization: Initialization) -> (Vec<f64>, Vec<f64>) {
+        initialization
+            .initialize(Activation::Relu, 8, 4, &mut StdRng::seed_from_u64(1))
+            .unwrap()
+    }
+
+    #[test]
+    fn random_weights_stay_in_range() {
+        let xavier_limit = (6.0_f64 / 12.0).sqrt();
+        let he_limit = (6.0_f64 / 4.0).sqrt();
+        [
+            (Initialization::Xavier, -xavier_limit, xavier_limit),
+            (Initialization::He, -he_limit, he_limit),
+            (
+                Initialization::Uniform {
+                    min: -0.2,
+                    max: 0.3,
+                },
+                -0.2,
+                0.3,
+            ),
+        ]
+        .into_iter()
+        .for_each(|(initialization, min, max)| {
+            let (weights, biases) = initialize(initialization.clone());
+            assert_eq!(weights.len(), 32);
+            assert!(
+                weights.iter().all(|w| (min..=max).contains(w)),
+                "{:?} went out of range: {:?}",
+                initialization,
+                weights
+            );
+            // unlike the default, these should give both signs
+            assert!(weights.iter().any(|w| *w < 0.0) && weights.iter().any(|w| *w > 0.0));
+            assert_eq!(biases, vec![0.0; 8]);
+        });
+    }
+
+    #[test]
+    fn fixed_values() {
+        assert_eq!(
+            initialize(Initialization::Zeros),
+            (vec![0.0; 32], vec![0.0; 8])
+        );
+        assert_eq!(
+            initialize(Initialization::Constant(0.5)),
+            (vec![0.5; 32], vec![0.5; 8])
+        );
+        assert!(Initialization::Uniform { min: 1.0, max: 0.0 }
+            .initialize(Activation::Relu, 1, 1, &mut StdRng::seed_from_u64(1))
+            .is_err());
+    }
+
+    #[test]
+    fn from_file_copies_a_saved_layer() {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(3)
+            .add_layer(LayerBuilder::new(LayerType::RELU, 4).set_initialization(Initialization::He))
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        let saved = builder.build().unwrap();
+        let path = std::env::temp_dir().join("reinforcement_learning_initialization_test.model");
+        saved.save(&path).unwrap();
+
+        let from_file = |layer_index: usize| Initialization::FromFile {
+            path: path.clone(),
+            layer_index,
+        };
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(3)
+            .add_layer(LayerBuilder::new(LayerType::RELU, 4).set_initialization(from_file(0)))
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1).set_initialization(from_file(1)));
+        let loaded = builder.build();
+
+        let mut wrong_shape = ModelBuilder::new();
+        wrong_shape
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(2)
+            .add_layer(LayerBuilder::new(LayerType::RELU, 4).set_initialization(from_file(0)));
+        let wrong_shape = wrong_shape.build();
+        let missing_layer =
+            from_file(2).initialize(Activation::Linear, 1, 4, &mut StdRng::seed_from_u64(1));
+        std::fs::remove_file(&path).unwrap();
+
+        let input = vec![0.3, -0.7, 2.0];
+        assert_eq!(saved.predict(input.clone()), loaded.unwrap().predict(input));
+        assert!(wrong_shape.is_err());
+        assert!(missing_layer.is_err());
+    }
+}
@@ -0,0 +1,296 @@
+/// # Conv 2D Shape
+///
+/// The shape of a 2D convolution. Inputs and outputs stay flat like every other layer's, laid out
+/// channel by channel and each channel row by row, so `(channel, y, x)` of the input is at
+/// `(channel * input_height + y) * input_width + x`. Twelve 8x8 piece planes one after the other,
+/// like the start of [`ChessStateV2`](crate::chess_state_v2::ChessStateV2)'s values, are 12
+/// input channels of height and width 8.
+///
+/// Each output channel has one kernel of `input_channels * kernel_size * kernel_size` weights and
+/// a bias, the layer's weights are those kernels one after the other. Padding is zeros.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conv2DShape {
+    pub input_channels: usize,
+    pub input_height: usize,
+    pub input_width: usize,
+    pub output_channels: usize,
+    pub kernel_size: usize,
+    pub stride: usize,
+    pub padding: usize,
+}
+
+impl Conv2DShape {
+    /// A stride of 1 and no padding, change the fields for anything else.
+    pub fn new(
+        input_channels: usize,
+        input_height: usize,
+        input_width: usize,
+        output_channels: usize,
+        kernel_size: usize,
+    ) -> Self {
+        Self {
+            input_channels,
+            input_height,
+            input_width,
+            output_channels,
+            kernel_size,
+            stride: 1,
+            padding: 0,
+        }
+    }
+
+    pub fn get_output_height(&self) -> usize {
+        (self.input_height + 2 * self.padding - self.kernel_size) / self.stride + 1
+    }
+
+    pub fn get_output_width(&self) -> usize {
+        (self.input_width + 2 * self.padding - self.kernel_size) / self.stride + 1
+    }
+
+    pub fn get_input_count(&self) -> usize {
+        self.input_channels * self.input_height * self.input_width
+    }
+
+    pub fn get_output_count(&self) -> usize {
+        self.output_channels * self.get_output_height() * self.get_output_width()
+    }
+
+    /// the number of weights in each output channel's kernel
+    pub fn get_kernel_weight_count(&self) -> usize {
+        self.input_channels * self.kernel_size * self.kernel_size
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.input_channels == 0 || self.output_channels == 0 || self.kernel_size == 0 {
+            return Err("a convolution needs at least one channel in and out, and a kernel".into());
+        }
+        if self.stride == 0 {
+            return Err("a convolution's stride must be at least 1".to_string());
+        }
+        if self.kernel_size > self.input_height + 2 * self.padding
+            || self.kernel_size > self.input_width + 2 * self.padding
+        {
+            return Err(format!(
+                "a kernel of {} doesn't fit a {}x{} input padded by {}",
+                self.kernel_size, self.input_height, self.input_width, self.padding
+            ));
+        }
+        Ok(())
+    }
+
+    /// Calls `connection(output_index, weight_index, input_index)` for every kernel weight that
+    /// lands on the input, rather than the padding, for a single sample.
+    fn for_each_connection(&self, mut connection: impl FnMut(usize, usize, usize)) {
+        let output_height = self.get_output_height();
+        let output_width = self.get_output_width();
+        for output_channel in 0..self.output_channels {
+            for output_y in 0..output_height {
+                for output_x in 0..output_width {
+                    let output_index =
+                        (output_channel * output_height + output_y) * output_width + output_x;
+                    for input_channel in 0..self.input_channels {
+                        for kernel_y in 0..self.kernel_size {
+                            let input_y = (output_y * self.stride + kernel_y) as isize
+                                - self.padding as isize;
+                            if input_y < 0 || input_y >= self.input_height as isize {
+                                continue;
+                            }
+                            for kernel_x in 0..self.kernel_size {
+                                let input_x = (output_x * self.stride + kernel_x) as isize
+                                    - self.padding as isize;
+                                if input_x < 0 || input_x >= self.input_width as isize {
+                                    continue;
+                                }
+                                let weight_index = output_channel * self.get_kernel_weight_count()
+                                    + (input_channel * self.kernel_size + kernel_y)
+                                        * self.kernel_size
+                                    + kernel_x;
+                                let input_index = (input_channel * self.input_height
+                                    + input_y as usize)
+                                    * self.input_width
+                                    + input_x as usize;
+                                connection(output_index, weight_index, input_index);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// the output channel each output belongs to
+    fn get_output_channel(&self, output_index: usize) -> usize {
+        output_index / (self.get_output_height() * self.get_output_width())
+    }
+
+    /// Fills `outputs` with the weighted sums of one sample, before any activation.
+    pub(super) fn forward(
+        &self,
+        weights: &[f64],
+        biases: &[f64],
+        inputs: &[f64],
+        outputs: &mut [f64],
+    ) {
+        outputs
+            .iter_mut()
+            .enumerate()
+            .for_each(|(index, output)| *output = biases[self.get_output_channel(index)]);
+        self.for_each_connection(|output_index, weight_index, input_index| {
+            outputs[output_index] += weights[weight_index] * inputs[input_index];
+        });
+    }
+
+    /// Adds one sample's share of the gradients, `deltas` is how the loss changes with each
+    /// weighted sum and `scale` is one over the batch size.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn backwards(
+        &self,
+        weights: &[f64],
+        inputs: &[f64],
+        deltas: &[f64],
+        scale: f64,
+        weight_gradients: &mut [f64],
+        bias_gradients: &mut [f64],
+        input_gradients: &mut [f64],
+    ) {
+        deltas.iter().enumerate().for_each(|(index, delta)| {
+            bias_gradients[self.get_output_channel(index)] += scale * delta;
+        });
+        self.for_each_connection(|output_index, weight_index, input_index| {
+            let delta = deltas[output_index];
+            weight_gradients[weight_index] += scale * delta * inputs[input_index];
+            input_gradients[input_index] += weights[weight_index] * delta;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::activation::Activation;
+    use crate::attempts_at_framework::v2::artificial_neural_network::layer::Layer;
+
+    #[test]
+    fn output_sizes() {
+        let shape = Conv2DShape::new(12, 8, 8, 16, 3);
+        assert_eq!(shape.get_output_height(), 6);
+        assert_eq!(shape.get_output_count(), 16 * 6 * 6);
+        assert_eq!(shape.get_kernel_weight_count(), 12 * 9);
+
+        let mut padded = shape;
+        padded.padding = 1;
+        padded.stride = 2;
+        assert_eq!(padded.get_output_width(), 4);
+        assert!(padded.validate().is_ok());
+
+        let mut too_small = Conv2DShape::new(1, 2, 2, 1, 3);
+        assert!(too_small.validate().is_err());
+        too_small.padding = 1;
+        assert!(too_small.validate().is_ok());
+    }
+
+    #[test]
+    fn forward_by_hand() {
+        // a 3x3 input, one channel, with a 2x2 kernel that adds the top left and takes away the
+        // bottom right of each window
+        let shape = Conv2DShape::new(1, 3, 3, 1, 2);
+        let layer = Layer::conv_2d(
+            Activation::Linear,
+            shape,
+            vec![1.0, 0.0, 0.0, -1.0],
+            vec![0.5],
+        );
+        let inputs = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0];
+        assert_eq!(layer.forward(&inputs), vec![-3.5, -3.5, -3.5, -3.5]);
+
+        // with padding the corners only see one input
+        let mut padded_shape = shape;
+        padded_shape.padding = 1;
+        padded_shape.stride = 2;
+        let padded = Layer::conv_2d(
+            Activation::Linear,
+            padded_shape,
+            vec![0.0, 0.0, 0.0, 1.0],
+            vec![0.0],
+        );
+        assert_eq!(padded.forward(&inputs), vec![1.0, 3.0, 7.0, 9.0]);
+    }
+
+    #[test]
+    fn backprop_matches_finite_differences() {
+        use crate::attempts_at_framework::v2::artificial_neural_network::initialization::Initialization;
+        use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+        use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+            LayerBuilder, LayerType, ModelBuilder,
+        };
+
+        let mut shape = Conv2DShape::new(2, 4, 5, 3, 3);
+        shape.padding = 1;
+        shape.stride = 2;
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(shape.get_input_count())
+            .set_seed(6)
+            .add_layer(
+                LayerBuilder::new_conv_2d(LayerType::TANH, shape)
+                    .set_initialization(Initialization::Xavier),
+            )
+            .add_layer(LayerBuilder::new_flatten())
+            .add_layer(
+                LayerBuilder::new(LayerType::LINEAR, 2).set_initialization(Initialization::Xavier),
+            );
+        let model = builder.build().unwrap();
+
+        let input = (0..shape.get_input_count())
+            .map(|i| ((i * 7) % 11) as f64 / 5.0 - 1.0)
+            .collect::<Vec<f64>>();
+        let check = model.check_gradients(&input, &[0.3, -0.8]);
+        let worst = check.get_worst_parameter().unwrap();
+        assert!(
+            check.get_max_relative_error() < 1e-5,
+            "parameter {} was {} by backprop but {} numerically",
+            worst,
+            check.analytical[worst],
+            check.numerical[worst]
+        );
+    }
+
+    #[test]
+    fn learns_a_kernel() {
+        use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::sgd::Sgd;
+
+        let shape = Conv2DShape::new(1, 5, 5, 1, 3);
+        let kernel = vec![1.0, 0.0, -1.0, 2.0, 0.0, -2.0, 1.0, 0.0, -1.0];
+        let target = Layer::conv_2d(Activation::Linear, shape, kernel.clone(), vec![0.0]);
+        let mut layer = Layer::conv_2d(Activation::Linear, shape, vec![0.0; 9], vec![0.0]);
+
+        let inputs = (0..20)
+            .map(|sample| {
+                (0..25)
+                    .map(|i| ((i * 13 + sample * 7) % 17) as f64 / 8.0 - 1.0)
+                    .collect::<Vec<f64>>()
+            })
+            .collect::<Vec<Vec<f64>>>();
+        let mut optimizer = Sgd::new(0.05);
+        (0..500).for_each(|_| {
+            inputs.iter().for_each(|input| {
+                let expected = target.forward(input);
+                let output = layer.forward(input);
+                let gradient = expected
+                    .iter()
+                    .zip(output.iter())
+                    .map(|(e, o)| e - o)
+                    .collect::<Vec<f64>>();
+                layer.backwards(input, &output, &gradient, &mut optimizer);
+            });
+        });
+
+        layer
+            .get_weights()
+            .iter()
+            .zip(kernel.iter())
+            .for_each(|(learned, expected)| assert!((learned - expected).abs() < 1e-3));
+        assert!(layer.get_biases()[0].abs() < 1e-3);
+    }
+}
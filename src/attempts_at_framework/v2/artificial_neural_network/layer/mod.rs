@@ -1,75 +1,403 @@
-use crate::attempts_at_framework::v2::artificial_neural_network::neuron::linear_neuron::LinearNeuron;
-use crate::attempts_at_framework::v2::artificial_neural_network::neuron::relu_neuron::ReluNeuron;
-use crate::attempts_at_framework::v2::artificial_neural_network::neuron::Neuron;
+pub mod conv2d;
+
+use crate::attempts_at_framework::v2::artificial_neural_network::activation::Activation;
+use crate::attempts_at_framework::v2::artificial_neural_network::initialization::Initialization;
+use crate::attempts_at_framework::v2::artificial_neural_network::layer::conv2d::Conv2DShape;
+use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::Optimizer;
+use rand::{rng, RngCore};
 use std::sync::atomic::AtomicUsize;
 
 static LAYER_COUNT: AtomicUsize = AtomicUsize::new(0);
 
+/// How a layer's outputs are connected to its inputs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayerKind {
+    /// every output sees every input
+    Dense,
+    /// each output only sees the window of the input under it, see [`Conv2DShape`]
+    Conv2D(Conv2DShape),
+    /// Hands its input straight on, with no weights. Inputs are flat already, so this only marks
+    /// where a model stops treating its values as a grid.
+    Flatten,
+}
+
+/// # Layer
+///
+/// A fully connected layer by default, the weights for every output are kept in one contiguous
+/// row major matrix, so row `o` of `weights` holds the `input_count` weights feeding output `o`.
+/// Convolution layers keep one row per output channel instead, see [`LayerKind`].
+///
+/// Batches are passed as flat slices too, sample after sample, so a batch of 3 inputs to a layer
+/// with 2 inputs is a slice of 6 values.
 pub struct Layer {
     id: usize,
     name: Option<String>,
-    neurons: Vec<Box<dyn Neuron>>,
+    kind: LayerKind,
+    activation: Activation,
+    weights: Vec<f64>,
+    biases: Vec<f64>,
     input_count: usize,
+    output_count: usize,
+    /// the chance of each output being dropped while training
+    dropout: f64,
 }
 
 impl Layer {
-    pub fn new<N: Neuron + 'static>(neuron_count: usize, input_count: usize) -> Self {
-        let neurons = (0..neuron_count)
-            .map(|_| Box::new(N::new(input_count)) as Box<dyn Neuron>)
-            .collect();
+    pub fn new(activation: Activation, output_count: usize, input_count: usize) -> Self {
+        Self::new_with_rng(activation, output_count, input_count, &mut rng())
+    }
+
+    /// Same as [`Layer::new`] but any random starting weights are drawn from `rng`. Layers start
+    /// with [`Initialization::Default`], use [`Layer::from_weights`] for anything else.
+    pub fn new_with_rng(
+        activation: Activation,
+        output_count: usize,
+        input_count: usize,
+        rng: &mut dyn RngCore,
+    ) -> Self {
+        let (weights, biases) = Initialization::Default
+            .initialize(activation, output_count, input_count, rng)
+            .expect("the default initialization always works");
+        Self::from_weights(activation, input_count, weights, biases)
+    }
+
+    /// Builds a layer out of existing weights, `weights` is row major with one row of
+    /// `input_count` weights per bias.
+    pub fn from_weights(
+        activation: Activation,
+        input_count: usize,
+        weights: Vec<f64>,
+        biases: Vec<f64>,
+    ) -> Self {
+        assert_eq!(
+            weights.len(),
+            biases.len() * input_count,
+            "a layer with {} outputs and {} inputs needs {} weights",
+            biases.len(),
+            input_count,
+            biases.len() * input_count
+        );
+        let output_count = biases.len();
+        Self::from_parts(
+            LayerKind::Dense,
+            activation,
+            input_count,
+            output_count,
+            weights,
+            biases,
+        )
+    }
+
+    /// A 2D convolution, `weights` holds one kernel per output channel, see [`Conv2DShape`].
+    pub fn conv_2d(
+        activation: Activation,
+        shape: Conv2DShape,
+        weights: Vec<f64>,
+        biases: Vec<f64>,
+    ) -> Self {
+        if let Err(message) = shape.validate() {
+            panic!("{}", message);
+        }
+        assert_eq!(
+            biases.len(),
+            shape.output_channels,
+            "a convolution needs a bias per output channel"
+        );
+        assert_eq!(
+            weights.len(),
+            shape.output_channels * shape.get_kernel_weight_count(),
+            "a convolution needs a kernel of {} weights per output channel",
+            shape.get_kernel_weight_count()
+        );
+        Self::from_parts(
+            LayerKind::Conv2D(shape),
+            activation,
+            shape.get_input_count(),
+            shape.get_output_count(),
+            weights,
+            biases,
+        )
+    }
+
+    pub fn flatten(input_count: usize) -> Self {
+        Self::from_parts(
+            LayerKind::Flatten,
+            Activation::Linear,
+            input_count,
+            input_count,
+            Vec::new(),
+            Vec::new(),
+        )
+    }
+
+    fn from_parts(
+        kind: LayerKind,
+        activation: Activation,
+        input_count: usize,
+        output_count: usize,
+        weights: Vec<f64>,
+        biases: Vec<f64>,
+    ) -> Self {
         let id = LAYER_COUNT.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
         Layer {
             id,
             name: None,
-            neurons,
+            kind,
+            dropout: 0.0,
+            activation,
+            output_count,
+            weights,
+            biases,
             input_count,
         }
     }
 
-    pub fn get_id(&self) -> usize {
-        self.id
+    pub fn get_kind(&self) -> LayerKind {
+        self.kind
     }
 
-    pub fn get_neurons(&self) -> &Vec<Box<dyn Neuron>> {
-        &self.neurons
+    pub fn get_id(&self) -> usize {
+        self.id
     }
 
     pub fn get_name(&self) -> Option<&String> {
         self.name.as_ref()
     }
 
+    /// Only used to tell layers apart, in [`Model::get_summary`](crate::attempts_at_framework::v2::artificial_neural_network::model::Model::get_summary)
+    /// for one. Names aren't saved with the model.
+    pub fn set_name(&mut self, name: String) {
+        self.name = Some(name);
+    }
+
+    pub fn get_activation(&self) -> Activation {
+        self.activation
+    }
+
+    pub fn get_dropout(&self) -> f64 {
+        self.dropout
+    }
+
+    /// While training, each output is zeroed with a chance of `dropout` and the rest are scaled up
+    /// to make up for it, predictions always use every output. Dropout isn't saved with the model.
+    pub fn set_dropout(&mut self, dropout: f64) {
+        if !(0.0..1.0).contains(&dropout) {
+            panic!("Dropout must be at least 0.0 and less than 1.0")
+        }
+        self.dropout = dropout;
+    }
+
+    pub fn get_input_count(&self) -> usize {
+        self.input_count
+    }
+
+    pub fn get_output_count(&self) -> usize {
+        self.output_count
+    }
+
+    pub fn get_weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    pub fn get_biases(&self) -> &[f64] {
+        &self.biases
+    }
+
+    /// The weights that go with a single bias, for a dense layer those feeding one output, for a
+    /// convolution one output channel's kernel.
+    pub fn get_weights_for_output(&self, output: usize) -> &[f64] {
+        let row_length = self.weights.len() / self.biases.len().max(1);
+        &self.weights[output * row_length..(output + 1) * row_length]
+    }
+
+    /// Overwrites this layer's weights and biases with those of `other`, which must be the same
+    /// shape.
+    pub fn copy_weights_from(&mut self, other: &Layer) {
+        assert_eq!(self.input_count, other.input_count);
+        assert_eq!(self.output_count, other.output_count);
+        self.weights.copy_from_slice(&other.weights);
+        self.biases.copy_from_slice(&other.biases);
+    }
+
     pub fn forward(&self, inputs: &[f64]) -> Vec<f64> {
-        self.neurons.iter().map(|n| n.forward(inputs)).collect()
+        self.forward_batch(inputs, 1)
+    }
+
+    pub fn forward_batch(&self, inputs: &[f64], batch_size: usize) -> Vec<f64> {
+        assert_eq!(inputs.len(), batch_size * self.input_count);
+
+        let mut outputs = vec![0.0; batch_size * self.output_count];
+        inputs
+            .chunks_exact(self.input_count.max(1))
+            .zip(outputs.chunks_exact_mut(self.output_count.max(1)))
+            .for_each(|(sample, sample_outputs)| match self.kind {
+                LayerKind::Dense => {
+                    self.weights
+                        .chunks_exact(self.input_count.max(1))
+                        .zip(self.biases.iter())
+                        .zip(sample_outputs.iter_mut())
+                        .for_each(|((row, bias), output)| {
+                            *output = row
+                                .iter()
+                                .zip(sample)
+                                .fold(*bias, |acc, (w, x)| acc + w * x);
+                        });
+                    self.activation.apply_to_outputs(sample_outputs);
+                }
+                LayerKind::Conv2D(shape) => {
+                    shape.forward(&self.weights, &self.biases, sample, sample_outputs);
+                    self.activation.apply_to_outputs(sample_outputs);
+                }
+                LayerKind::Flatten => sample_outputs.copy_from_slice(sample),
+            });
+        outputs
+    }
+
+    /// `gradient` is how the loss changes with each output of this layer, the layer works out
+    /// the gradient of its weights and biases, has the optimizer apply them, and returns how the
+    /// loss changes with each of its inputs, which is the gradient for the layer before it.
+    pub fn backwards(
+        &mut self,
+        inputs: &[f64],
+        outputs: &[f64],
+        gradient: &[f64],
+        optimizer: &mut dyn Optimizer,
+    ) -> Vec<f64> {
+        self.backwards_batch(inputs, outputs, gradient, 1, optimizer)
+    }
+
+    /// Same as [`Layer::backwards`] for a whole batch, the weight gradients are averaged over the
+    /// batch so a single update is made.
+    pub fn backwards_batch(
+        &mut self,
+        inputs: &[f64],
+        outputs: &[f64],
+        gradient: &[f64],
+        batch_size: usize,
+        optimizer: &mut dyn Optimizer,
+    ) -> Vec<f64> {
+        let gradients = self.compute_gradients(inputs, outputs, gradient, batch_size);
+        self.apply_gradients(&gradients, optimizer);
+        gradients.inputs
+    }
+
+    pub fn compute_gradients(
+        &self,
+        inputs: &[f64],
+        outputs: &[f64],
+        gradient: &[f64],
+        batch_size: usize,
+    ) -> LayerGradients {
+        assert_eq!(inputs.len(), batch_size * self.input_count);
+        assert_eq!(outputs.len(), batch_size * self.output_count);
+        assert_eq!(gradient.len(), batch_size * self.output_count);
+
+        let mut gradients = LayerGradients {
+            weights: vec![0.0; self.weights.len()],
+            biases: vec![0.0; self.biases.len()],
+            inputs: vec![0.0; batch_size * self.input_count],
+        };
+        let scale = 1.0 / batch_size as f64;
+
+        if self.kind == LayerKind::Flatten {
+            gradients.inputs.copy_from_slice(gradient);
+            return gradients;
+        }
+
+        for sample in 0..batch_size {
+            let sample_inputs = &inputs[sample * self.input_count..(sample + 1) * self.input_count];
+            let sample_input_gradient =
+                &mut gradients.inputs[sample * self.input_count..(sample + 1) * self.input_count];
+
+            let output_range = sample * self.output_count..(sample + 1) * self.output_count;
+            // how the loss changes with each weighted sum, before the activation was applied
+            let deltas = self
+                .activation
+                .backwards(&outputs[output_range.clone()], &gradient[output_range]);
+
+            if let LayerKind::Conv2D(shape) = self.kind {
+                shape.backwards(
+                    &self.weights,
+                    sample_inputs,
+                    &deltas,
+                    scale,
+                    &mut gradients.weights,
+                    &mut gradients.biases,
+                    sample_input_gradient,
+                );
+                continue;
+            }
+
+            for (output, delta) in deltas.into_iter().enumerate() {
+                if delta == 0.0 {
+                    continue;
+                }
+
+                let row_range = output * self.input_count..(output + 1) * self.input_count;
+                sample_input_gradient
+                    .iter_mut()
+                    .zip(&self.weights[row_range.clone()])
+                    .for_each(|(g, w)| *g += w * delta);
+                gradients.weights[row_range]
+                    .iter_mut()
+                    .zip(sample_inputs)
+                    .for_each(|(g, x)| *g += scale * delta * x);
+                gradients.biases[output] += scale * delta;
+            }
+        }
+
+        gradients
+    }
+
+    pub fn apply_gradients(&mut self, gradients: &LayerGradients, optimizer: &mut dyn Optimizer) {
+        optimizer.update(self.id * 2, &mut self.weights, &gradients.weights);
+        optimizer.update(self.id * 2 + 1, &mut self.biases, &gradients.biases);
     }
+}
 
-    pub fn backwards(&mut self, inputs: &[f64], gradient: &[f64], learning_rate: f64) -> Vec<f64> {
-        self.neurons
-            .iter_mut()
-            .zip(gradient)
-            .map(|(neuron, gradient)| neuron.backwards(inputs, *gradient, learning_rate))
-            .fold(vec![0.0; self.input_count], |acc, x| {
-                acc.iter().zip(x.iter()).map(|(&a, &b)| a + b).collect()
-            })
+/// A clone is a new layer, with its own id, that starts with the same weights.
+impl Clone for Layer {
+    fn clone(&self) -> Self {
+        let mut layer = Layer::from_parts(
+            self.kind,
+            self.activation,
+            self.input_count,
+            self.output_count,
+            self.weights.clone(),
+            self.biases.clone(),
+        );
+        layer.name = self.name.clone();
+        layer.dropout = self.dropout;
+        layer
     }
 }
 
+/// The gradients worked out by [`Layer::compute_gradients`], `weights` has the same row major
+/// layout as the layer's weights.
+pub struct LayerGradients {
+    pub weights: Vec<f64>,
+    pub biases: Vec<f64>,
+    pub inputs: Vec<f64>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::sgd::Sgd;
 
     #[test]
     fn test_new_layer_structs() {
-        let learning_rate = 0.01;
+        let mut optimizer = Sgd::new(0.01);
         let data = vec![
             (vec![2.0, 1.0], 5.0),
             (vec![1.0, 2.0], 4.0),
             (vec![3.0, 3.0], 9.0),
         ];
-        let test_date = vec![4.0, 2.0];
+        let test_date = [4.0, 2.0];
         let test_expected = 10.0;
 
-        let mut hidden_layer = Layer::new::<ReluNeuron>(2, 2);
-        let mut output_layer = Layer::new::<LinearNeuron>(1, 2);
+        let mut hidden_layer = Layer::new(Activation::Relu, 2, 2);
+        let mut output_layer = Layer::new(Activation::Linear, 1, 2);
 
         for epoch in 1..1000 {
             let mut total_loss = 0.0;
@@ -79,10 +407,18 @@ mod tests {
                 let loss = (expected - predicted[0]).powi(2);
 
                 let gradient = expected - predicted[0];
-                let gradient_hidden =
-                    output_layer.backwards(&hidden_layer_outputs[..], &[gradient], learning_rate);
-                let gradient_output =
-                    hidden_layer.backwards(&input[..], &gradient_hidden, learning_rate);
+                let gradient_hidden = output_layer.backwards(
+                    &hidden_layer_outputs,
+                    &predicted,
+                    &[gradient],
+                    &mut optimizer,
+                );
+                hidden_layer.backwards(
+                    &input[..],
+                    &hidden_layer_outputs,
+                    &gradient_hidden,
+                    &mut optimizer,
+                );
 
                 total_loss += loss;
             }
@@ -93,6 +429,105 @@ mod tests {
 
         let hidden_layer_outputs = hidden_layer.forward(&test_date[..]);
         let predicted = output_layer.forward(&hidden_layer_outputs[..]);
-        println!("Input 4.0, 2.0 -> Prediction: {}", predicted[0]);
+        println!(
+            "Input 4.0, 2.0 -> Prediction: {}, expected {}",
+            predicted[0], test_expected
+        );
+    }
+
+    #[test]
+    fn test_relu_basic_functionality() {
+        let layer = Layer::from_weights(Activation::Relu, 2, vec![0.5, -0.5], vec![0.0]);
+
+        // (2.0 * 0.5) + (1.0 * -0.5) + 0.0 = 0.5
+        assert_eq!(layer.forward(&[2.0, 1.0]), vec![0.5]);
+
+        // (-2.0 * 0.5) + (1.0 * -0.5) + 0.0 = -1.5, but ReLU should clamp it to 0
+        assert_eq!(layer.forward(&[-2.0, 1.0]), vec![0.0]);
+    }
+
+    #[test]
+    fn test_relu_learning() {
+        let mut layer = Layer::from_weights(Activation::Relu, 2, vec![0.1, 0.1], vec![0.0]);
+
+        let inputs = vec![1.0, 1.0];
+        let initial_output = layer.forward(&inputs);
+
+        let gradients = layer.backwards(&inputs, &initial_output, &[1.0], &mut Sgd::new(0.1));
+        assert_eq!(gradients, vec![0.1, 0.1]);
+
+        let final_output = layer.forward(&inputs);
+        assert!(
+            final_output[0] > initial_output[0],
+            "Layer should learn and increase output"
+        );
+    }
+
+    #[test]
+    fn test_convergence() {
+        [Activation::Linear, Activation::Relu]
+            .into_iter()
+            .for_each(|activation| {
+                let mut layer = Layer::from_weights(activation, 2, vec![0.1, 0.1], vec![0.0]);
+                let inputs = vec![2.0, 1.0];
+                let expected = 2.0;
+
+                for _ in 0..10000 {
+                    let output = layer.forward(&inputs);
+                    if (output[0] - expected).abs() < 0.000001 {
+                        break;
+                    }
+                    layer.backwards(
+                        &inputs,
+                        &output,
+                        &[expected - output[0]],
+                        &mut Sgd::new(0.1),
+                    );
+                }
+
+                assert!((layer.forward(&inputs)[0] - expected).abs() < 0.000001);
+            });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_input_size_mismatch() {
+        let layer = Layer::new(Activation::Relu, 1, 2);
+        layer.forward(&[1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn batches_match_single_samples() {
+        let weights = vec![0.2, -0.4, 0.6, 0.1, 0.3, -0.5];
+        let biases = vec![0.1, -0.2];
+        let mut single =
+            Layer::from_weights(Activation::Sigmoid, 3, weights.clone(), biases.clone());
+        let mut batched = Layer::from_weights(Activation::Sigmoid, 3, weights, biases);
+
+        let inputs = vec![1.0, 2.0, 3.0, -1.0, 0.5, 0.0];
+        let outputs = batched.forward_batch(&inputs, 2);
+        assert_eq!(&outputs[..2], single.forward(&inputs[..3]).as_slice());
+        assert_eq!(&outputs[2..], single.forward(&inputs[3..]).as_slice());
+
+        // the same sample twice in a batch should move the weights exactly like it did once
+        let repeated_inputs = [&inputs[..3], &inputs[..3]].concat();
+        let repeated_outputs = [&outputs[..2], &outputs[..2]].concat();
+        let gradient = vec![0.5, -1.0];
+        let input_gradient = batched.backwards_batch(
+            &repeated_inputs,
+            &repeated_outputs,
+            &[gradient.clone(), gradient.clone()].concat(),
+            2,
+            &mut Sgd::new(0.1),
+        );
+        let single_input_gradient =
+            single.backwards(&inputs[..3], &outputs[..2], &gradient, &mut Sgd::new(0.1));
+
+        assert_eq!(&input_gradient[..3], single_input_gradient.as_slice());
+        batched
+            .get_weights()
+            .iter()
+            .zip(single.get_weights())
+            .for_each(|(b, s)| assert!((b - s).abs() < 1e-12));
     }
 }
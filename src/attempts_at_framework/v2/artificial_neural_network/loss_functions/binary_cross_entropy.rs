@@ -0,0 +1,68 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::LossFunction;
+
+/// predictions are kept at least this far from 0.0 and 1.0 so the logs never blow up
+const MIN_PROBABILITY: f64 = 1e-12;
+
+/// # Binary Cross Entropy
+///
+/// `-Σ e ln(p) + (1 - e) ln(1 - p)`, for outputs that are each the chance of something being
+/// true on its own, like a sigmoid layer's. Every output is its own yes or no question.
+#[derive(Clone)]
+pub struct BinaryCrossEntropy;
+
+fn clamp(predicted: f64) -> f64 {
+    predicted.clamp(MIN_PROBABILITY, 1.0 - MIN_PROBABILITY)
+}
+
+impl LossFunction for BinaryCrossEntropy {
+    fn calculate_loss(&self, expected: &[f64], predicted: &[f64]) -> f64 {
+        expected
+            .iter()
+            .zip(predicted.iter())
+            .map(|(e, p)| {
+                let p = clamp(*p);
+                -(e * p.ln() + (1.0 - e) * (1.0 - p).ln())
+            })
+            .sum()
+    }
+
+    fn calculate_gradient(&self, expected: &[f64], predicted: &[f64]) -> Vec<f64> {
+        expected
+            .iter()
+            .zip(predicted.iter())
+            .map(|(e, p)| {
+                let p = clamp(*p);
+                e / p - (1.0 - e) / (1.0 - p)
+            })
+            .collect()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "binary_cross_entropy"
+    }
+
+    fn clone_box(&self) -> Box<dyn LossFunction> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::tests::assert_gradient_matches_loss;
+
+    #[test]
+    fn test_calculate_loss() {
+        assert!(BinaryCrossEntropy.calculate_loss(&[1.0, 0.0], &[1.0, 0.0]) < 1e-9);
+        let loss = BinaryCrossEntropy.calculate_loss(&[1.0, 0.0], &[0.5, 0.5]);
+        assert!((loss - 2.0 * 2.0_f64.ln()).abs() < 1e-12);
+        assert!(BinaryCrossEntropy
+            .calculate_loss(&[1.0], &[0.0])
+            .is_finite());
+    }
+
+    #[test]
+    fn test_calculate_gradient() {
+        assert_gradient_matches_loss(&BinaryCrossEntropy, &[1.0, 0.0, 0.3], &[0.6, 0.2, 0.9]);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::LossFunction;
+
+/// predictions are kept at least this far from 0.0 so the log never blows up
+const MIN_PROBABILITY: f64 = 1e-12;
+
+/// # Cross Entropy
+///
+/// Categorical cross entropy, `-Σ e ln(p)`, for outputs that are a probability of each class, as
+/// out of a softmax layer. `expected` is usually one hot, but any distribution works, like the
+/// visit counts of a search.
+#[derive(Clone)]
+pub struct CrossEntropy;
+
+impl LossFunction for CrossEntropy {
+    fn calculate_loss(&self, expected: &[f64], predicted: &[f64]) -> f64 {
+        expected
+            .iter()
+            .zip(predicted.iter())
+            .map(|(e, p)| -e * p.max(MIN_PROBABILITY).ln())
+            .sum()
+    }
+
+    /// `e / p`, through a softmax layer this works out to `e - p`
+    fn calculate_gradient(&self, expected: &[f64], predicted: &[f64]) -> Vec<f64> {
+        expected
+            .iter()
+            .zip(predicted.iter())
+            .map(|(e, p)| e / p.max(MIN_PROBABILITY))
+            .collect()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "cross_entropy"
+    }
+
+    fn clone_box(&self) -> Box<dyn LossFunction> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::tests::assert_gradient_matches_loss;
+
+    #[test]
+    fn test_calculate_loss() {
+        let expected = vec![0.0, 1.0, 0.0];
+        assert_eq!(
+            CrossEntropy.calculate_loss(&expected, &[0.0, 1.0, 0.0]),
+            0.0
+        );
+        let loss = CrossEntropy.calculate_loss(&expected, &[0.25, 0.5, 0.25]);
+        assert!((loss - 2.0_f64.ln()).abs() < 1e-12);
+        assert!(CrossEntropy
+            .calculate_loss(&expected, &[1.0, 0.0, 0.0])
+            .is_finite());
+    }
+
+    #[test]
+    fn test_calculate_gradient() {
+        assert_gradient_matches_loss(&CrossEntropy, &[0.2, 0.7, 0.1], &[0.3, 0.5, 0.2]);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::LossFunction;
+
+/// how far off a prediction can be before the loss stops growing with the square
+const DELTA: f64 = 1.0;
+
+/// # Huber
+///
+/// Squared error for small errors and absolute error past [`DELTA`], so one wildly wrong target,
+/// which bootstrapped targets early on often are, can only pull the weights so far. It is the loss
+/// DQN is usually trained with.
+#[derive(Clone)]
+pub struct Huber;
+
+impl LossFunction for Huber {
+    fn calculate_loss(&self, expected: &[f64], predicted: &[f64]) -> f64 {
+        expected
+            .iter()
+            .zip(predicted.iter())
+            .map(|(e, p)| {
+                let error = (e - p).abs();
+                if error <= DELTA {
+                    0.5 * error.powi(2)
+                } else {
+                    DELTA * (error - 0.5 * DELTA)
+                }
+            })
+            .sum()
+    }
+
+    fn calculate_gradient(&self, expected: &[f64], predicted: &[f64]) -> Vec<f64> {
+        expected
+            .iter()
+            .zip(predicted.iter())
+            .map(|(e, p)| (e - p).clamp(-DELTA, DELTA))
+            .collect()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "huber"
+    }
+
+    fn clone_box(&self) -> Box<dyn LossFunction> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::tests::assert_gradient_matches_loss;
+
+    #[test]
+    fn test_calculate_loss() {
+        assert_eq!(Huber.calculate_loss(&[1.0, 2.0], &[1.0, 2.0]), 0.0);
+        // 0.5 * 0.5^2 for the small error, 1.0 * (3.0 - 0.5) for the big one
+        assert_eq!(Huber.calculate_loss(&[1.0, 2.0], &[1.5, -1.0]), 2.625);
+    }
+
+    #[test]
+    fn test_calculate_gradient() {
+        assert_gradient_matches_loss(&Huber, &[1.0, 2.0, -4.0], &[1.5, -1.0, 0.2]);
+    }
+}
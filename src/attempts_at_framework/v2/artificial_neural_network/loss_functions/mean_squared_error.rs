@@ -1,5 +1,6 @@
 use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::LossFunction;
 
+#[derive(Clone)]
 pub struct MeanSquaredError;
 
 impl LossFunction for MeanSquaredError {
@@ -11,6 +12,8 @@ impl LossFunction for MeanSquaredError {
             .sum()
     }
 
+    /// Half the true gradient of the loss, the 2 is left for the learning rate to soak up as it
+    /// always has been. [`LossFunction::get_gradient_scale`] says so.
     fn calculate_gradient(&self, expected: &[f64], predicted: &[f64]) -> Vec<f64> {
         expected
             .iter()
@@ -18,11 +21,24 @@ impl LossFunction for MeanSquaredError {
             .map(|(e, p)| e - p)
             .collect()
     }
+
+    fn get_gradient_scale(&self) -> f64 {
+        0.5
+    }
+
+    fn get_name(&self) -> &'static str {
+        "mean_squared_error"
+    }
+
+    fn clone_box(&self) -> Box<dyn LossFunction> {
+        Box::new(self.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::tests::assert_gradient_matches_loss;
 
     #[test]
     fn test_calculate_loss_zero_loss() {
@@ -39,4 +55,9 @@ mod tests {
         let loss = MeanSquaredError.calculate_loss(&expected, &predicted);
         assert_eq!(loss, 9.0);
     }
+
+    #[test]
+    fn test_gradient_matches_loss() {
+        assert_gradient_matches_loss(&MeanSquaredError, &[1.0, 2.0, 3.0], &[-1.0, 1.5, 5.0]);
+    }
 }
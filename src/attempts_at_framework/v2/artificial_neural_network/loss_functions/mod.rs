@@ -1,6 +1,84 @@
+pub mod binary_cross_entropy;
+pub mod cross_entropy;
+pub mod huber;
 pub mod mean_squared_error;
 
+use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::binary_cross_entropy::BinaryCrossEntropy;
+use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::cross_entropy::CrossEntropy;
+use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::huber::Huber;
+use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+
 pub trait LossFunction: Send + Sync {
     fn calculate_loss(&self, expected: &[f64], predicted: &[f64]) -> f64;
+    /// How to move each prediction to lower the loss, the negative of the loss's derivative, so
+    /// for squared error it is just `expected - predicted`.
     fn calculate_gradient(&self, expected: &[f64], predicted: &[f64]) -> Vec<f64>;
+    /// How much of the loss's true gradient `calculate_gradient` gives, for anything comparing
+    /// the two, like `Model::check_gradients`.
+    fn get_gradient_scale(&self) -> f64 {
+        1.0
+    }
+    /// the name written to, and read from, saved model files for this loss function
+    fn get_name(&self) -> &'static str;
+    fn clone_box(&self) -> Box<dyn LossFunction>;
+}
+
+/// Looks up a loss function by the name returned from [`LossFunction::get_name`].
+pub fn loss_function_from_name(name: &str) -> Option<Box<dyn LossFunction>> {
+    match name {
+        "mean_squared_error" => Some(Box::new(MeanSquaredError)),
+        "cross_entropy" => Some(Box::new(CrossEntropy)),
+        "binary_cross_entropy" => Some(Box::new(BinaryCrossEntropy)),
+        "huber" => Some(Box::new(Huber)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Checks `calculate_gradient` against nudging each prediction, remembering the gradient is
+    /// the direction that lowers the loss.
+    pub(crate) fn assert_gradient_matches_loss(
+        loss_function: &dyn LossFunction,
+        expected: &[f64],
+        predicted: &[f64],
+    ) {
+        let gradient = loss_function
+            .calculate_gradient(expected, predicted)
+            .into_iter()
+            .map(|gradient| gradient / loss_function.get_gradient_scale())
+            .collect::<Vec<f64>>();
+        let epsilon = 1e-6;
+        (0..predicted.len()).for_each(|index| {
+            let mut nudged = predicted.to_vec();
+            nudged[index] += epsilon;
+            let numerical = -(loss_function.calculate_loss(expected, &nudged)
+                - loss_function.calculate_loss(expected, predicted))
+                / epsilon;
+            assert!(
+                (numerical - gradient[index]).abs() < 1e-4,
+                "{} gave prediction {} a gradient of {} but expected {}",
+                loss_function.get_name(),
+                index,
+                gradient[index],
+                numerical
+            );
+        });
+    }
+
+    #[test]
+    fn names_round_trip() {
+        let loss_functions: Vec<Box<dyn LossFunction>> = vec![
+            Box::new(MeanSquaredError),
+            Box::new(CrossEntropy),
+            Box::new(BinaryCrossEntropy),
+            Box::new(Huber),
+        ];
+        loss_functions.iter().for_each(|loss_function| {
+            let found = loss_function_from_name(loss_function.get_name()).unwrap();
+            assert_eq!(found.get_name(), loss_function.get_name());
+        });
+    }
 }
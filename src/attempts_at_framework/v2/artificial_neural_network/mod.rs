@@ -1,4 +1,7 @@
+pub mod activation;
+pub mod initialization;
 mod layer;
 pub mod loss_functions;
 pub mod model;
-mod neuron;
+pub mod optimizer;
+pub mod trainer;
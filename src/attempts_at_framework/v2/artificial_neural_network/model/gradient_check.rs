@@ -0,0 +1,232 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+
+/// how far each parameter is nudged either way to estimate its gradient
+const EPSILON: f64 = 1e-6;
+/// gradients smaller than this on both sides are treated as agreeing, as their relative error is
+/// all rounding
+const NEGLIGIBLE_GRADIENT: f64 = 1e-9;
+
+/// # Gradient Check
+///
+/// The result of [`Model::check_gradients`] or [`Model::check_output_gradients`], every list has
+/// one entry per weight and bias, laid out like [`Model::get_parameter_gradients`]. Gradients are
+/// of the loss itself, or of the summed outputs, so positive means raising the parameter raises
+/// it.
+#[derive(Debug, Clone)]
+pub struct GradientCheck {
+    pub analytical: Vec<f64>,
+    pub numerical: Vec<f64>,
+    /// `|analytical - numerical| / max(|analytical|, |numerical|)`
+    pub relative_errors: Vec<f64>,
+}
+
+impl GradientCheck {
+    pub fn get_max_relative_error(&self) -> f64 {
+        self.relative_errors.iter().cloned().fold(0.0, f64::max)
+    }
+
+    /// the index of the parameter that disagrees the most, `None` for a model without parameters
+    pub fn get_worst_parameter(&self) -> Option<usize> {
+        self.relative_errors
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+}
+
+impl Model {
+    /// Checks backpropagation by comparing the gradient of the model's loss on one sample, worked
+    /// out by the layers, against central finite differences of the loss. Relative errors
+    /// around 1e-7 or less mean backprop is right, anything near 1.0 means it's broken. Inputs
+    /// that land right on a relu's kink can disagree without anything being wrong.
+    pub fn check_gradients(&self, input: &[f64], expected: &[f64]) -> GradientCheck {
+        // the layers hand back the direction that lowers the loss, scaled however the loss
+        // function scales it, so flip and unscale it
+        let scale = self.get_loss_function().get_gradient_scale();
+        let analytical = self
+            .get_loss_gradients(input.to_vec(), expected)
+            .into_iter()
+            .map(|gradient| -gradient / scale)
+            .collect::<Vec<f64>>();
+
+        self.compare_with_finite_differences(analytical, |model| {
+            model
+                .get_loss_function()
+                .calculate_loss(expected, &model.predict(input.to_vec()))
+        })
+    }
+
+    /// Like [`Model::check_gradients`] but checks [`Model::get_parameter_gradients`], the
+    /// gradient of the model's outputs summed, so no loss function or target is involved.
+    pub fn check_output_gradients(&self, input: &[f64]) -> GradientCheck {
+        let analytical = self.get_parameter_gradients(input.to_vec());
+        self.compare_with_finite_differences(analytical, |model| {
+            model.predict(input.to_vec()).iter().sum()
+        })
+    }
+
+    /// Nudges each parameter either way on a copy of the model to estimate the gradient of
+    /// `measure`, and compares it with `analytical`.
+    fn compare_with_finite_differences(
+        &self,
+        analytical: Vec<f64>,
+        measure: impl Fn(&Model) -> f64,
+    ) -> GradientCheck {
+        let mut nudged = self.clone_weights();
+        let mut measure_after = |step: &[f64]| {
+            nudged.add_to_parameters(step);
+            let value = measure(&nudged);
+            let undo = step.iter().map(|s| -s).collect::<Vec<f64>>();
+            nudged.add_to_parameters(&undo);
+            value
+        };
+        let numerical = (0..analytical.len())
+            .map(|index| {
+                let mut step = vec![0.0; analytical.len()];
+                step[index] = EPSILON;
+                let raised = measure_after(&step);
+                step[index] = -EPSILON;
+                let lowered = measure_after(&step);
+                (raised - lowered) / (2.0 * EPSILON)
+            })
+            .collect::<Vec<f64>>();
+
+        let relative_errors = analytical
+            .iter()
+            .zip(numerical.iter())
+            .map(|(a, n)| {
+                let scale = a.abs().max(n.abs());
+                if scale < NEGLIGIBLE_GRADIENT {
+                    0.0
+                } else {
+                    (a - n).abs() / scale
+                }
+            })
+            .collect();
+
+        GradientCheck {
+            analytical,
+            numerical,
+            relative_errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attempts_at_framework::v2::artificial_neural_network::initialization::Initialization;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::binary_cross_entropy::BinaryCrossEntropy;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::cross_entropy::CrossEntropy;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::huber::Huber;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::LossFunction;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+
+    fn hidden_layer_types() -> Vec<LayerType> {
+        vec![
+            LayerType::RELU,
+            LayerType::LINEAR,
+            LayerType::SOFTMAX,
+            LayerType::SIGMOID,
+            LayerType::TANH,
+            LayerType::LEAKY_RELU,
+            LayerType::ELU,
+        ]
+    }
+
+    /// A loss function with an output layer that suits it, and a target it can reach.
+    fn loss_functions() -> Vec<(Box<dyn LossFunction>, LayerType, Vec<f64>)> {
+        vec![
+            (
+                Box::new(MeanSquaredError),
+                LayerType::LINEAR,
+                vec![0.5, -1.5, 2.0],
+            ),
+            (Box::new(Huber), LayerType::LINEAR, vec![0.5, -1.5, 4.0]),
+            (
+                Box::new(CrossEntropy),
+                LayerType::SOFTMAX,
+                vec![0.1, 0.2, 0.7],
+            ),
+            (
+                Box::new(BinaryCrossEntropy),
+                LayerType::SIGMOID,
+                vec![1.0, 0.0, 0.4],
+            ),
+        ]
+    }
+
+    fn build_model(
+        hidden_layer: LayerType,
+        output_layer: LayerType,
+        loss_function: Box<dyn LossFunction>,
+    ) -> Model {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(loss_function)
+            .set_input_size(4)
+            .set_seed(11)
+            .add_layer(
+                LayerBuilder::new(hidden_layer, 5).set_initialization(Initialization::Xavier),
+            )
+            .add_layer(
+                LayerBuilder::new(output_layer, 3).set_initialization(Initialization::Xavier),
+            );
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn backprop_matches_finite_differences() {
+        let input = [0.6, -1.1, 0.25, 1.7];
+        hidden_layer_types().into_iter().for_each(|hidden_layer| {
+            loss_functions()
+                .into_iter()
+                .for_each(|(loss_function, output_layer, expected)| {
+                    let loss_name = loss_function.get_name();
+                    let model = build_model(hidden_layer, output_layer, loss_function);
+                    let check = model.check_gradients(&input, &expected);
+                    let worst = check.get_worst_parameter().unwrap();
+
+                    assert_eq!(check.relative_errors.len(), model.get_parameter_count());
+                    assert!(
+                        check.get_max_relative_error() < 1e-5,
+                        "{:?} layer with {} loss, parameter {} was {} by backprop but {} numerically",
+                        hidden_layer,
+                        loss_name,
+                        worst,
+                        check.analytical[worst],
+                        check.numerical[worst]
+                    );
+                });
+        });
+    }
+
+    #[test]
+    fn loss_gradients_point_downhill() {
+        let mut model = build_model(
+            LayerType::TANH,
+            LayerType::LINEAR,
+            Box::new(MeanSquaredError),
+        );
+        let input = vec![0.6, -1.1, 0.25, 1.7];
+        let expected = [0.5, -1.5, 2.0];
+        let loss = |model: &Model| {
+            model
+                .get_loss_function()
+                .calculate_loss(&expected, &model.predict(input.clone()))
+        };
+
+        let before = loss(&model);
+        let step = model
+            .get_loss_gradients(input.clone(), &expected)
+            .iter()
+            .map(|g| 0.01 * g)
+            .collect::<Vec<f64>>();
+        model.add_to_parameters(&step);
+        assert!(loss(&model) < before);
+    }
+}
@@ -1,16 +1,36 @@
-use crate::attempts_at_framework::v2::artificial_neural_network::layer::Layer;
+use crate::attempts_at_framework::v2::artificial_neural_network::layer::{Layer, LayerGradients};
 use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::LossFunction;
+use crate::attempts_at_framework::v2::artificial_neural_network::model::regularization::Regularization;
+use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::sgd::Sgd;
+use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::Optimizer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
+pub mod gradient_check;
 pub mod model_builder;
+pub mod model_file;
+pub mod regularization;
+pub mod summary;
+
+/// [`Model::predict_batch`] doesn't start a thread for fewer samples than this, the thread would
+/// cost more than it saves.
+const MIN_SAMPLES_PER_THREAD: usize = 16;
 
 pub struct Model {
     name: String,
     version: String,
     layers: Vec<Box<Layer>>,
     loss_function: Box<dyn LossFunction>,
+    optimizer: Box<dyn Optimizer>,
+    regularization: Regularization,
+    /// only used for dropout
+    rng: StdRng,
+    prediction_threads: usize,
 }
 
 impl Model {
+    /// The model starts with plain [`Sgd`] at a learning rate of 0.01, use
+    /// [`Model::set_optimizer`] to change that.
     pub fn new(
         name: String,
         version: String,
@@ -22,6 +42,10 @@ impl Model {
             version,
             layers,
             loss_function,
+            optimizer: Box::new(Sgd::new(0.01)),
+            regularization: Regularization::default(),
+            rng: StdRng::from_os_rng(),
+            prediction_threads: 1,
         }
     }
 
@@ -31,50 +55,463 @@ impl Model {
             .fold(input, |acc, layer| layer.forward(&acc))
     }
 
+    /// Runs all the inputs through the model together, a layer at a time. The outputs are in the
+    /// same order as the inputs and exactly what [`Model::predict`] gives for each. Big batches
+    /// are split between threads when [`Model::set_prediction_threads`] allows it.
+    pub fn predict_batch(&self, inputs: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        if inputs.is_empty() {
+            return Vec::new();
+        }
+
+        let thread_count = self
+            .prediction_threads
+            .min(inputs.len() / MIN_SAMPLES_PER_THREAD)
+            .max(1);
+        if thread_count == 1 {
+            return Self::predict_samples(&self.layers, inputs);
+        }
+
+        let chunk_size = inputs.len().div_ceil(thread_count);
+        let layers = &self.layers;
+        std::thread::scope(|scope| {
+            let handles = inputs
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || Self::predict_samples(layers, chunk)))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    }
+
+    fn predict_samples(layers: &[Box<Layer>], inputs: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        let outputs = layers.iter().fold(inputs.concat(), |acc, layer| {
+            layer.forward_batch(&acc, inputs.len())
+        });
+        let output_size = outputs.len() / inputs.len();
+        outputs
+            .chunks_exact(output_size)
+            .map(|output| output.to_vec())
+            .collect()
+    }
+
+    /// A single gradient descent step on one sample, ignores the model's optimizer and moves
+    /// every weight by `learning_rate` times its gradient.
     pub fn train(&mut self, input: Vec<f64>, expected: Vec<f64>, learning_rate: f64) -> f64 {
-        let mut layer_inputs = self
-            .layers
-            .iter()
-            .fold(vec![input.clone()], |mut inputs, layer| {
-                let predicted = layer.forward(&inputs.last().unwrap());
-                inputs.push(predicted);
-                inputs
-            });
+        let mut optimizer = Sgd::new(learning_rate);
+        self.train_on_batch(Some(&mut optimizer), input, &expected, 1, None, None)
+    }
+
+    /// Like [`Model::train`] but only moves the output at `output_index` towards `expected`, the
+    /// other outputs get no gradient at all. For models with one output per action, where only
+    /// the value of the action taken has a target. Returns the loss of that one output.
+    pub fn train_output(
+        &mut self,
+        input: Vec<f64>,
+        output_index: usize,
+        expected: f64,
+        learning_rate: f64,
+    ) -> f64 {
+        let mut optimizer = Sgd::new(learning_rate);
+        self.train_on_batch(
+            Some(&mut optimizer),
+            input,
+            &[expected],
+            1,
+            Some(&[output_index]),
+            None,
+        )
+    }
 
-        // removing the last element as that is from the output layer and is the prediction
-        let prediction = layer_inputs.pop().unwrap();
+    /// Makes one update from a whole batch of samples using the model's optimizer, the
+    /// gradients are averaged over the batch. Returns the average loss of the samples before the
+    /// update.
+    pub fn train_batch(&mut self, inputs: &[Vec<f64>], expected: &[Vec<f64>]) -> f64 {
+        assert_eq!(
+            inputs.len(),
+            expected.len(),
+            "every input needs an expected output"
+        );
+        if inputs.is_empty() {
+            return 0.0;
+        }
 
-        let loss = self.loss_function.calculate_loss(&expected, &prediction);
+        self.train_on_batch(
+            None,
+            inputs.concat(),
+            &expected.concat(),
+            inputs.len(),
+            None,
+            None,
+        )
+    }
+
+    /// Like [`Model::train_batch`] but each sample's gradient is multiplied by its weight before
+    /// they're averaged, whatever the loss function. For importance sampling weights from a
+    /// prioritized replay buffer. The returned loss is unweighted.
+    pub fn train_weighted_batch(
+        &mut self,
+        inputs: &[Vec<f64>],
+        expected: &[Vec<f64>],
+        weights: &[f64],
+    ) -> f64 {
+        assert_eq!(
+            inputs.len(),
+            expected.len(),
+            "every input needs an expected output"
+        );
+        assert_eq!(inputs.len(), weights.len(), "every input needs a weight");
+        if inputs.is_empty() {
+            return 0.0;
+        }
+
+        self.train_on_batch(
+            None,
+            inputs.concat(),
+            &expected.concat(),
+            inputs.len(),
+            None,
+            Some(weights),
+        )
+    }
+
+    /// Uses the model's own optimizer unless `optimizer` is given. The returned loss leaves out
+    /// the regularization penalties.
+    ///
+    /// With `trained_outputs`, one output index per sample, `expected` only has a value for each
+    /// sample's trained output and every other output's gradient is left at 0.0. With
+    /// `sample_weights` each sample's output gradient is scaled by its weight.
+    fn train_on_batch(
+        &mut self,
+        optimizer: Option<&mut dyn Optimizer>,
+        inputs: Vec<f64>,
+        expected: &[f64],
+        batch_size: usize,
+        trained_outputs: Option<&[usize]>,
+        sample_weights: Option<&[f64]>,
+    ) -> f64 {
+        let optimizer = match optimizer {
+            Some(optimizer) => optimizer,
+            None => self.optimizer.as_mut(),
+        };
 
         /*
-        Hot dam ok this needs an explanation, this logic finds the gradient of the output layer
+        The input followed by the output of every layer, each layer's output is the next one's
+        input. With dropout the next layer gets the dropped outputs, but the layer needs its
+        untouched outputs to work out its activation's derivative, so both are kept along with
+        the mask that was used.
+         */
+        let mut layer_outputs = Vec::with_capacity(self.layers.len());
+        let mut layer_inputs = Vec::with_capacity(self.layers.len() + 1);
+        let mut dropout_masks = Vec::with_capacity(self.layers.len());
+        layer_inputs.push(inputs);
+        for layer in self.layers.iter() {
+            let output = layer.forward_batch(layer_inputs.last().unwrap(), batch_size);
+            let dropout = layer.get_dropout();
+            let mask = if dropout > 0.0 {
+                let kept_scale = 1.0 / (1.0 - dropout);
+                Some(
+                    (0..output.len())
+                        .map(|_| {
+                            if self.rng.random::<f64>() < dropout {
+                                0.0
+                            } else {
+                                kept_scale
+                            }
+                        })
+                        .collect::<Vec<f64>>(),
+                )
+            } else {
+                None
+            };
+            let next_input = match &mask {
+                Some(mask) => output.iter().zip(mask).map(|(o, m)| o * m).collect(),
+                None => output.clone(),
+            };
+            layer_outputs.push(output);
+            layer_inputs.push(next_input);
+            dropout_masks.push(mask);
+        }
+
+        let prediction = layer_inputs.last().unwrap();
+        let output_size = prediction.len() / batch_size;
+        let mut loss = 0.0;
+        let mut gradient = Vec::with_capacity(prediction.len());
+        match trained_outputs {
+            None => {
+                assert_eq!(
+                    expected.len(),
+                    prediction.len(),
+                    "expected {} values per sample",
+                    output_size
+                );
+                expected
+                    .chunks_exact(output_size)
+                    .zip(prediction.chunks_exact(output_size))
+                    .for_each(|(expected, predicted)| {
+                        loss += self.loss_function.calculate_loss(expected, predicted);
+                        gradient.extend(self.loss_function.calculate_gradient(expected, predicted));
+                    });
+            }
+            Some(trained_outputs) => {
+                assert_eq!(expected.len(), batch_size, "expected one value per sample");
+                assert_eq!(
+                    trained_outputs.len(),
+                    batch_size,
+                    "every sample needs an output to train"
+                );
+                prediction
+                    .chunks_exact(output_size)
+                    .zip(trained_outputs.iter().zip(expected))
+                    .for_each(|(predicted, (output_index, expected))| {
+                        assert!(
+                            *output_index < output_size,
+                            "output {} of a model with {} outputs",
+                            output_index,
+                            output_size
+                        );
+                        let predicted = &predicted[*output_index..*output_index + 1];
+                        let expected = std::slice::from_ref(expected);
+                        loss += self.loss_function.calculate_loss(expected, predicted);
+                        let mut sample_gradient = vec![0.0; output_size];
+                        sample_gradient[*output_index] =
+                            self.loss_function.calculate_gradient(expected, predicted)[0];
+                        gradient.extend(sample_gradient);
+                    });
+            }
+        }
+        if let Some(sample_weights) = sample_weights {
+            gradient
+                .chunks_exact_mut(output_size)
+                .zip(sample_weights)
+                .for_each(|(sample_gradient, weight)| {
+                    sample_gradient.iter_mut().for_each(|g| *g *= weight)
+                });
+        }
 
-        But due to the back propagation process, each layer starting with the output layer needs
-        the gradient calculated via the chain rule from the previous layer; this is the response
-        from the `backwards` function provided by each layer. So we update this with the previous
-        layers gradients, but it needs to be primed for the output layer.
+        /*
+        Each layer takes the gradient of the loss with respect to its outputs and hands back the
+        gradient with respect to its inputs, which is the output gradient of the layer before it.
+        The loss function primes this for the output layer. Every layer's gradients are worked out
+        before any are applied, as clipping needs the length of all of them together.
          */
-        let mut gradient = self
-            .loss_function
-            .calculate_gradient(&expected, &prediction)
-            .iter()
-            .zip(prediction.iter())
-            .zip(self.layers.last().unwrap().get_neurons().iter())
-            .map(|((loss_gradient, predicted), neuron)| {
-                loss_gradient * neuron.activation_derivative(*predicted)
-            })
-            .collect::<Vec<f64>>();
+        let mut layer_gradients = Vec::with_capacity(self.layers.len());
+        for (index, layer) in self.layers.iter().enumerate().rev() {
+            if let Some(mask) = &dropout_masks[index] {
+                gradient.iter_mut().zip(mask).for_each(|(g, m)| *g *= m);
+            }
+            let mut gradients = layer.compute_gradients(
+                &layer_inputs[index],
+                &layer_outputs[index],
+                &gradient,
+                batch_size,
+            );
+            gradient = std::mem::take(&mut gradients.inputs);
+            gradients
+                .weights
+                .iter_mut()
+                .zip(layer.get_weights())
+                .for_each(|(g, w)| *g += self.regularization.get_weight_gradient(*w));
+            layer_gradients.push(gradients);
+        }
+
+        if let Some(max_gradient_norm) = self.regularization.max_gradient_norm {
+            let norm = layer_gradients
+                .iter()
+                .flat_map(|gradients| gradients.weights.iter().chain(gradients.biases.iter()))
+                .map(|g| g.powi(2))
+                .sum::<f64>()
+                .sqrt();
+            if norm > max_gradient_norm {
+                let scale = max_gradient_norm / norm;
+                layer_gradients.iter_mut().for_each(|gradients| {
+                    gradients
+                        .weights
+                        .iter_mut()
+                        .chain(gradients.biases.iter_mut())
+                        .for_each(|g| *g *= scale);
+                });
+            }
+        }
 
         self.layers
             .iter_mut()
             .rev()
-            .zip(layer_inputs.iter().rev())
-            .for_each(|(layer, inputs)| {
-                // TODO might need to add to total loss, figure that out
-                gradient = layer.backwards(&inputs, &gradient, learning_rate);
+            .zip(layer_gradients.iter())
+            .for_each(|(layer, gradients)| layer.apply_gradients(gradients, optimizer));
+
+        loss / batch_size as f64
+    }
+
+    /// The gradient of the model's outputs, summed, with respect to every weight and bias. This
+    /// is what methods like eligibility traces need, rather than the gradient of a loss. Layer by
+    /// layer, weights then biases, in the same order [`Model::add_to_parameters`] takes them.
+    pub fn get_parameter_gradients(&self, input: Vec<f64>) -> Vec<f64> {
+        self.backpropagate_to_parameters(input, |prediction| vec![1.0; prediction.len()])
+    }
+
+    /// How to move every weight and bias to lower the loss on a single sample, the same
+    /// direction [`Model::train`] moves them in, laid out like [`Model::get_parameter_gradients`].
+    pub fn get_loss_gradients(&self, input: Vec<f64>, expected: &[f64]) -> Vec<f64> {
+        self.backpropagate_to_parameters(input, |prediction| {
+            assert_eq!(
+                expected.len(),
+                prediction.len(),
+                "expected {} values",
+                prediction.len()
+            );
+            self.loss_function.calculate_gradient(expected, prediction)
+        })
+    }
+
+    /// Runs `input` forward, then backpropagates the gradient `output_gradient` works out from the
+    /// prediction, without changing any weights.
+    fn backpropagate_to_parameters(
+        &self,
+        input: Vec<f64>,
+        output_gradient: impl FnOnce(&[f64]) -> Vec<f64>,
+    ) -> Vec<f64> {
+        let mut layer_outputs = Vec::with_capacity(self.layers.len() + 1);
+        layer_outputs.push(input);
+        for layer in self.layers.iter() {
+            let output = layer.forward(layer_outputs.last().unwrap());
+            layer_outputs.push(output);
+        }
+
+        let mut gradient = output_gradient(layer_outputs.last().unwrap());
+        let mut layer_gradients = Vec::with_capacity(self.layers.len());
+        self.layers
+            .iter()
+            .enumerate()
+            .rev()
+            .for_each(|(index, layer)| {
+                let gradients = layer.compute_gradients(
+                    &layer_outputs[index],
+                    &layer_outputs[index + 1],
+                    &gradient,
+                    1,
+                );
+                gradient = gradients.inputs;
+                layer_gradients.push((gradients.weights, gradients.biases));
             });
 
-        loss
+        layer_gradients
+            .into_iter()
+            .rev()
+            .flat_map(|(weights, biases)| weights.into_iter().chain(biases))
+            .collect()
+    }
+
+    /// Adds `step` to every weight and bias, `step` is laid out the same way as
+    /// [`Model::get_parameter_gradients`].
+    pub fn add_to_parameters(&mut self, step: &[f64]) {
+        assert_eq!(
+            step.len(),
+            self.get_parameter_count(),
+            "a step is needed for every weight and bias"
+        );
+        let mut sgd = Sgd::new(1.0);
+        let mut offset = 0;
+        self.layers.iter_mut().for_each(|layer| {
+            let weight_count = layer.get_weights().len();
+            let bias_count = layer.get_biases().len();
+            let gradients = LayerGradients {
+                weights: step[offset..offset + weight_count].to_vec(),
+                biases: step[offset + weight_count..offset + weight_count + bias_count].to_vec(),
+                inputs: Vec::new(),
+            };
+            layer.apply_gradients(&gradients, &mut sgd);
+            offset += weight_count + bias_count;
+        });
+    }
+
+    pub(crate) fn get_layers(&self) -> &[Box<Layer>] {
+        &self.layers
+    }
+
+    /// the number of weights and biases in the model
+    pub fn get_parameter_count(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|layer| layer.get_weights().len() + layer.get_biases().len())
+            .sum()
+    }
+
+    /// A copy of the model with the same layers, weights, loss function and regularization. The
+    /// copy starts with its own default optimizer, as optimizer state belongs to the layers it was
+    /// built up for.
+    pub fn clone_weights(&self) -> Model {
+        let mut model = Model::new(
+            self.name.clone(),
+            self.version.clone(),
+            self.layers.clone(),
+            self.loss_function.clone_box(),
+        );
+        model.regularization = self.regularization;
+        model.prediction_threads = self.prediction_threads;
+        model
+    }
+
+    /// Overwrites every weight and bias with those of `other`, both models must have the same
+    /// shape. Handy for keeping a target network in sync with the network being trained.
+    pub fn copy_weights_from(&mut self, other: &Model) {
+        assert_eq!(
+            self.layers.len(),
+            other.layers.len(),
+            "models have a different number of layers"
+        );
+        self.layers
+            .iter_mut()
+            .zip(other.layers.iter())
+            .for_each(|(layer, other_layer)| layer.copy_weights_from(other_layer));
+    }
+
+    /// The most threads [`Model::predict_batch`] splits a batch between, defaults to 1 so nothing
+    /// runs in parallel. [`std::thread::available_parallelism`] is a good choice otherwise.
+    pub fn set_prediction_threads(&mut self, prediction_threads: usize) {
+        self.prediction_threads = prediction_threads.max(1);
+    }
+
+    pub fn get_prediction_threads(&self) -> usize {
+        self.prediction_threads
+    }
+
+    pub fn set_regularization(&mut self, regularization: Regularization) {
+        self.regularization = regularization;
+    }
+
+    pub fn get_regularization(&self) -> Regularization {
+        self.regularization
+    }
+
+    /// Seeds the dropout masks used while training.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn set_optimizer(&mut self, optimizer: Box<dyn Optimizer>) {
+        self.optimizer = optimizer;
+    }
+
+    pub fn get_optimizer(&self) -> &dyn Optimizer {
+        self.optimizer.as_ref()
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_version(&self) -> &str {
+        &self.version
+    }
+
+    /// The number of values the model expects to be passed to `predict` and `train`
+    pub fn get_input_size(&self) -> usize {
+        self.layers
+            .first()
+            .map(|layer| layer.get_input_count())
+            .unwrap_or(0)
     }
 
     pub fn get_loss_function(&self) -> &dyn LossFunction {
@@ -84,11 +521,11 @@ impl Model {
     pub fn print_weights(&self) {
         let display_text = self.layers.iter().enumerate().map(|(layer_index, layer)| {
             let layers_neuron_display = layer
-                .get_neurons()
+                .get_biases()
                 .iter()
                 .enumerate()
-                .map(|(neuron_index, neuron)| {
-                    let (weights, bias) = neuron.get_weights_and_bias();
+                .map(|(neuron_index, bias)| {
+                    let weights = layer.get_weights_for_output(neuron_index);
                     let weights_display = weights
                         .iter()
                         .map(|weight| weight.to_string())
@@ -109,14 +546,13 @@ impl Model {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::attempts_at_framework::v2::artificial_neural_network::neuron::linear_neuron::LinearNeuron;
-    use crate::attempts_at_framework::v2::artificial_neural_network::neuron::relu_neuron::ReluNeuron;
+    use crate::attempts_at_framework::v2::artificial_neural_network::activation::Activation;
     use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
 
     #[test]
     fn test_model() {
-        let output_layer = Layer::new::<LinearNeuron>(1, 2);
-        let hidden_layer = Layer::new::<ReluNeuron>(2, 2);
+        let output_layer = Layer::new(Activation::Linear, 1, 2);
+        let hidden_layer = Layer::new(Activation::Relu, 2, 2);
 
         let boxed_output_layer = Box::new(output_layer);
         let boxed_hidden_layer = Box::new(hidden_layer);
@@ -128,6 +564,10 @@ mod test {
             version: "1.0".to_string(),
             layers,
             loss_function: Box::new(MeanSquaredError),
+            optimizer: Box::new(Sgd::new(0.01)),
+            regularization: Regularization::default(),
+            rng: StdRng::seed_from_u64(0),
+            prediction_threads: 1,
         };
 
         let learning_rate = 0.01;
@@ -158,4 +598,316 @@ mod test {
         println!("Loss: {}", loss);
         println!("Predicted: {:?}", predicted);
     }
+
+    #[test]
+    fn parameter_gradients_match_the_change_in_output() {
+        let model = Model::new(
+            "test".to_string(),
+            "1.0".to_string(),
+            vec![
+                Box::new(Layer::from_weights(
+                    Activation::Sigmoid,
+                    2,
+                    vec![0.5, -0.3, 0.8, 0.1],
+                    vec![0.1, -0.2],
+                )),
+                Box::new(Layer::from_weights(
+                    Activation::Linear,
+                    2,
+                    vec![1.5, -0.7],
+                    vec![0.3],
+                )),
+            ],
+            Box::new(MeanSquaredError),
+        );
+        let check = model.check_output_gradients(&[0.4, -1.2]);
+        let worst = check.get_worst_parameter().unwrap();
+
+        assert_eq!(check.analytical.len(), model.get_parameter_count());
+        assert!(
+            check.get_max_relative_error() < 1e-5,
+            "parameter {} had a gradient of {} but expected {}",
+            worst,
+            check.analytical[worst],
+            check.numerical[worst]
+        );
+    }
+
+    fn get_parameters(model: &Model) -> Vec<f64> {
+        model
+            .get_layers()
+            .iter()
+            .flat_map(|layer| layer.get_weights().iter().chain(layer.get_biases()))
+            .cloned()
+            .collect()
+    }
+
+    /// `x0 + x1` with a third input that is only noise
+    fn noisy_data() -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        let inputs = (0..12)
+            .map(|i| {
+                let i = i as f64;
+                vec![(i * 0.7).sin(), (i * 1.3).cos(), (i * 2.9).sin() * 0.1]
+            })
+            .collect::<Vec<Vec<f64>>>();
+        let expected = inputs
+            .iter()
+            .map(|input| vec![input[0] + input[1] + input[2]])
+            .collect();
+        (inputs, expected)
+    }
+
+    fn train_on_noisy_data(regularization: Regularization) -> Vec<f64> {
+        let (inputs, expected) = noisy_data();
+        let mut model = Model::new(
+            "test".to_string(),
+            "1.0".to_string(),
+            vec![Box::new(Layer::from_weights(
+                Activation::Linear,
+                3,
+                vec![0.5, 0.5, 0.5],
+                vec![0.0],
+            ))],
+            Box::new(MeanSquaredError),
+        );
+        model.set_optimizer(Box::new(Sgd::new(0.1)));
+        model.set_regularization(regularization);
+        (0..2000).for_each(|_| {
+            model.train_batch(&inputs, &expected);
+        });
+        get_parameters(&model)
+    }
+
+    #[test]
+    fn weight_penalties_shrink_weights() {
+        let plain = train_on_noisy_data(Regularization::default());
+        let l2 = train_on_noisy_data(Regularization {
+            l2: 0.1,
+            ..Default::default()
+        });
+        let l1 = train_on_noisy_data(Regularization {
+            l1: 0.01,
+            ..Default::default()
+        });
+
+        let length = |weights: &[f64]| weights[..3].iter().map(|w| w.powi(2)).sum::<f64>();
+        assert!(length(&l2) < length(&plain));
+        // the noisy input's weight is what l1 should get rid of
+        assert!(
+            l1[2].abs() < 0.01 && plain[2].abs() > 0.5,
+            "l1 left the noise weight at {} and without it was {}",
+            l1[2],
+            plain[2]
+        );
+    }
+
+    #[test]
+    fn clipping_caps_the_step() {
+        let build = |max_gradient_norm: Option<f64>| {
+            let mut model = Model::new(
+                "test".to_string(),
+                "1.0".to_string(),
+                vec![Box::new(Layer::from_weights(
+                    Activation::Linear,
+                    2,
+                    vec![0.1, 0.1],
+                    vec![0.0],
+                ))],
+                Box::new(MeanSquaredError),
+            );
+            model.set_optimizer(Box::new(Sgd::new(1.0)));
+            model.set_regularization(Regularization {
+                max_gradient_norm,
+                ..Default::default()
+            });
+            model
+        };
+        let step_length = |mut model: Model| {
+            let before = get_parameters(&model);
+            model.train_batch(&[vec![3.0, -4.0]], &[vec![100.0]]);
+            get_parameters(&model)
+                .iter()
+                .zip(before)
+                .map(|(after, before)| (after - before).powi(2))
+                .sum::<f64>()
+                .sqrt()
+        };
+
+        assert!(step_length(build(None)) > 100.0);
+        assert!((step_length(build(Some(0.5))) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn dropout_only_applies_while_training() {
+        use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+            LayerBuilder, LayerType, ModelBuilder,
+        };
+
+        let build = |dropout: f64| {
+            let mut builder = ModelBuilder::new();
+            builder
+                .set_loss_function(Box::new(MeanSquaredError))
+                .set_input_size(3)
+                .set_seed(4)
+                .add_layer(LayerBuilder::new(LayerType::RELU, 16).set_dropout(dropout))
+                .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+            builder.build().unwrap()
+        };
+        let input = vec![0.5, -0.2, 0.9];
+        let mut without_dropout = build(0.0);
+        let mut with_dropout = build(0.5);
+        let mut same_seed = build(0.5);
+        assert_eq!(
+            with_dropout.predict(input.clone()),
+            without_dropout.predict(input.clone())
+        );
+
+        [&mut without_dropout, &mut with_dropout, &mut same_seed]
+            .into_iter()
+            .for_each(|model| {
+                model.train_batch(std::slice::from_ref(&input), &[vec![1.0]]);
+            });
+        assert_ne!(
+            get_parameters(&with_dropout),
+            get_parameters(&without_dropout)
+        );
+        assert_eq!(get_parameters(&with_dropout), get_parameters(&same_seed));
+        // half the hidden outputs are dropped, so only some of the output weights move
+        let moved = with_dropout.get_layers()[1]
+            .get_weights()
+            .iter()
+            .zip(build(0.5).get_layers()[1].get_weights())
+            .filter(|(after, before)| after != before)
+            .count();
+        assert!(
+            moved > 0 && moved < 16,
+            "{} of 16 output weights moved",
+            moved
+        );
+        assert_eq!(
+            with_dropout.predict(input.clone()),
+            with_dropout.predict(input)
+        );
+    }
+
+    #[test]
+    fn train_output_leaves_the_other_outputs_alone() {
+        let mut model = Model::new(
+            "test".to_string(),
+            "1.0".to_string(),
+            vec![Box::new(Layer::from_weights(
+                Activation::Linear,
+                2,
+                vec![0.5, -0.5, 1.0, 0.0, 0.25, 0.75],
+                vec![0.0, 0.0, 0.0],
+            ))],
+            Box::new(MeanSquaredError),
+        );
+        let input = vec![1.0, 2.0];
+        let before = model.predict(input.clone());
+
+        (0..200).for_each(|_| {
+            model.train_output(input.clone(), 1, 4.0, 0.05);
+        });
+
+        let after = model.predict(input);
+        assert!((after[1] - 4.0).abs() < 1e-6);
+        assert_eq!(after[0], before[0]);
+        assert_eq!(after[2], before[2]);
+    }
+
+    #[test]
+    fn predict_batch_matches_predict() {
+        let mut model = Model::new(
+            "test".to_string(),
+            "1.0".to_string(),
+            vec![
+                Box::new(Layer::new(Activation::Tanh, 8, 3)),
+                Box::new(Layer::new(Activation::Linear, 2, 8)),
+            ],
+            Box::new(MeanSquaredError),
+        );
+        let inputs = (0..100)
+            .map(|i| vec![i as f64 / 50.0 - 1.0, (i % 7) as f64, (i % 3) as f64 - 1.0])
+            .collect::<Vec<Vec<f64>>>();
+        let expected = inputs
+            .iter()
+            .map(|input| model.predict(input.clone()))
+            .collect::<Vec<Vec<f64>>>();
+
+        assert_eq!(model.predict_batch(&inputs), expected);
+        model.set_prediction_threads(4);
+        assert_eq!(model.predict_batch(&inputs), expected);
+        assert_eq!(model.predict_batch(&inputs[..5]), expected[..5]);
+        assert!(model.predict_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn sample_weights_scale_the_step_whatever_the_loss() {
+        use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::huber::Huber;
+
+        let step = |weight: f64| {
+            let mut model = Model::new(
+                "test".to_string(),
+                "1.0".to_string(),
+                vec![Box::new(Layer::from_weights(
+                    Activation::Linear,
+                    2,
+                    vec![0.0, 0.0],
+                    vec![0.0],
+                ))],
+                Box::new(Huber),
+            );
+            // far enough from the target that the Huber gradient is clipped
+            model.train_weighted_batch(&[vec![1.0, 2.0]], &[vec![10.0]], &[weight]);
+            model.predict(vec![1.0, 2.0])[0]
+        };
+
+        let full = step(1.0);
+        assert!(full > 0.0);
+        assert!((step(0.25) - 0.25 * full).abs() < 1e-12);
+        assert_eq!(step(0.0), 0.0);
+    }
+
+    #[test]
+    fn train_batch_with_each_optimizer() {
+        use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::adam::Adam;
+        use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::momentum::Momentum;
+        use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::rms_prop::RmsProp;
+
+        let inputs = vec![vec![2.0, 1.0], vec![1.0, 2.0], vec![3.0, 3.0]];
+        let expected = vec![vec![5.0], vec![4.0], vec![9.0]];
+
+        let optimizers: Vec<Box<dyn Optimizer>> = vec![
+            Box::new(Sgd::new(0.05)),
+            Box::new(Momentum::new(0.01, 0.9)),
+            Box::new(RmsProp::new(0.01, 0.9)),
+            Box::new(Adam::new(0.05)),
+        ];
+
+        optimizers.into_iter().for_each(|optimizer| {
+            let name = optimizer.get_name();
+            let mut model = Model::new(
+                "test".to_string(),
+                "1.0".to_string(),
+                vec![Box::new(Layer::new(Activation::Linear, 1, 2))],
+                Box::new(MeanSquaredError),
+            );
+            model.set_optimizer(optimizer);
+
+            let first_loss = model.train_batch(&inputs, &expected);
+            let last_loss = (0..2000)
+                .map(|_| model.train_batch(&inputs, &expected))
+                .last()
+                .unwrap();
+
+            assert!(
+                last_loss < 0.01 && last_loss < first_loss,
+                "{} went from a loss of {} to {}",
+                name,
+                first_loss,
+                last_loss
+            );
+        });
+    }
 }
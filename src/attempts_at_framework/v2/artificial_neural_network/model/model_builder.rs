@@ -1,8 +1,14 @@
-use crate::attempts_at_framework::v2::artificial_neural_network::layer::Layer;
+use crate::attempts_at_framework::v2::artificial_neural_network::activation::Activation;
+use crate::attempts_at_framework::v2::artificial_neural_network::initialization::Initialization;
+pub use crate::attempts_at_framework::v2::artificial_neural_network::layer::conv2d::Conv2DShape;
+use crate::attempts_at_framework::v2::artificial_neural_network::layer::{Layer, LayerKind};
 use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::LossFunction;
+use crate::attempts_at_framework::v2::artificial_neural_network::model::regularization::Regularization;
 use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
-use crate::attempts_at_framework::v2::artificial_neural_network::neuron::linear_neuron::LinearNeuron;
-use crate::attempts_at_framework::v2::artificial_neural_network::neuron::relu_neuron::ReluNeuron;
+use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::Optimizer;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::error::Error;
 
 pub struct ModelBuilder {
     name: Option<String>,
@@ -10,33 +16,141 @@ pub struct ModelBuilder {
     layers: Vec<LayerBuilder>,
     input_size: Option<usize>,
     loss_function: Option<Box<dyn LossFunction>>,
+    optimizer: Option<Box<dyn Optimizer>>,
+    seed: Option<u64>,
+    regularization: Regularization,
 }
 
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LayerType {
     RELU,
     LINEAR,
+    /// outputs that add up to 1.0, usually the last layer of a policy network trained with
+    /// [`CrossEntropy`](crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::cross_entropy::CrossEntropy)
+    SOFTMAX,
+    SIGMOID,
+    TANH,
+    LEAKY_RELU,
+    ELU,
 }
 
 pub struct LayerBuilder {
+    name: Option<String>,
+    kind: LayerKind,
     layer_type: LayerType,
     number_of_neurons: usize,
+    initialization: Initialization,
+    dropout: f64,
 }
 
 impl LayerBuilder {
+    /// A fully connected layer.
     pub fn new(layer_type: LayerType, number_of_neurons: usize) -> Self {
         Self {
+            name: None,
+            kind: LayerKind::Dense,
             layer_type,
             number_of_neurons,
+            initialization: Initialization::Default,
+            dropout: 0.0,
         }
     }
 
-    pub fn build(&self, number_of_inputs: usize) -> Layer {
-        match self.layer_type {
-            LayerType::RELU => Layer::new::<ReluNeuron>(self.number_of_neurons, number_of_inputs),
-            LayerType::LINEAR => {
-                Layer::new::<LinearNeuron>(self.number_of_neurons, number_of_inputs)
+    /// A 2D convolution, the layer before it needs to output `shape.get_input_count()` values.
+    /// Initialization treats each output channel's kernel like a neuron with
+    /// `shape.get_kernel_weight_count()` inputs.
+    pub fn new_conv_2d(layer_type: LayerType, shape: Conv2DShape) -> Self {
+        let mut layer_builder = Self::new(layer_type, shape.get_output_count());
+        layer_builder.kind = LayerKind::Conv2D(shape);
+        layer_builder
+    }
+
+    /// Hands on whatever comes into it, to mark the end of the convolutions.
+    pub fn new_flatten() -> Self {
+        let mut layer_builder = Self::new(LayerType::LINEAR, 0);
+        layer_builder.kind = LayerKind::Flatten;
+        layer_builder
+    }
+
+    /// Names the layer, see [`Layer::set_name`].
+    pub fn set_name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// How the layer's starting weights are picked, [`Initialization::Default`] if not set.
+    pub fn set_initialization(mut self, initialization: Initialization) -> Self {
+        self.initialization = initialization;
+        self
+    }
+
+    /// The chance of each of the layer's outputs being dropped while training, see
+    /// [`Layer::set_dropout`].
+    pub fn set_dropout(mut self, dropout: f64) -> Self {
+        if !(0.0..1.0).contains(&dropout) {
+            panic!("Dropout must be at least 0.0 and less than 1.0")
+        }
+        self.dropout = dropout;
+        self
+    }
+
+    pub fn build(&self, number_of_inputs: usize) -> Result<Layer, Box<dyn Error>> {
+        self.build_with_rng(number_of_inputs, &mut rand::rng())
+    }
+
+    /// Same as [`LayerBuilder::build`] but any random starting weights are drawn from `rng`.
+    pub fn build_with_rng(
+        &self,
+        number_of_inputs: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<Layer, Box<dyn Error>> {
+        let activation = match self.layer_type {
+            LayerType::RELU => Activation::Relu,
+            LayerType::LINEAR => Activation::Linear,
+            LayerType::SOFTMAX => Activation::Softmax,
+            LayerType::SIGMOID => Activation::Sigmoid,
+            LayerType::TANH => Activation::Tanh,
+            LayerType::LEAKY_RELU => Activation::LeakyRelu,
+            LayerType::ELU => Activation::Elu,
+        };
+        let mut layer = match self.kind {
+            LayerKind::Dense => {
+                let (weights, biases) = self.initialization.initialize(
+                    activation,
+                    self.number_of_neurons,
+                    number_of_inputs,
+                    rng,
+                )?;
+                Layer::from_weights(activation, number_of_inputs, weights, biases)
+            }
+            LayerKind::Conv2D(shape) => {
+                shape.validate()?;
+                if shape.get_input_count() != number_of_inputs {
+                    return Err(format!(
+                        "a {}x{}x{} convolution takes {} inputs, but is getting {}",
+                        shape.input_channels,
+                        shape.input_height,
+                        shape.input_width,
+                        shape.get_input_count(),
+                        number_of_inputs
+                    ))?;
+                }
+                let (weights, biases) = self.initialization.initialize(
+                    activation,
+                    shape.output_channels,
+                    shape.get_kernel_weight_count(),
+                    rng,
+                )?;
+                Layer::conv_2d(activation, shape, weights, biases)
             }
+            LayerKind::Flatten => Layer::flatten(number_of_inputs),
+        };
+        layer.set_dropout(self.dropout);
+        if let Some(name) = &self.name {
+            layer.set_name(name.clone());
         }
+        Ok(layer)
     }
 }
 
@@ -48,6 +162,9 @@ impl ModelBuilder {
             layers: Vec::new(),
             input_size: None,
             loss_function: None,
+            optimizer: None,
+            seed: None,
+            regularization: Regularization::default(),
         }
     }
 
@@ -71,6 +188,38 @@ impl ModelBuilder {
         self
     }
 
+    /// The optimizer used by [`Model::train_batch`], plain SGD is used if this is not set.
+    pub fn set_optimizer(&mut self, optimizer: Box<dyn Optimizer>) -> &mut Self {
+        self.optimizer = Some(optimizer);
+        self
+    }
+
+    /// Seeds the starting weights, models built with the same seed and layers start out
+    /// identical. Without a seed every build starts from different weights.
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// L1 penalty on the weights, see [`Regularization::l1`].
+    pub fn set_l1(&mut self, l1: f64) -> &mut Self {
+        self.regularization.l1 = l1;
+        self
+    }
+
+    /// L2 penalty on the weights, see [`Regularization::l2`].
+    pub fn set_l2(&mut self, l2: f64) -> &mut Self {
+        self.regularization.l2 = l2;
+        self
+    }
+
+    /// Caps the length of each training step's gradient, see
+    /// [`Regularization::max_gradient_norm`].
+    pub fn set_max_gradient_norm(&mut self, max_gradient_norm: f64) -> &mut Self {
+        self.regularization.max_gradient_norm = Some(max_gradient_norm);
+        self
+    }
+
     pub fn add_layer(&mut self, layer_builder: LayerBuilder) -> &mut Self {
         self.layers.push(layer_builder);
         self
@@ -92,23 +241,33 @@ impl ModelBuilder {
             return Err("No layers have been added")?;
         }
 
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
         let mut next_input_size = self.input_size.unwrap();
-        let layers: Vec<Box<Layer>> = self
+        let layers = self
             .layers
             .iter()
             .map(|layer_builder| {
-                let layer = layer_builder.build(next_input_size);
-                next_input_size = layer_builder.number_of_neurons;
-                Box::new(layer)
+                let layer = layer_builder.build_with_rng(next_input_size, &mut rng)?;
+                next_input_size = layer.get_output_count();
+                Ok(Box::new(layer))
             })
-            .collect();
+            .collect::<Result<Vec<Box<Layer>>, Box<dyn Error>>>()?;
 
-        let model = Model::new(
+        let mut model = Model::new(
             self.name.clone().unwrap_or_else(|| "Unnamed".to_string()),
             self.version.clone().unwrap_or_else(|| "0.0.1".to_string()),
             layers,
             self.loss_function.unwrap(),
         );
+        if let Some(optimizer) = self.optimizer {
+            model.set_optimizer(optimizer);
+        }
+        model.set_regularization(self.regularization);
+        // the same seed gives the same dropout masks too
+        model.rng = rng;
         Ok(model)
     }
 }
@@ -160,4 +319,81 @@ mod tests {
         println!("Loss: {}", loss);
         println!("Predicted: {:?}", predicted);
     }
+
+    #[test]
+    fn same_seed_builds_the_same_model() {
+        let build = |seed: u64| {
+            let mut builder = ModelBuilder::new();
+            builder
+                .set_loss_function(Box::new(MeanSquaredError))
+                .set_input_size(3)
+                .set_seed(seed)
+                .add_layer(LayerBuilder::new(LayerType::RELU, 4))
+                .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+            builder.build().unwrap()
+        };
+
+        let input = vec![0.5, -1.0, 2.0];
+        let first = build(7).predict(input.clone());
+        assert_eq!(first, build(7).predict(input.clone()));
+        assert_ne!(first, build(8).predict(input));
+    }
+
+    #[test]
+    fn convolutions_must_fit_their_input() {
+        let build = |input_size: usize| {
+            let mut builder = ModelBuilder::new();
+            builder
+                .set_loss_function(Box::new(MeanSquaredError))
+                .set_input_size(input_size)
+                .add_layer(LayerBuilder::new_conv_2d(
+                    LayerType::RELU,
+                    Conv2DShape::new(12, 8, 8, 4, 3),
+                ))
+                .add_layer(LayerBuilder::new_flatten())
+                .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+            builder.build()
+        };
+        let model = build(768).unwrap();
+        assert_eq!(model.predict(vec![0.0; 768]).len(), 1);
+        assert!(build(790).is_err());
+    }
+
+    #[test]
+    fn learns_action_probabilities() {
+        use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::cross_entropy::CrossEntropy;
+        use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::adam::Adam;
+
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(CrossEntropy))
+            .set_optimizer(Box::new(Adam::new(0.05)))
+            .set_input_size(2)
+            .set_seed(3)
+            .add_layer(LayerBuilder::new(LayerType::RELU, 4))
+            .add_layer(LayerBuilder::new(LayerType::SOFTMAX, 3));
+        let mut model = builder.build().unwrap();
+
+        // the best of 3 actions for each of 2 states
+        let inputs = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        let expected = vec![vec![0.0, 0.0, 1.0], vec![1.0, 0.0, 0.0]];
+        (0..500).for_each(|_| {
+            model.train_batch(&inputs, &expected);
+        });
+
+        inputs
+            .iter()
+            .zip(expected.iter())
+            .for_each(|(input, expected)| {
+                let probabilities = model.predict(input.clone());
+                assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+                let best = expected.iter().position(|e| *e == 1.0).unwrap();
+                assert!(
+                    probabilities[best] > 0.9,
+                    "{:?} should favour action {}",
+                    probabilities,
+                    best
+                );
+            });
+    }
 }
@@ -0,0 +1,481 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::activation::Activation;
+use crate::attempts_at_framework::v2::artificial_neural_network::layer::conv2d::Conv2DShape;
+use crate::attempts_at_framework::v2::artificial_neural_network::layer::{Layer, LayerKind};
+use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::loss_function_from_name;
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// The version of the on disk format written by [`Model::save`], bump this whenever the layout
+/// below changes so old files are rejected instead of being read wrong. Version 2 only added
+/// convolution and flatten layers, so version 1 files are still read.
+pub const MODEL_FILE_FORMAT_VERSION: u32 = 2;
+
+/*
+A saved model is a plain text file, one entry per line:
+
+    format_version: 2
+    name: chess value
+    version: 0.0.1
+    loss_function: mean_squared_error
+    input_size: 790
+    layer: linear 64
+    neuron: <bias> <weight 1> <weight 2> ...
+    ...
+    layer: linear 1
+    neuron: <bias> <weight 1> ...
+
+Every `layer` line is followed by exactly as many `neuron` lines as it says it has. Floats are
+written with rust's default formatting which round trips exactly.
+
+Convolutions and flatten layers have their own `layer` lines:
+
+    layer: conv2d relu <input channels> <input height> <input width> <output channels> <kernel size> <stride> <padding>
+    neuron: <bias> <kernel weight 1> <kernel weight 2> ...
+    layer: flatten <size>
+
+with a `neuron` line per output channel, and none for a flatten layer.
+ */
+
+impl Model {
+    /// Writes the model, including every weight and bias, to the file at `path`.
+    pub fn save(&self, path: &Path) -> Result<(), ModelFileError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ModelFileError::new(format!("could not create {}: {}", parent.display(), e))
+            })?;
+        }
+        let file = File::create(path).map_err(|e| {
+            ModelFileError::new(format!("could not create {}: {}", path.display(), e))
+        })?;
+        let mut writer = BufWriter::new(file);
+        self.write_to(&mut writer)
+            .and_then(|_| writer.flush())
+            .map_err(|e| ModelFileError::new(format!("could not write {}: {}", path.display(), e)))
+    }
+
+    /// Reads a model previously written with [`Model::save`].
+    pub fn load(path: &Path) -> Result<Model, ModelFileError> {
+        let file = File::open(path).map_err(|e| {
+            ModelFileError::new(format!("could not open {}: {}", path.display(), e))
+        })?;
+        Self::read_from(BufReader::new(file))
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "format_version: {}", MODEL_FILE_FORMAT_VERSION)?;
+        writeln!(writer, "name: {}", self.name)?;
+        writeln!(writer, "version: {}", self.version)?;
+        writeln!(writer, "loss_function: {}", self.loss_function.get_name())?;
+        writeln!(writer, "input_size: {}", self.get_input_size())?;
+
+        for layer in &self.layers {
+            match layer.get_kind() {
+                LayerKind::Dense => writeln!(
+                    writer,
+                    "layer: {} {}",
+                    layer.get_activation().get_name(),
+                    layer.get_output_count()
+                )?,
+                LayerKind::Conv2D(shape) => writeln!(
+                    writer,
+                    "layer: conv2d {} {} {} {} {} {} {} {}",
+                    layer.get_activation().get_name(),
+                    shape.input_channels,
+                    shape.input_height,
+                    shape.input_width,
+                    shape.output_channels,
+                    shape.kernel_size,
+                    shape.stride,
+                    shape.padding
+                )?,
+                LayerKind::Flatten => {
+                    writeln!(writer, "layer: flatten {}", layer.get_output_count())?
+                }
+            }
+            for (output, bias) in layer.get_biases().iter().enumerate() {
+                let values = std::iter::once(*bias)
+                    .chain(layer.get_weights_for_output(output).iter().copied())
+                    .map(|v| v.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                writeln!(writer, "neuron: {}", values)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: BufRead>(reader: R) -> Result<Model, ModelFileError> {
+        let mut entries = EntryReader::new(reader);
+
+        let (line_number, format_version) = entries.next_entry("format_version")?;
+        let format_version: u32 = parse_value(&format_version, line_number)?;
+        if !(1..=MODEL_FILE_FORMAT_VERSION).contains(&format_version) {
+            return Err(ModelFileError::new(format!(
+                "unsupported model file format version {}, only up to version {} can be read",
+                format_version, MODEL_FILE_FORMAT_VERSION
+            )));
+        }
+
+        let (_, name) = entries.next_entry("name")?;
+        let (_, version) = entries.next_entry("version")?;
+
+        let (line_number, loss_function_name) = entries.next_entry("loss_function")?;
+        let loss_function = loss_function_from_name(&loss_function_name).ok_or_else(|| {
+            ModelFileError::new(format!(
+                "line {}: unknown loss function `{}`",
+                line_number, loss_function_name
+            ))
+        })?;
+
+        let (line_number, input_size) = entries.next_entry("input_size")?;
+        let input_size: usize = parse_value(&input_size, line_number)?;
+
+        let mut layers: Vec<Box<Layer>> = Vec::new();
+        let mut expected_input_count = input_size;
+        loop {
+            if !layers.is_empty() && entries.is_at_end() {
+                break;
+            }
+            let (line_number, layer_description) = entries.next_entry("layer")?;
+            let layer = match layer_description.split_whitespace().collect::<Vec<&str>>()[..] {
+                ["flatten", size] => {
+                    let size: usize = parse_value(size, line_number)?;
+                    if size != expected_input_count {
+                        return Err(ModelFileError::new(format!(
+                            "line {}: flatten layer {} has size {}, but is getting {} inputs",
+                            line_number,
+                            layers.len(),
+                            size,
+                            expected_input_count
+                        )));
+                    }
+                    Layer::flatten(size)
+                }
+                ["conv2d", activation, ref shape_values @ ..] => {
+                    let activation = parse_activation(activation, line_number)?;
+                    let shape_values = shape_values
+                        .iter()
+                        .map(|value| parse_value::<usize>(value, line_number))
+                        .collect::<Result<Vec<usize>, ModelFileError>>()?;
+                    let shape = match shape_values[..] {
+                        [input_channels, input_height, input_width, output_channels, kernel_size, stride, padding] => {
+                            Conv2DShape {
+                                input_channels,
+                                input_height,
+                                input_width,
+                                output_channels,
+                                kernel_size,
+                                stride,
+                                padding,
+                            }
+                        }
+                        _ => {
+                            return Err(ModelFileError::new(format!(
+                                "line {}: expected `conv2d <activation> <input channels> <input height> <input width> <output channels> <kernel size> <stride> <padding>` but found `{}`",
+                                line_number, layer_description
+                            )))
+                        }
+                    };
+                    shape.validate().map_err(|message| {
+                        ModelFileError::new(format!("line {}: {}", line_number, message))
+                    })?;
+                    if shape.get_input_count() != expected_input_count {
+                        return Err(ModelFileError::new(format!(
+                            "line {}: convolution layer {} takes {} inputs, but is getting {}",
+                            line_number,
+                            layers.len(),
+                            shape.get_input_count(),
+                            expected_input_count
+                        )));
+                    }
+                    let (weights, biases) = read_neurons(
+                        &mut entries,
+                        layers.len(),
+                        shape.output_channels,
+                        shape.get_kernel_weight_count(),
+                    )?;
+                    Layer::conv_2d(activation, shape, weights, biases)
+                }
+                [layer_type, neuron_count] => {
+                    let neuron_count: usize = parse_value(neuron_count, line_number)?;
+                    let activation = parse_activation(layer_type, line_number)?;
+                    let (weights, biases) = read_neurons(
+                        &mut entries,
+                        layers.len(),
+                        neuron_count,
+                        expected_input_count,
+                    )?;
+                    Layer::from_weights(activation, expected_input_count, weights, biases)
+                }
+                _ => {
+                    return Err(ModelFileError::new(format!(
+                        "line {}: expected `<layer type> <neuron count>` but found `{}`",
+                        line_number, layer_description
+                    )))
+                }
+            };
+            expected_input_count = layer.get_output_count();
+            layers.push(Box::new(layer));
+        }
+
+        Ok(Model::new(name, version, layers, loss_function))
+    }
+}
+
+fn parse_activation(name: &str, line_number: usize) -> Result<Activation, ModelFileError> {
+    Activation::from_name(name).ok_or_else(|| {
+        ModelFileError::new(format!(
+            "line {}: unknown layer type `{}`",
+            line_number, name
+        ))
+    })
+}
+
+/// Reads the `neuron` lines of a layer, each a bias followed by `weights_per_neuron` weights.
+/// Returns every weight, row after row, and the biases.
+fn read_neurons<R: BufRead>(
+    entries: &mut EntryReader<R>,
+    layer_index: usize,
+    neuron_count: usize,
+    weights_per_neuron: usize,
+) -> Result<(Vec<f64>, Vec<f64>), ModelFileError> {
+    let mut weights = Vec::with_capacity(neuron_count * weights_per_neuron);
+    let mut biases = Vec::with_capacity(neuron_count);
+    for neuron_index in 0..neuron_count {
+        let (line_number, values) = entries.next_entry("neuron")?;
+        let values = values
+            .split_whitespace()
+            .map(|v| parse_value::<f64>(v, line_number))
+            .collect::<Result<Vec<f64>, ModelFileError>>()?;
+        let (bias, neuron_weights) = values.split_first().ok_or_else(|| {
+            ModelFileError::new(format!("line {}: neuron has no bias", line_number))
+        })?;
+        if neuron_weights.len() != weights_per_neuron {
+            return Err(ModelFileError::new(format!(
+                "line {}: layer {} neuron {} has {} weights, but the layer takes {} inputs",
+                line_number,
+                layer_index,
+                neuron_index,
+                neuron_weights.len(),
+                weights_per_neuron
+            )));
+        }
+        biases.push(*bias);
+        weights.extend_from_slice(neuron_weights);
+    }
+    Ok((weights, biases))
+}
+
+/// Reads `key: value` lines one at a time, skipping blank lines.
+struct EntryReader<R: BufRead> {
+    lines: std::iter::Peekable<std::iter::Enumerate<std::io::Lines<R>>>,
+}
+
+impl<R: BufRead> EntryReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines().enumerate().peekable(),
+        }
+    }
+
+    fn is_at_end(&mut self) -> bool {
+        while let Some((_, Ok(line))) = self.lines.peek() {
+            if !line.trim().is_empty() {
+                return false;
+            }
+            self.lines.next();
+        }
+        self.lines.peek().is_none()
+    }
+
+    fn next_entry(&mut self, expected_key: &str) -> Result<(usize, String), ModelFileError> {
+        if self.is_at_end() {
+            return Err(ModelFileError::new(format!(
+                "unexpected end of file, expected `{}`",
+                expected_key
+            )));
+        }
+        let (index, line) = self.lines.next().unwrap();
+        let line_number = index + 1;
+        let line = line.map_err(|e| {
+            ModelFileError::new(format!("could not read line {}: {}", line_number, e))
+        })?;
+        match line.split_once(':') {
+            Some((key, value)) if key.trim() == expected_key => {
+                Ok((line_number, value.trim().to_string()))
+            }
+            _ => Err(ModelFileError::new(format!(
+                "line {}: expected `{}` but found `{}`",
+                line_number, expected_key, line
+            ))),
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str, line_number: usize) -> Result<T, ModelFileError> {
+    value.parse::<T>().map_err(|_| {
+        ModelFileError::new(format!(
+            "line {}: could not parse `{}` as a {}",
+            line_number,
+            value,
+            std::any::type_name::<T>()
+        ))
+    })
+}
+
+pub struct ModelFileError {
+    message: String,
+}
+
+impl ModelFileError {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl Debug for ModelFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ModelFileError: {}", self.message)
+    }
+}
+
+impl Display for ModelFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ModelFileError: {}", self.message)
+    }
+}
+
+impl Error for ModelFileError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+
+    fn build_model() -> Model {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_name("test model".to_string())
+            .set_version("1.2.3".to_string())
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(3)
+            .add_layer(LayerBuilder::new(LayerType::RELU, 4))
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 2));
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn round_trip_keeps_weights_and_predictions() {
+        let mut model = build_model();
+        model.train(vec![1.0, 0.5, -0.25], vec![1.0, -1.0], 0.01);
+
+        let mut buffer = Vec::new();
+        model.write_to(&mut buffer).unwrap();
+        let loaded = Model::read_from(buffer.as_slice()).unwrap();
+
+        assert_eq!(loaded.get_name(), "test model");
+        assert_eq!(loaded.get_version(), "1.2.3");
+        assert_eq!(loaded.get_loss_function().get_name(), "mean_squared_error");
+        assert_eq!(loaded.get_input_size(), 3);
+
+        let input = vec![0.3, -0.7, 2.0];
+        assert_eq!(model.predict(input.clone()), loaded.predict(input));
+
+        let mut reloaded_buffer = Vec::new();
+        loaded.write_to(&mut reloaded_buffer).unwrap();
+        assert_eq!(buffer, reloaded_buffer);
+    }
+
+    #[test]
+    fn save_and_load_from_disk() {
+        let model = build_model();
+        let path = std::env::temp_dir().join("reinforcement_learning_model_file_test.model");
+
+        model.save(&path).unwrap();
+        let loaded = Model::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let input = vec![1.0, 2.0, 3.0];
+        assert_eq!(model.predict(input.clone()), loaded.predict(input));
+    }
+
+    #[test]
+    fn mismatched_input_size_is_an_error() {
+        let file = "format_version: 1
+name: broken
+version: 0.0.1
+loss_function: mean_squared_error
+input_size: 2
+layer: relu 1
+neuron: 0 1 1
+layer: linear 1
+neuron: 0 1 1
+";
+        let error = Model::read_from(file.as_bytes()).err().unwrap();
+        assert!(
+            error.to_string().contains("layer 1 neuron 0 has 2 weights"),
+            "unexpected error: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn round_trip_with_convolutions() {
+        use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::Conv2DShape;
+
+        let mut shape = Conv2DShape::new(2, 4, 4, 3, 3);
+        shape.padding = 1;
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(shape.get_input_count())
+            .add_layer(LayerBuilder::new_conv_2d(LayerType::RELU, shape))
+            .add_layer(LayerBuilder::new_flatten())
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        let model = builder.build().unwrap();
+
+        let mut buffer = Vec::new();
+        model.write_to(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.contains("layer: conv2d relu 2 4 4 3 3 1 1\n"));
+        assert!(text.contains("layer: flatten 48\nlayer: linear 1\n"));
+
+        let loaded = Model::read_from(buffer.as_slice()).unwrap();
+        let input = (0..32).map(|i| i as f64 / 10.0).collect::<Vec<f64>>();
+        assert_eq!(model.predict(input.clone()), loaded.predict(input));
+    }
+
+    #[test]
+    fn convolution_that_does_not_fit_is_an_error() {
+        let file = "format_version: 2
+name: broken
+version: 0.0.1
+loss_function: mean_squared_error
+input_size: 10
+layer: conv2d relu 1 3 3 1 2 1 0
+neuron: 0 1 1 1 1
+";
+        let error = Model::read_from(file.as_bytes()).err().unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("takes 9 inputs, but is getting 10"),
+            "unexpected error: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn unknown_format_version_is_an_error() {
+        let file = "format_version: 99\nname: future\n";
+        let error = Model::read_from(file.as_bytes()).err().unwrap();
+        assert!(error.to_string().contains("unsupported model file format"));
+    }
+}
@@ -0,0 +1,66 @@
+/// # Regularization
+///
+/// Penalties on large weights, and a cap on how big a step training can take, set with
+/// [`ModelBuilder`](super::model_builder::ModelBuilder) or
+/// [`Model::set_regularization`](super::Model::set_regularization). Only weights are penalized,
+/// never biases. Dropout is set per layer on the
+/// [`LayerBuilder`](super::model_builder::LayerBuilder).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Regularization {
+    /// `l1 * Σ|w|` is added to the loss, which pushes unneeded weights all the way to 0.0
+    pub l1: f64,
+    /// `l2 * Σw² / 2` is added to the loss, weight decay, which keeps every weight small
+    pub l2: f64,
+    /// The gradient of all the weights and biases together is scaled down to this length
+    /// whenever it is longer, so one bad batch can't throw the weights miles off.
+    pub max_gradient_norm: Option<f64>,
+}
+
+impl Regularization {
+    /// How the penalties move `weight`, in the same direction as the gradients the layers work out.
+    pub fn get_weight_gradient(&self, weight: f64) -> f64 {
+        let l1 = if weight > 0.0 {
+            self.l1
+        } else if weight < 0.0 {
+            -self.l1
+        } else {
+            0.0
+        };
+        -self.l2 * weight - l1
+    }
+
+    /// The penalties' share of the loss for the given weights.
+    pub fn get_penalty<'a>(&self, weights: impl Iterator<Item = &'a f64>) -> f64 {
+        weights
+            .map(|w| self.l1 * w.abs() + self.l2 * w.powi(2) / 2.0)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weight_gradient_matches_the_change_in_penalty() {
+        let regularization = Regularization {
+            l1: 0.3,
+            l2: 0.05,
+            max_gradient_norm: None,
+        };
+        let epsilon = 1e-6;
+        [-2.0, -0.4, 0.1, 1.5].iter().for_each(|weight: &f64| {
+            let numerical = -(regularization.get_penalty([weight + epsilon].iter())
+                - regularization.get_penalty([*weight].iter()))
+                / epsilon;
+            assert!(
+                (numerical - regularization.get_weight_gradient(*weight)).abs() < 1e-4,
+                "weight {} had a gradient of {} but expected {}",
+                weight,
+                regularization.get_weight_gradient(*weight),
+                numerical
+            );
+        });
+        assert_eq!(regularization.get_weight_gradient(0.0), 0.0);
+    }
+}
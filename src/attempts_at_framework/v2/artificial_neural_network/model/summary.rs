@@ -0,0 +1,304 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::activation::Activation;
+use crate::attempts_at_framework::v2::artificial_neural_network::layer::LayerKind;
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+use std::fmt::{Display, Formatter};
+
+/// One layer of a [`ModelSummary`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerSummary {
+    pub name: Option<String>,
+    /// `dense`, `conv2d` or `flatten`, as in saved model files
+    pub kind: &'static str,
+    pub activation: Activation,
+    /// `[values]` for dense and flatten layers, `[channels, height, width]` around convolutions
+    pub input_shape: Vec<usize>,
+    pub output_shape: Vec<usize>,
+    /// weights and biases
+    pub parameter_count: usize,
+}
+
+/// # Model Summary
+///
+/// The shape of a model layer by layer, from [`Model::get_summary`]. Displaying it gives a table
+/// like:
+///
+/// ```text
+/// chess value 0.0.1, mean_squared_error loss
+/// layer   name            type     activation  input      output     parameters
+/// 0       board           conv2d   relu        12x8x8     16x6x6     1744
+/// 1                       flatten  linear      16x6x6     576        0
+/// 2                       dense    linear      576        1          577
+/// total parameters: 2321
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelSummary {
+    pub name: String,
+    pub version: String,
+    pub loss_function: String,
+    pub input_size: usize,
+    pub layers: Vec<LayerSummary>,
+    pub parameter_count: usize,
+}
+
+impl Display for ModelSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let shape_text = |shape: &[usize]| {
+            shape
+                .iter()
+                .map(|size| size.to_string())
+                .collect::<Vec<String>>()
+                .join("x")
+        };
+
+        writeln!(
+            f,
+            "{} {}, {} loss",
+            self.name, self.version, self.loss_function
+        )?;
+        writeln!(
+            f,
+            "{:<8}{:<16}{:<9}{:<12}{:<11}{:<11}parameters",
+            "layer", "name", "type", "activation", "input", "output"
+        )?;
+        for (index, layer) in self.layers.iter().enumerate() {
+            writeln!(
+                f,
+                "{:<8}{:<16}{:<9}{:<12}{:<11}{:<11}{}",
+                index,
+                layer.name.as_deref().unwrap_or(""),
+                layer.kind,
+                layer.activation.get_name(),
+                shape_text(&layer.input_shape),
+                shape_text(&layer.output_shape),
+                layer.parameter_count
+            )?;
+        }
+        write!(f, "total parameters: {}", self.parameter_count)
+    }
+}
+
+/// The mean, standard deviation, min and max of a list of values, all 0.0 for an empty list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValueStats {
+    pub count: usize,
+    pub mean: f64,
+    pub std: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ValueStats {
+    pub fn from_values(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self {
+                count: 0,
+                mean: 0.0,
+                std: 0.0,
+                min: 0.0,
+                max: 0.0,
+            };
+        }
+
+        let count = values.len();
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count as f64;
+        Self {
+            count,
+            mean,
+            std: variance.sqrt(),
+            min: values.iter().cloned().fold(f64::INFINITY, f64::min),
+            max: values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+/// How one layer's weights look, from [`Model::get_layer_stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerStats {
+    pub name: Option<String>,
+    pub weights: ValueStats,
+    pub biases: ValueStats,
+    /// The fraction of a ReLU layer's outputs that were 0.0 for every one of the sample inputs,
+    /// those neurons get no gradient so never learn again. `None` for other activations or when
+    /// there were no samples.
+    pub dead_relu_fraction: Option<f64>,
+}
+
+impl Model {
+    pub fn get_summary(&self) -> ModelSummary {
+        let mut input_shape = vec![self.get_input_size()];
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let (kind, layer_input_shape, output_shape) = match layer.get_kind() {
+                    LayerKind::Dense => (
+                        "dense",
+                        vec![layer.get_input_count()],
+                        vec![layer.get_output_count()],
+                    ),
+                    LayerKind::Conv2D(shape) => (
+                        "conv2d",
+                        vec![shape.input_channels, shape.input_height, shape.input_width],
+                        vec![
+                            shape.output_channels,
+                            shape.get_output_height(),
+                            shape.get_output_width(),
+                        ],
+                    ),
+                    // what's being flattened is whatever shape the layer before handed on
+                    LayerKind::Flatten => (
+                        "flatten",
+                        input_shape.clone(),
+                        vec![layer.get_output_count()],
+                    ),
+                };
+                input_shape = output_shape.clone();
+                LayerSummary {
+                    name: layer.get_name().cloned(),
+                    kind,
+                    activation: layer.get_activation(),
+                    input_shape: layer_input_shape,
+                    output_shape,
+                    parameter_count: layer.get_weights().len() + layer.get_biases().len(),
+                }
+            })
+            .collect();
+
+        ModelSummary {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            loss_function: self.loss_function.get_name().to_string(),
+            input_size: self.get_input_size(),
+            layers,
+            parameter_count: self.get_parameter_count(),
+        }
+    }
+
+    /// Statistics for every layer's weights and biases. `inputs` are only used to find dead
+    /// ReLUs, pass a handful of typical inputs, or none to skip that.
+    pub fn get_layer_stats(&self, inputs: &[Vec<f64>]) -> Vec<LayerStats> {
+        let mut layer_inputs = inputs.concat();
+        self.layers
+            .iter()
+            .map(|layer| {
+                let dead_relu_fraction = if inputs.is_empty() {
+                    None
+                } else {
+                    let outputs = layer.forward_batch(&layer_inputs, inputs.len());
+                    let dead_fraction = match layer.get_activation() {
+                        Activation::Relu if layer.get_output_count() > 0 => {
+                            let output_count = layer.get_output_count();
+                            let dead_count = (0..output_count)
+                                .filter(|output| {
+                                    outputs
+                                        .iter()
+                                        .skip(*output)
+                                        .step_by(output_count)
+                                        .all(|value| *value <= 0.0)
+                                })
+                                .count();
+                            Some(dead_count as f64 / output_count as f64)
+                        }
+                        _ => None,
+                    };
+                    layer_inputs = outputs;
+                    dead_fraction
+                };
+
+                LayerStats {
+                    name: layer.get_name().cloned(),
+                    weights: ValueStats::from_values(layer.get_weights()),
+                    biases: ValueStats::from_values(layer.get_biases()),
+                    dead_relu_fraction,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::initialization::Initialization;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        Conv2DShape, LayerBuilder, LayerType, ModelBuilder,
+    };
+
+    #[test]
+    fn summarises_every_layer() {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_name("chess value".to_string())
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(12 * 8 * 8)
+            .add_layer(
+                LayerBuilder::new_conv_2d(LayerType::RELU, Conv2DShape::new(12, 8, 8, 16, 3))
+                    .set_name("board".to_string()),
+            )
+            .add_layer(LayerBuilder::new_flatten())
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        let summary = builder.build().unwrap().get_summary();
+
+        assert_eq!(summary.input_size, 768);
+        assert_eq!(summary.loss_function, "mean_squared_error");
+        assert_eq!(summary.layers.len(), 3);
+        assert_eq!(summary.layers[0].name.as_deref(), Some("board"));
+        assert_eq!(summary.layers[0].kind, "conv2d");
+        assert_eq!(summary.layers[0].input_shape, vec![12, 8, 8]);
+        assert_eq!(summary.layers[0].output_shape, vec![16, 6, 6]);
+        assert_eq!(summary.layers[0].parameter_count, 16 * 12 * 9 + 16);
+        assert_eq!(summary.layers[1].input_shape, vec![16, 6, 6]);
+        assert_eq!(summary.layers[1].output_shape, vec![576]);
+        assert_eq!(summary.layers[1].parameter_count, 0);
+        assert_eq!(summary.layers[2].name, None);
+        assert_eq!(summary.layers[2].activation, Activation::Linear);
+        assert_eq!(summary.parameter_count, 1744 + 577);
+
+        let text = summary.to_string();
+        assert!(text.starts_with("chess value 0.0.1, mean_squared_error loss\n"));
+        assert!(text.contains("board"));
+        assert!(text.contains("12x8x8"));
+        assert!(text.ends_with("total parameters: 2321"));
+    }
+
+    #[test]
+    fn value_stats() {
+        let stats = ValueStats::from_values(&[1.0, 3.0, -2.0, 6.0]);
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.mean, 2.0);
+        assert_eq!(stats.std, 8.5_f64.sqrt());
+        assert_eq!(stats.min, -2.0);
+        assert_eq!(stats.max, 6.0);
+        assert_eq!(ValueStats::from_values(&[]).count, 0);
+    }
+
+    #[test]
+    fn finds_dead_relus() {
+        // the second neuron's bias keeps it below zero for every input
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(2)
+            .add_layer(
+                LayerBuilder::new(LayerType::RELU, 2)
+                    .set_initialization(Initialization::Zeros)
+                    .set_name("hidden".to_string()),
+            )
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        let mut model = builder.build().unwrap();
+        model.add_to_parameters(&[1.0, 1.0, 1.0, 1.0, 0.0, -10.0, 0.0, 0.0, 0.0]);
+
+        let inputs = vec![vec![1.0, 2.0], vec![-0.5, 3.0], vec![2.0, 2.0]];
+        let stats = model.get_layer_stats(&inputs);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].name.as_deref(), Some("hidden"));
+        assert_eq!(stats[0].weights.mean, 1.0);
+        assert_eq!(stats[0].biases.min, -10.0);
+        assert_eq!(stats[0].dead_relu_fraction, Some(0.5));
+        assert_eq!(stats[1].dead_relu_fraction, None);
+
+        assert_eq!(model.get_layer_stats(&[])[0].dead_relu_fraction, None);
+    }
+}
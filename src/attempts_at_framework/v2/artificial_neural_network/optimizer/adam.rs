@@ -0,0 +1,106 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::Optimizer;
+use std::collections::HashMap;
+
+struct AdamState {
+    step: i32,
+    first_moments: Vec<f64>,
+    second_moments: Vec<f64>,
+}
+
+/// Adam, combines momentum with RMSProp and corrects both averages for starting at zero.
+pub struct Adam {
+    learning_rate: f64,
+    beta_1: f64,
+    beta_2: f64,
+    epsilon: f64,
+    states: HashMap<usize, AdamState>,
+}
+
+impl Adam {
+    /// Uses the usual defaults of 0.9 and 0.999 for the moment decay rates.
+    pub fn new(learning_rate: f64) -> Self {
+        Self::with_decay_rates(learning_rate, 0.9, 0.999)
+    }
+
+    pub fn with_decay_rates(learning_rate: f64, beta_1: f64, beta_2: f64) -> Self {
+        if !(0.0..1.0).contains(&beta_1) || !(0.0..1.0).contains(&beta_2) {
+            panic!("decay rates must be between 0.0 and 1.0");
+        }
+        Self {
+            learning_rate,
+            beta_1,
+            beta_2,
+            epsilon: 1e-8,
+            states: HashMap::new(),
+        }
+    }
+}
+
+impl Optimizer for Adam {
+    fn update(&mut self, parameter_id: usize, parameters: &mut [f64], gradients: &[f64]) {
+        let state = self
+            .states
+            .entry(parameter_id)
+            .or_insert_with(|| AdamState {
+                step: 0,
+                first_moments: vec![0.0; parameters.len()],
+                second_moments: vec![0.0; parameters.len()],
+            });
+        assert_eq!(
+            state.first_moments.len(),
+            parameters.len(),
+            "parameter {} changed size between updates",
+            parameter_id
+        );
+
+        state.step += 1;
+        let first_correction = 1.0 - self.beta_1.powi(state.step);
+        let second_correction = 1.0 - self.beta_2.powi(state.step);
+
+        parameters
+            .iter_mut()
+            .zip(gradients)
+            .zip(state.first_moments.iter_mut())
+            .zip(state.second_moments.iter_mut())
+            .for_each(|(((p, g), m), v)| {
+                *m = self.beta_1 * *m + (1.0 - self.beta_1) * g;
+                *v = self.beta_2 * *v + (1.0 - self.beta_2) * g * g;
+                let m_hat = *m / first_correction;
+                let v_hat = *v / second_correction;
+                *p += self.learning_rate * m_hat / (v_hat.sqrt() + self.epsilon);
+            });
+    }
+
+    fn get_name(&self) -> &'static str {
+        "adam"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::tests::distance_after_steps;
+
+    #[test]
+    fn first_step_is_the_learning_rate() {
+        let mut parameters = vec![0.0, 0.0];
+        Adam::new(0.1).update(0, &mut parameters, &[5.0, -0.01]);
+        assert!((parameters[0] - 0.1).abs() < 1e-6);
+        assert!((parameters[1] + 0.1).abs() < 1e-4);
+    }
+
+    #[test]
+    fn parameters_are_tracked_separately() {
+        let mut optimizer = Adam::new(0.1);
+        let mut first = vec![0.0];
+        let mut second = vec![0.0, 0.0];
+        optimizer.update(0, &mut first, &[1.0]);
+        optimizer.update(1, &mut second, &[1.0, 1.0]);
+        assert_eq!(first[0], second[0]);
+    }
+
+    #[test]
+    fn converges() {
+        assert!(distance_after_steps(&mut Adam::new(0.05), 2000) < 1e-3);
+    }
+}
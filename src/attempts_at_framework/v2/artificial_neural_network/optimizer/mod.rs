@@ -0,0 +1,61 @@
+pub mod adam;
+pub mod momentum;
+pub mod rms_prop;
+pub mod sgd;
+
+/// # Optimizer
+///
+/// Layers work out the gradient for each of their parameters, the optimizer decides how far to
+/// move each parameter along it. Following the rest of the network, gradients point in the
+/// direction that lowers the loss, so optimizers add to the parameters.
+///
+/// Any per parameter state, like Adam's moments, lives in the optimizer and is keyed by
+/// `parameter_id`, which is unique to each group of parameters (for example a layer's weights).
+pub trait Optimizer: Send + Sync {
+    fn update(&mut self, parameter_id: usize, parameters: &mut [f64], gradients: &[f64]);
+    fn get_name(&self) -> &'static str;
+}
+
+/// Gets the state kept for a group of parameters, creating it filled with zeros the first time
+/// it is seen.
+fn get_state(
+    states: &mut std::collections::HashMap<usize, Vec<f64>>,
+    parameter_id: usize,
+    size: usize,
+) -> &mut Vec<f64> {
+    let state = states
+        .entry(parameter_id)
+        .or_insert_with(|| vec![0.0; size]);
+    assert_eq!(
+        state.len(),
+        size,
+        "parameter {} changed size between updates",
+        parameter_id
+    );
+    state
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Moves the parameters towards the target with the gradient of a squared error, returns how
+    /// far they ended up from it.
+    pub fn distance_after_steps(optimizer: &mut dyn Optimizer, steps: usize) -> f64 {
+        let target = [3.0, -2.0, 0.5];
+        let mut parameters = vec![0.0; 3];
+        (0..steps).for_each(|_| {
+            let gradients = target
+                .iter()
+                .zip(parameters.iter())
+                .map(|(t, p)| t - p)
+                .collect::<Vec<f64>>();
+            optimizer.update(0, &mut parameters, &gradients);
+        });
+        target
+            .iter()
+            .zip(parameters.iter())
+            .map(|(t, p)| (t - p).abs())
+            .sum()
+    }
+}
@@ -0,0 +1,64 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::{
+    get_state, Optimizer,
+};
+use std::collections::HashMap;
+
+/// Gradient descent with momentum, each parameter keeps a velocity that is a decaying sum of its
+/// past gradients, which smooths out noisy single sample updates.
+pub struct Momentum {
+    learning_rate: f64,
+    momentum: f64,
+    velocities: HashMap<usize, Vec<f64>>,
+}
+
+impl Momentum {
+    pub fn new(learning_rate: f64, momentum: f64) -> Self {
+        if !(0.0..1.0).contains(&momentum) {
+            panic!("momentum must be between 0.0 and 1.0");
+        }
+        Self {
+            learning_rate,
+            momentum,
+            velocities: HashMap::new(),
+        }
+    }
+}
+
+impl Optimizer for Momentum {
+    fn update(&mut self, parameter_id: usize, parameters: &mut [f64], gradients: &[f64]) {
+        let velocities = get_state(&mut self.velocities, parameter_id, parameters.len());
+        parameters
+            .iter_mut()
+            .zip(gradients)
+            .zip(velocities.iter_mut())
+            .for_each(|((p, g), v)| {
+                *v = self.momentum * *v + g;
+                *p += self.learning_rate * *v;
+            });
+    }
+
+    fn get_name(&self) -> &'static str {
+        "momentum"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::tests::distance_after_steps;
+
+    #[test]
+    fn velocity_builds_up() {
+        let mut optimizer = Momentum::new(0.1, 0.9);
+        let mut parameters = vec![0.0];
+        optimizer.update(0, &mut parameters, &[1.0]);
+        optimizer.update(0, &mut parameters, &[1.0]);
+        // 0.1 * 1.0 then 0.1 * (0.9 + 1.0)
+        assert!((parameters[0] - 0.29).abs() < 1e-12);
+    }
+
+    #[test]
+    fn converges() {
+        assert!(distance_after_steps(&mut Momentum::new(0.05, 0.9), 500) < 1e-6);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::{
+    get_state, Optimizer,
+};
+use std::collections::HashMap;
+
+/// RMSProp, each parameter's step is divided by a running average of its squared gradients, so
+/// parameters with large gradients take smaller steps.
+pub struct RmsProp {
+    learning_rate: f64,
+    decay_rate: f64,
+    epsilon: f64,
+    squared_averages: HashMap<usize, Vec<f64>>,
+}
+
+impl RmsProp {
+    pub fn new(learning_rate: f64, decay_rate: f64) -> Self {
+        if !(0.0..1.0).contains(&decay_rate) {
+            panic!("decay rate must be between 0.0 and 1.0");
+        }
+        Self {
+            learning_rate,
+            decay_rate,
+            epsilon: 1e-8,
+            squared_averages: HashMap::new(),
+        }
+    }
+}
+
+impl Optimizer for RmsProp {
+    fn update(&mut self, parameter_id: usize, parameters: &mut [f64], gradients: &[f64]) {
+        let squared_averages =
+            get_state(&mut self.squared_averages, parameter_id, parameters.len());
+        parameters
+            .iter_mut()
+            .zip(gradients)
+            .zip(squared_averages.iter_mut())
+            .for_each(|((p, g), s)| {
+                *s = self.decay_rate * *s + (1.0 - self.decay_rate) * g * g;
+                *p += self.learning_rate * g / (s.sqrt() + self.epsilon);
+            });
+    }
+
+    fn get_name(&self) -> &'static str {
+        "rms_prop"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::tests::distance_after_steps;
+
+    #[test]
+    fn step_size_does_not_depend_on_gradient_size() {
+        let mut small = vec![0.0];
+        let mut large = vec![0.0];
+        RmsProp::new(0.1, 0.9).update(0, &mut small, &[0.001]);
+        RmsProp::new(0.1, 0.9).update(0, &mut large, &[1000.0]);
+        assert!((small[0] - large[0]).abs() < 1e-4);
+    }
+
+    #[test]
+    fn converges() {
+        // RMSProp keeps bouncing around the target by about its learning rate
+        assert!(distance_after_steps(&mut RmsProp::new(0.01, 0.9), 1000) < 0.1);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::Optimizer;
+
+/// Plain stochastic gradient descent, every parameter moves `learning_rate` times its gradient.
+pub struct Sgd {
+    learning_rate: f64,
+}
+
+impl Sgd {
+    pub fn new(learning_rate: f64) -> Self {
+        Self { learning_rate }
+    }
+}
+
+impl Optimizer for Sgd {
+    fn update(&mut self, _parameter_id: usize, parameters: &mut [f64], gradients: &[f64]) {
+        parameters
+            .iter_mut()
+            .zip(gradients)
+            .for_each(|(p, g)| *p += self.learning_rate * g);
+    }
+
+    fn get_name(&self) -> &'static str {
+        "sgd"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::tests::distance_after_steps;
+
+    #[test]
+    fn single_step() {
+        let mut parameters = vec![1.0, 2.0];
+        Sgd::new(0.1).update(0, &mut parameters, &[1.0, -2.0]);
+        assert_eq!(parameters, vec![1.1, 1.8]);
+    }
+
+    #[test]
+    fn converges() {
+        assert!(distance_after_steps(&mut Sgd::new(0.1), 200) < 1e-6);
+    }
+}
@@ -0,0 +1,374 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+use crate::service::LineChartData;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+/// How the model did over one epoch, handed to the callback and kept in the [`TrainingHistory`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochMetrics {
+    /// counts from 1
+    pub epoch: usize,
+    /// the average loss of the training samples, each taken before its batch's update
+    pub training_loss: f64,
+    /// the average loss of the validation samples after the epoch, if there are any
+    pub validation_loss: Option<f64>,
+}
+
+/// Every epoch [`Trainer::train`] ran, and whether early stopping cut it short.
+#[derive(Debug, Clone)]
+pub struct TrainingHistory {
+    pub epochs: Vec<EpochMetrics>,
+    pub stopped_early: bool,
+}
+
+impl TrainingHistory {
+    /// `(epoch, training loss)` points, ready for [`LineChartData::new`]
+    pub fn get_training_loss_points(&self) -> Vec<(f32, f32)> {
+        self.epochs
+            .iter()
+            .map(|metrics| (metrics.epoch as f32, metrics.training_loss as f32))
+            .collect()
+    }
+
+    /// `(epoch, validation loss)` points, empty when there was no validation split
+    pub fn get_validation_loss_points(&self) -> Vec<(f32, f32)> {
+        self.epochs
+            .iter()
+            .filter_map(|metrics| {
+                metrics
+                    .validation_loss
+                    .map(|loss| (metrics.epoch as f32, loss as f32))
+            })
+            .collect()
+    }
+
+    /// The training loss, and the validation loss when there is one, as lines for a
+    /// [`LineChartBuilder`](crate::service::LineChartBuilder).
+    pub fn get_chart_data(&self) -> Vec<LineChartData> {
+        let mut data = vec![LineChartData::new(
+            "Training loss".to_string(),
+            self.get_training_loss_points(),
+        )];
+        let validation_points = self.get_validation_loss_points();
+        if !validation_points.is_empty() {
+            data.push(LineChartData::new(
+                "Validation loss".to_string(),
+                validation_points,
+            ));
+        }
+        data
+    }
+}
+
+/// called by the [`Trainer`] at the end of every epoch
+pub type EpochCallback = Box<dyn FnMut(&EpochMetrics)>;
+
+/// # Trainer
+///
+/// Runs the epoch loop around [`Model::train_batch`], so the model's optimizer does the updates.
+///
+/// ```ignore
+/// let mut trainer = Trainer::new(100, 16);
+/// trainer
+///     .set_validation_split(0.2)
+///     .set_early_stopping(10)
+///     .set_callback(Box::new(|metrics| println!("{:?}", metrics)));
+/// let history = trainer.train(&mut model, &inputs, &expected)?;
+/// ```
+pub struct Trainer {
+    epochs: usize,
+    batch_size: usize,
+    shuffle: bool,
+    validation_split: f64,
+    patience: Option<usize>,
+    callback: Option<EpochCallback>,
+    rng: StdRng,
+}
+
+impl Trainer {
+    /// Shuffles every epoch, with no validation split and no early stopping, until set otherwise.
+    pub fn new(epochs: usize, batch_size: usize) -> Self {
+        Self {
+            epochs,
+            batch_size,
+            shuffle: true,
+            validation_split: 0.0,
+            patience: None,
+            callback: None,
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// Whether the training samples are put in a new order every epoch.
+    pub fn set_shuffle(&mut self, shuffle: bool) -> &mut Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// The fraction of the samples held back to measure the model on rather than train it with.
+    /// The last samples are held back, before any shuffling, so the same samples are held back
+    /// every epoch.
+    pub fn set_validation_split(&mut self, validation_split: f64) -> &mut Self {
+        self.validation_split = validation_split;
+        self
+    }
+
+    /// Stops once the loss hasn't improved for `patience` epochs in a row, either way the model
+    /// is left with the weights from its best epoch. The validation loss is watched when there is
+    /// a validation split, otherwise the training loss.
+    pub fn set_early_stopping(&mut self, patience: usize) -> &mut Self {
+        self.patience = Some(patience);
+        self
+    }
+
+    /// Called at the end of every epoch, for printing progress or collecting points to plot.
+    pub fn set_callback(&mut self, callback: EpochCallback) -> &mut Self {
+        self.callback = Some(callback);
+        self
+    }
+
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn train(
+        &mut self,
+        model: &mut Model,
+        inputs: &[Vec<f64>],
+        expected: &[Vec<f64>],
+    ) -> Result<TrainingHistory, TrainerError> {
+        if inputs.len() != expected.len() {
+            return Err(TrainerError::new(format!(
+                "{} inputs but {} expected outputs",
+                inputs.len(),
+                expected.len()
+            )));
+        }
+        if self.batch_size == 0 {
+            return Err(TrainerError::new(
+                "batch size must be at least 1".to_string(),
+            ));
+        }
+        if !(0.0..1.0).contains(&self.validation_split) {
+            return Err(TrainerError::new(format!(
+                "validation split must be at least 0.0 and less than 1.0, not {}",
+                self.validation_split
+            )));
+        }
+
+        let validation_count = (inputs.len() as f64 * self.validation_split).round() as usize;
+        let training_count = inputs.len() - validation_count;
+        if training_count == 0 {
+            return Err(TrainerError::new(format!(
+                "a validation split of {} leaves nothing to train on",
+                self.validation_split
+            )));
+        }
+        let (validation_inputs, validation_expected) =
+            (&inputs[training_count..], &expected[training_count..]);
+
+        let mut order = (0..training_count).collect::<Vec<usize>>();
+        let mut history = TrainingHistory {
+            epochs: Vec::with_capacity(self.epochs),
+            stopped_early: false,
+        };
+        let mut best: Option<(f64, Model)> = None;
+        let mut epochs_without_improvement = 0;
+
+        for epoch in 1..=self.epochs {
+            if self.shuffle {
+                order.shuffle(&mut self.rng);
+            }
+
+            let training_loss = order
+                .chunks(self.batch_size)
+                .map(|batch| {
+                    let batch_inputs = batch
+                        .iter()
+                        .map(|index| inputs[*index].clone())
+                        .collect::<Vec<Vec<f64>>>();
+                    let batch_expected = batch
+                        .iter()
+                        .map(|index| expected[*index].clone())
+                        .collect::<Vec<Vec<f64>>>();
+                    model.train_batch(&batch_inputs, &batch_expected) * batch.len() as f64
+                })
+                .sum::<f64>()
+                / training_count as f64;
+
+            let validation_loss = if validation_count > 0 {
+                Some(Self::average_loss(
+                    model,
+                    validation_inputs,
+                    validation_expected,
+                ))
+            } else {
+                None
+            };
+
+            let metrics = EpochMetrics {
+                epoch,
+                training_loss,
+                validation_loss,
+            };
+            if let Some(callback) = self.callback.as_mut() {
+                callback(&metrics);
+            }
+            history.epochs.push(metrics);
+
+            if let Some(patience) = self.patience {
+                let watched_loss = validation_loss.unwrap_or(training_loss);
+                match &best {
+                    Some((best_loss, _)) if watched_loss >= *best_loss => {
+                        epochs_without_improvement += 1;
+                    }
+                    _ => {
+                        best = Some((watched_loss, model.clone_weights()));
+                        epochs_without_improvement = 0;
+                    }
+                }
+                if epochs_without_improvement >= patience {
+                    history.stopped_early = epoch < self.epochs;
+                    break;
+                }
+            }
+        }
+
+        if let Some((_, best_model)) = best {
+            model.copy_weights_from(&best_model);
+        }
+        Ok(history)
+    }
+
+    fn average_loss(model: &Model, inputs: &[Vec<f64>], expected: &[Vec<f64>]) -> f64 {
+        inputs
+            .iter()
+            .zip(expected)
+            .map(|(input, expected)| {
+                model
+                    .get_loss_function()
+                    .calculate_loss(expected, &model.predict(input.clone()))
+            })
+            .sum::<f64>()
+            / inputs.len() as f64
+    }
+}
+
+pub struct TrainerError {
+    message: String,
+}
+
+impl TrainerError {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl Debug for TrainerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TrainerError: {}", self.message)
+    }
+}
+
+impl Display for TrainerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TrainerError: {}", self.message)
+    }
+}
+
+impl Error for TrainerError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+    use crate::attempts_at_framework::v2::artificial_neural_network::optimizer::sgd::Sgd;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn build_model(learning_rate: f64) -> Model {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_optimizer(Box::new(Sgd::new(learning_rate)))
+            .set_input_size(2)
+            .set_seed(1)
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        builder.build().unwrap()
+    }
+
+    /// samples of `2x + y`
+    fn data() -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        let inputs = (0..20)
+            .map(|i| vec![(i % 5) as f64 - 2.0, (i / 5) as f64 - 1.5])
+            .collect::<Vec<Vec<f64>>>();
+        let expected = inputs
+            .iter()
+            .map(|input| vec![2.0 * input[0] + input[1]])
+            .collect();
+        (inputs, expected)
+    }
+
+    #[test]
+    fn learns_a_linear_function() {
+        let (inputs, expected) = data();
+        let mut model = build_model(0.05);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let seen_by_callback = seen.clone();
+
+        let mut trainer = Trainer::new(50, 4);
+        trainer
+            .set_seed(2)
+            .set_validation_split(0.25)
+            .set_callback(Box::new(move |metrics| {
+                seen_by_callback.borrow_mut().push(metrics.epoch)
+            }));
+        let history = trainer.train(&mut model, &inputs, &expected).unwrap();
+
+        assert_eq!(history.epochs.len(), 50);
+        assert!(!history.stopped_early);
+        assert_eq!(*seen.borrow(), (1..=50).collect::<Vec<usize>>());
+        let last = history.epochs.last().unwrap();
+        assert!(last.training_loss < history.epochs[0].training_loss);
+        assert!(last.validation_loss.unwrap() < 1e-4);
+        assert_eq!(history.get_validation_loss_points().len(), 50);
+        assert_eq!(history.get_chart_data().len(), 2);
+    }
+
+    #[test]
+    fn stops_once_the_loss_stops_improving() {
+        let (inputs, expected) = data();
+        // nothing is learnt at a learning rate of 0.0 so the loss never improves on the first epoch
+        let mut model = build_model(0.0);
+        let mut trainer = Trainer::new(100, 5);
+        trainer.set_early_stopping(3);
+        let history = trainer.train(&mut model, &inputs, &expected).unwrap();
+
+        assert!(history.stopped_early);
+        assert_eq!(history.epochs.len(), 4);
+        assert!(history.get_validation_loss_points().is_empty());
+    }
+
+    #[test]
+    fn bad_settings_are_errors() {
+        let (inputs, expected) = data();
+        let mut model = build_model(0.01);
+        assert!(Trainer::new(1, 0)
+            .train(&mut model, &inputs, &expected)
+            .is_err());
+        assert!(Trainer::new(1, 4)
+            .set_validation_split(1.0)
+            .train(&mut model, &inputs, &expected)
+            .is_err());
+        assert!(Trainer::new(1, 4)
+            .train(&mut model, &inputs, &expected[1..])
+            .is_err());
+    }
+}
@@ -1,4 +1,5 @@
 pub mod agent;
 pub mod artificial_neural_network;
-mod policy;
+pub mod policy;
+pub mod replay_buffer;
 pub mod state;
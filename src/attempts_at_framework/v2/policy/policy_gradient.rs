@@ -1,12 +1,241 @@
 use crate::attempts_at_framework::v1::policy::{Policy, PolicyError};
+use crate::attempts_at_framework::v2::policy::soft_max;
+use rand::{Rng, RngCore};
+use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
 
-pub struct PolicyGradient {
-    preferences: HashMap<(String, String), f64>,
+/// # Policy Gradient
+///
+/// A softmax policy over action preferences, the probability of picking an action is
+/// `e^h(s, a) / sum(e^h(s, b))` where `h` is the preference. Preferences that have never been set
+/// are 0.0, so a new policy picks every action evenly.
+///
+/// Most methods take the actions available from the state, as only the environment knows what
+/// they are. The [`Policy`] impl can only pick from actions that have had a preference set.
+#[derive(Debug, Clone)]
+pub struct PolicyGradient<S = String, A = String> {
+    preferences: HashMap<S, Vec<(A, f64)>>,
 }
 
-impl Policy for PolicyGradient {
-    fn select_action_for_state(&self, state_id: &str) -> Result<String, Box<PolicyError>> {
-        todo!()
+impl<S: Clone + Eq + Hash, A: Clone + PartialEq> PolicyGradient<S, A> {
+    pub fn new() -> Self {
+        Self {
+            preferences: HashMap::new(),
+        }
+    }
+
+    pub fn get_preference<Q>(&self, state_id: &Q, action: &A) -> f64
+    where
+        S: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.preferences
+            .get(state_id)
+            .and_then(|preferences| preferences.iter().find(|(a, _)| a == action))
+            .map(|(_, preference)| *preference)
+            .unwrap_or(0.0)
+    }
+
+    pub fn set_preference(&mut self, state_id: S, action: A, preference: f64) {
+        let preferences = self.preferences.entry(state_id).or_default();
+        match preferences.iter_mut().find(|(a, _)| *a == action) {
+            Some((_, existing)) => *existing = preference,
+            None => preferences.push((action, preference)),
+        }
+    }
+
+    /// The chance of picking each of `actions`, in the same order.
+    pub fn get_probabilities<Q>(&self, state_id: &Q, actions: &[A]) -> Vec<f64>
+    where
+        S: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let preferences = actions
+            .iter()
+            .map(|action| self.get_preference(state_id, action))
+            .collect::<Vec<f64>>();
+        soft_max(&preferences)
+    }
+
+    /// Samples one of `actions` by its probability, returns `None` if there are no actions.
+    pub fn select_action<Q>(&self, state_id: &Q, actions: &[A], rng: &mut dyn RngCore) -> Option<A>
+    where
+        S: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let probabilities = self.get_probabilities(state_id, actions);
+        let sample: f64 = rng.random();
+
+        let mut cumulative_probability = 0.0;
+        for (action, probability) in actions.iter().zip(probabilities.iter()) {
+            cumulative_probability += probability;
+            if sample < cumulative_probability {
+                return Some(action.clone());
+            }
+        }
+        // rounding can leave the total just under 1.0
+        actions.last().cloned()
+    }
+
+    /// `ln π(a|s)`, the natural log of the chance of picking `action` out of `actions`.
+    pub fn log_prob<Q>(&self, state_id: &Q, actions: &[A], action: &A) -> f64
+    where
+        S: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let probabilities = self.get_probabilities(state_id, actions);
+        match actions.iter().position(|a| a == action) {
+            Some(index) => probabilities[index].ln(),
+            None => f64::NEG_INFINITY,
+        }
+    }
+
+    /// `∇ ln π(a|s)` with respect to the preference of each of `actions`, in the same order.
+    /// For softmax this is `1 - π(b|s)` for the picked action and `-π(b|s)` for every other
+    /// action `b`.
+    pub fn get_gradient<Q>(&self, state_id: &Q, actions: &[A], action: &A) -> Vec<f64>
+    where
+        S: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get_probabilities(state_id, actions)
+            .into_iter()
+            .zip(actions.iter())
+            .map(|(probability, a)| {
+                if a == action {
+                    1.0 - probability
+                } else {
+                    -probability
+                }
+            })
+            .collect()
+    }
+
+    /// Moves the preferences of `actions` by `step_size` times [`PolicyGradient::get_gradient`].
+    /// For REINFORCE the step size is the learning rate times the return, a positive step makes
+    /// `action` more likely and a negative one less likely.
+    pub fn update(&mut self, state_id: &S, actions: &[A], action: &A, step_size: f64) {
+        let gradient = self.get_gradient(state_id, actions, action);
+        actions.iter().zip(gradient).for_each(|(a, gradient)| {
+            let preference = self.get_preference(state_id, a) + step_size * gradient;
+            self.set_preference(state_id.clone(), a.clone(), preference);
+        });
+    }
+}
+
+impl<S: Clone + Eq + Hash, A: Clone + PartialEq> Default for PolicyGradient<S, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A, Q> Policy<Q, A> for PolicyGradient<S, A>
+where
+    S: Clone + Eq + Hash + Borrow<Q>,
+    A: Clone + PartialEq,
+    Q: Eq + Hash + Debug + ?Sized,
+{
+    fn select_action_for_state(&self, state_id: &Q) -> Result<A, Box<PolicyError>> {
+        self.select_action_for_state_with_rng(state_id, &mut rand::rng())
+    }
+
+    fn select_action_for_state_with_rng(
+        &self,
+        state_id: &Q,
+        rng: &mut dyn RngCore,
+    ) -> Result<A, Box<PolicyError>> {
+        let actions = match self.preferences.get(state_id) {
+            Some(preferences) => preferences
+                .iter()
+                .map(|(action, _)| action.clone())
+                .collect::<Vec<A>>(),
+            None => Vec::new(),
+        };
+
+        self.select_action(state_id, &actions, rng).ok_or_else(|| {
+            Box::new(PolicyError::new(format!(
+                "no preferences for state id {:?}",
+                state_id
+            )))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn actions() -> Vec<String> {
+        vec!["l".to_string(), "r".to_string()]
+    }
+
+    #[test]
+    fn new_policy_is_even() {
+        let policy: PolicyGradient = PolicyGradient::new();
+        assert_eq!(policy.get_probabilities("s", &actions()), vec![0.5, 0.5]);
+        assert_eq!(
+            policy.log_prob("s", &actions(), &"l".to_string()),
+            0.5_f64.ln()
+        );
+    }
+
+    #[test]
+    fn gradient_matches_the_change_in_log_prob() {
+        let mut policy: PolicyGradient = PolicyGradient::new();
+        policy.set_preference("s".to_string(), "l".to_string(), 0.3);
+        policy.set_preference("s".to_string(), "r".to_string(), -0.2);
+
+        let picked = "l".to_string();
+        let gradient = policy.get_gradient("s", &actions(), &picked);
+        let epsilon = 1e-6;
+
+        actions().iter().enumerate().for_each(|(index, action)| {
+            let mut nudged = policy.clone();
+            let preference = policy.get_preference("s", action);
+            nudged.set_preference("s".to_string(), action.clone(), preference + epsilon);
+
+            let numerical = (nudged.log_prob("s", &actions(), &picked)
+                - policy.log_prob("s", &actions(), &picked))
+                / epsilon;
+            assert!(
+                (numerical - gradient[index]).abs() < 1e-4,
+                "gradient for {} was {} but expected {}",
+                action,
+                gradient[index],
+                numerical
+            );
+        });
+    }
+
+    #[test]
+    fn updates_make_rewarded_actions_more_likely() {
+        let mut policy: PolicyGradient = PolicyGradient::new();
+        let state = "s".to_string();
+        (0..50).for_each(|_| policy.update(&state, &actions(), &"r".to_string(), 0.5));
+
+        let probabilities = policy.get_probabilities("s", &actions());
+        assert!(probabilities[1] > 0.95);
+        assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let rights = (0..200)
+            .filter(|_| {
+                policy
+                    .select_action_for_state_with_rng("s", &mut rng)
+                    .unwrap()
+                    == "r"
+            })
+            .count();
+        assert!(rights > 180);
+    }
+
+    #[test]
+    fn unknown_state_is_an_error() {
+        let policy: PolicyGradient = PolicyGradient::new();
+        assert!(policy.select_action_for_state("nowhere").is_err());
     }
 }
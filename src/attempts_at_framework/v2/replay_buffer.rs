@@ -0,0 +1,214 @@
+use rand::{Rng, RngCore};
+
+/// How a [`ReplayBuffer`] picks the experiences it hands back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    /// every stored experience is equally likely to be picked
+    Uniform,
+    /// Experiences are picked in proportion to `priority ^ alpha`, where the priority is the size
+    /// of the last error seen for it. `beta` controls how strongly the importance sampling
+    /// weights correct for this bias, 1.0 corrects fully.
+    Prioritized { alpha: f64, beta: f64 },
+}
+
+/// A single experience picked by [`ReplayBuffer::sample`]. `weight` is the importance sampling
+/// weight to scale its update by, it is always 1.0 for uniform sampling.
+pub struct Sample<'a, T> {
+    pub index: usize,
+    pub experience: &'a T,
+    pub weight: f64,
+}
+
+/// # Replay Buffer
+///
+/// Holds the most recent `capacity` experiences, once full the oldest experience is replaced
+/// by each new one. Lets agents learn from past experiences rather than only from the episode
+/// being generated.
+pub struct ReplayBuffer<T> {
+    capacity: usize,
+    experiences: Vec<T>,
+    priorities: Vec<f64>,
+    next_index: usize,
+    max_priority: f64,
+    sampling: Sampling,
+}
+
+impl<T> ReplayBuffer<T> {
+    pub fn new(capacity: usize, sampling: Sampling) -> Self {
+        if capacity == 0 {
+            panic!("a replay buffer needs room for at least one experience");
+        }
+        if let Sampling::Prioritized { alpha, beta } = sampling {
+            if alpha < 0.0 || !(0.0..=1.0).contains(&beta) {
+                panic!("alpha must be positive and beta must be between 0.0 and 1.0");
+            }
+        }
+
+        Self {
+            capacity,
+            experiences: Vec::with_capacity(capacity),
+            priorities: Vec::with_capacity(capacity),
+            next_index: 0,
+            max_priority: 1.0,
+            sampling,
+        }
+    }
+
+    pub fn uniform(capacity: usize) -> Self {
+        Self::new(capacity, Sampling::Uniform)
+    }
+
+    pub fn prioritized(capacity: usize, alpha: f64, beta: f64) -> Self {
+        Self::new(capacity, Sampling::Prioritized { alpha, beta })
+    }
+
+    pub fn len(&self) -> usize {
+        self.experiences.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.experiences.is_empty()
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get_sampling(&self) -> Sampling {
+        self.sampling
+    }
+
+    /// New experiences get the highest priority seen so far, so each is likely to be sampled
+    /// at least once.
+    pub fn push(&mut self, experience: T) {
+        if self.experiences.len() < self.capacity {
+            self.experiences.push(experience);
+            self.priorities.push(self.max_priority);
+        } else {
+            self.experiences[self.next_index] = experience;
+            self.priorities[self.next_index] = self.max_priority;
+        }
+        self.next_index = (self.next_index + 1) % self.capacity;
+    }
+
+    /// Picks `batch_size` experiences, with replacement. Returns nothing if the buffer is empty.
+    pub fn sample(&self, batch_size: usize) -> Vec<Sample<'_, T>> {
+        self.sample_with_rng(batch_size, &mut rand::rng())
+    }
+
+    /// Same as [`ReplayBuffer::sample`] but picks using `rng`, so a seeded rng picks the same
+    /// experiences every time.
+    pub fn sample_with_rng(&self, batch_size: usize, rng: &mut dyn RngCore) -> Vec<Sample<'_, T>> {
+        if self.experiences.is_empty() {
+            return Vec::new();
+        }
+
+        match self.sampling {
+            Sampling::Uniform => (0..batch_size)
+                .map(|_| {
+                    let index = rng.random_range(0..self.experiences.len());
+                    Sample {
+                        index,
+                        experience: &self.experiences[index],
+                        weight: 1.0,
+                    }
+                })
+                .collect(),
+            Sampling::Prioritized { alpha, beta } => {
+                let mut cumulative_priorities = Vec::with_capacity(self.priorities.len());
+                let mut total = 0.0;
+                self.priorities.iter().for_each(|priority| {
+                    total += priority.powf(alpha);
+                    cumulative_priorities.push(total);
+                });
+
+                let count = self.experiences.len() as f64;
+                let weight_of = |priority: f64| (count * priority.powf(alpha) / total).powf(-beta);
+
+                // the smallest priority gives the largest weight, weights are scaled by it so
+                // they only ever shrink updates
+                let min_priority = self
+                    .priorities
+                    .iter()
+                    .copied()
+                    .fold(f64::INFINITY, f64::min);
+                let max_weight = weight_of(min_priority);
+
+                (0..batch_size)
+                    .map(|_| {
+                        let target = rng.random_range(0.0..total);
+                        let index = cumulative_priorities
+                            .partition_point(|cumulative| *cumulative <= target)
+                            .min(self.experiences.len() - 1);
+                        Sample {
+                            index,
+                            experience: &self.experiences[index],
+                            weight: weight_of(self.priorities[index]) / max_weight,
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Sets the priority of a sampled experience from the error seen when learning from it, does
+    /// nothing for uniform sampling.
+    pub fn update_priority(&mut self, index: usize, error: f64) {
+        if self.sampling == Sampling::Uniform {
+            return;
+        }
+        // a small constant keeps every experience possible to sample
+        let priority = error.abs() + 1e-6;
+        self.priorities[index] = priority;
+        self.max_priority = self.max_priority.max(priority);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oldest_experiences_are_replaced() {
+        let mut buffer = ReplayBuffer::uniform(3);
+        (0..5).for_each(|i| buffer.push(i));
+
+        assert_eq!(buffer.len(), 3);
+        let mut seen = buffer
+            .sample(200)
+            .iter()
+            .map(|s| *s.experience)
+            .collect::<Vec<i32>>();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn empty_buffer_samples_nothing() {
+        let buffer: ReplayBuffer<i32> = ReplayBuffer::prioritized(3, 0.6, 0.4);
+        assert!(buffer.sample(10).is_empty());
+    }
+
+    #[test]
+    fn high_priority_experiences_are_sampled_more() {
+        let mut buffer = ReplayBuffer::prioritized(10, 1.0, 1.0);
+        (0..10).for_each(|i| buffer.push(i));
+        (0..10).for_each(|i| buffer.update_priority(i, if i == 7 { 100.0 } else { 1.0 }));
+
+        let samples = buffer.sample(1000);
+        let sevens = samples.iter().filter(|s| *s.experience == 7).count();
+        assert!(
+            sevens > 800,
+            "only sampled the high priority experience {} times",
+            sevens
+        );
+
+        samples.iter().for_each(|sample| {
+            assert!(sample.weight > 0.0 && sample.weight <= 1.0);
+            if *sample.experience == 7 {
+                assert!(sample.weight < 0.02);
+            }
+        });
+    }
+}
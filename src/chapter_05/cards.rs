@@ -1,4 +1,7 @@
 use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::cell::RefCell;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Value {
@@ -41,11 +44,22 @@ pub trait CardProvider {
     fn get_random_card(&self) -> Result<Value, ()>;
 }
 
-pub struct RandomCardProvider;
+pub struct RandomCardProvider {
+    rng: RefCell<StdRng>,
+}
 
 impl RandomCardProvider {
     pub fn new() -> Self {
-        RandomCardProvider
+        Self {
+            rng: RefCell::new(StdRng::from_os_rng()),
+        }
+    }
+
+    /// a provider that deals the same cards, in the same order, for the same seed
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
     }
 }
 
@@ -67,8 +81,7 @@ impl CardProvider for RandomCardProvider {
             Value::King,
         ];
 
-        let mut rng = rand::rng();
-        let item = values.choose(&mut rng).unwrap().clone();
+        let item = values.choose(&mut *self.rng.borrow_mut()).unwrap().clone();
         Ok(item)
     }
 }
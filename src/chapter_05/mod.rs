@@ -21,7 +21,8 @@ mod tests {
     use egui::Key::S;
     use plotters::prelude::ShapeStyle;
     use plotters::style::{BLUE, RED};
-    use rand::Rng;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
     use std::collections::HashMap;
     use std::hash::Hash;
     use std::path::PathBuf;
@@ -123,7 +124,8 @@ mod tests {
     fn test_monte_carlo_exploring_starts_for_blackjack() {
         let iteration_count = 100000000;
         let discount_rate = 1.0;
-        let card_provider: RandomCardProvider = RandomCardProvider::new();
+        let card_provider: RandomCardProvider = RandomCardProvider::with_seed(1);
+        let mut rng = StdRng::seed_from_u64(1);
 
         let player_count_range = 12_u8..=21;
         let dealer_showing_range = 2_u8..=11;
@@ -133,9 +135,9 @@ mod tests {
         let mut values: HashMap<String, (i32, f64)> = HashMap::new();
 
         (0..iteration_count).for_each(|_| {
-            let starting_player_count = rand::rng().random_range(player_count_range.clone());
-            let starting_dealer_showing = rand::rng().random_range(dealer_showing_range.clone());
-            let starting_player_usable_aces = rand::rng().random_bool(0.5);
+            let starting_player_count = rng.random_range(player_count_range.clone());
+            let starting_dealer_showing = rng.random_range(dealer_showing_range.clone());
+            let starting_player_usable_aces = rng.random_bool(0.5);
 
             let mut state = BlackJackState::new(
                 starting_player_count,
@@ -148,7 +150,7 @@ mod tests {
                 &starting_dealer_showing,
                 &starting_player_usable_aces,
             );
-            let is_starting_action_hit = rand::rng().random_bool(0.5);
+            let is_starting_action_hit = rng.random_bool(0.5);
 
             play_episode(&policy, &mut state, is_starting_action_hit);
             let reward = state.check_for_win();
@@ -255,18 +257,19 @@ mod tests {
     fn test_monte_carlo_kinda_exploring_starts_but_with_e_soft_policy() {
         let iteration_count = 100000000;
         let e_soft_rate = 0.3;
-        let card_provider: RandomCardProvider = RandomCardProvider::new();
+        let card_provider: RandomCardProvider = RandomCardProvider::with_seed(2);
+        let mut rng = StdRng::seed_from_u64(2);
 
         let player_count_range = 12_u8..=14;
         let dealer_showing_range = 2_u8..=11;
 
-        let mut policy = StochasticPolicy::new();
+        let mut policy = StochasticPolicy::with_seed(2);
         let mut values: HashMap<String, (i32, f64)> = HashMap::new();
 
         (0..iteration_count).for_each(|_| {
-            let starting_player_count = rand::rng().random_range(player_count_range.clone());
-            let starting_dealer_showing = rand::rng().random_range(dealer_showing_range.clone());
-            let starting_player_usable_aces = rand::rng().random_bool(0.5);
+            let starting_player_count = rng.random_range(player_count_range.clone());
+            let starting_dealer_showing = rng.random_range(dealer_showing_range.clone());
+            let starting_player_usable_aces = rng.random_bool(0.5);
 
             let mut state = BlackJackState::new(
                 starting_player_count,
@@ -591,12 +594,13 @@ mod tests {
         let player_count_starting_range = 11..=21;
         let dealer_showing_starting_range = 2..=11;
 
-        let card_provider: RandomCardProvider = RandomCardProvider::new();
+        let card_provider: RandomCardProvider = RandomCardProvider::with_seed(3);
+        let mut rng = StdRng::seed_from_u64(3);
         let mut state_action_values: HashMap<String, f64> = HashMap::new();
         let mut state_action_weights: HashMap<String, f64> = HashMap::new();
         let mut target_policy = DeterministicPolicy::new();
 
-        let mut behavior_policy = StochasticPolicy::new();
+        let mut behavior_policy = StochasticPolicy::with_seed(3);
         (2..=11).for_each(|dealer_showing| {
             vec![true, false].iter().for_each(|usable_ace| {
                 (2..=32).for_each(|player_count| {
@@ -616,11 +620,9 @@ mod tests {
         });
 
         (0..number_of_episodes).for_each(|i| {
-            let starting_player_count =
-                rand::rng().random_range(player_count_starting_range.clone());
-            let starting_dealer_showing =
-                rand::rng().random_range(dealer_showing_starting_range.clone());
-            let starting_player_usable_aces = rand::rng().random_bool(0.5);
+            let starting_player_count = rng.random_range(player_count_starting_range.clone());
+            let starting_dealer_showing = rng.random_range(dealer_showing_starting_range.clone());
+            let starting_player_usable_aces = rng.random_bool(0.5);
 
             let mut blackjack_state = BlackJackState::new(
                 starting_player_count,
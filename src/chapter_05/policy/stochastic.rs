@@ -1,5 +1,7 @@
 use crate::chapter_05::policy::Policy;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 pub struct StochasticPolicy {
@@ -7,12 +9,22 @@ pub struct StochasticPolicy {
     ///  - f64: odds of taking action
     ///  - String: action id as a string
     state_action_probabilities: HashMap<String, Vec<(f64, String)>>,
+    rng: RefCell<StdRng>,
 }
 
 impl StochasticPolicy {
     pub fn new() -> Self {
         StochasticPolicy {
             state_action_probabilities: HashMap::new(),
+            rng: RefCell::new(StdRng::from_os_rng()),
+        }
+    }
+
+    /// a policy that picks the same actions, in the same order, for the same seed
+    pub fn with_seed(seed: u64) -> Self {
+        StochasticPolicy {
+            state_action_probabilities: HashMap::new(),
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
         }
     }
 
@@ -75,7 +87,7 @@ impl StochasticPolicy {
 
 impl Policy for StochasticPolicy {
     fn pick_action_for_state(&self, state_id: &str) -> Result<&str, String> {
-        let mut random_number = rand::rng().random_range(0.0..1.0);
+        let mut random_number = self.rng.borrow_mut().random_range(0.0..1.0);
 
         if let Some(state_action_probabilities) = self.state_action_probabilities.get(state_id) {
             for (probability, action) in state_action_probabilities {
@@ -137,6 +149,33 @@ mod tests {
         println!("action_id: {}", action_id);
     }
 
+    #[test]
+    fn test_seeded_stochastic_policies_pick_the_same_actions() {
+        let picks = |seed: u64| {
+            let mut stochastic_policy = StochasticPolicy::with_seed(seed);
+            stochastic_policy
+                .set_state_action_probabilities(
+                    "state_1",
+                    vec![
+                        (0.5, String::from("action_1")),
+                        (0.5, String::from("action_2")),
+                    ],
+                )
+                .expect("set_state_action_probabilities failed");
+            (0..20)
+                .map(|_| {
+                    stochastic_policy
+                        .pick_action_for_state("state_1")
+                        .unwrap()
+                        .to_string()
+                })
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(picks(3), picks(3));
+        assert_ne!(picks(3), picks(4));
+    }
+
     #[test]
     fn test_get_action_from_stochastic_policy_with_multiple_actions_and_sum_not_1() {
         let mut stochastic_policy = StochasticPolicy::new();
@@ -1,6 +1,7 @@
 use crate::chapter_05::policy::{DeterministicPolicy, Policy};
 use crate::chapter_05::race_track::state::State;
 use rand::prelude::*;
+use rand::rngs::StdRng;
 use rand::Rng;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -12,6 +13,7 @@ pub struct MonteCarloOffPolicyLearner<S: State> {
     states: HashMap<String, Rc<S>>,
     discount_rate: f64,
     target_policy: DeterministicPolicy,
+    rng: StdRng,
 }
 
 impl<S: State> MonteCarloOffPolicyLearner<S> {
@@ -32,9 +34,14 @@ impl<S: State> MonteCarloOffPolicyLearner<S> {
             starting_states,
             discount_rate,
             states,
+            rng: StdRng::from_os_rng(),
         }
     }
 
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     pub fn get_target_policy(&self) -> &DeterministicPolicy {
         &self.target_policy
     }
@@ -99,13 +106,12 @@ impl<S: State> MonteCarloOffPolicyLearner<S> {
     }
 
     fn generate_episode(&mut self) -> Vec<(String, String, f64)> {
-        let mut rng = rand::rng();
-        let mut current_state = Rc::clone(self.starting_states.choose(&mut rng).unwrap());
+        let mut current_state = Rc::clone(self.starting_states.choose(&mut self.rng).unwrap());
         let mut history: Vec<(String, String, f64)> = Vec::new();
         while !current_state.is_terminal() {
             let current_state_id = current_state.get_id();
             let actions = current_state.get_actions();
-            let action = actions.choose(&mut rng).unwrap();
+            let action = actions.choose(&mut self.rng).unwrap();
             let (reward, new_state) = current_state.take_action(action);
             history.push((current_state_id.clone(), action.clone(), reward));
             let next_state = Rc::new(new_state);
@@ -120,4 +126,46 @@ impl<S: State> MonteCarloOffPolicyLearner<S> {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[derive(Clone)]
+    struct CorridorState {
+        position: usize,
+    }
+
+    impl State for CorridorState {
+        fn get_id(&self) -> String {
+            self.position.to_string()
+        }
+
+        fn get_actions(&self) -> Vec<String> {
+            vec!["left".to_string(), "right".to_string()]
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.position >= 4
+        }
+
+        fn take_action(&self, action: &str) -> (f64, Self) {
+            let position = match action {
+                "left" => self.position.saturating_sub(1),
+                _ => self.position + 1,
+            };
+            (-1.0, Self { position })
+        }
+    }
+
+    #[test]
+    fn seeded_learners_generate_the_same_episodes() {
+        let generate = |seed: u64| {
+            let mut learner =
+                MonteCarloOffPolicyLearner::new(vec![CorridorState { position: 0 }], 1.0);
+            learner.set_seed(seed);
+            (0..5)
+                .map(|_| learner.generate_episode())
+                .collect::<Vec<Vec<(String, String, f64)>>>()
+        };
+
+        assert_eq!(generate(3), generate(3));
+        assert_ne!(generate(3), generate(4));
+    }
 }
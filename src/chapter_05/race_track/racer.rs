@@ -44,6 +44,34 @@ impl<'a> Racer<'_> {
     pub fn decrease_vertical_velocity(&mut self) {
         self.velocity.1 = (self.max_velocity * -1).max(self.velocity.1 - 1);
     }
+
+    /// the track's planes, then where the racer is and its horizontal and vertical velocity
+    pub const PLANE_COUNT: usize = RaceTrack::PLANE_COUNT + 3;
+
+    /// The track's [`RaceTrack::get_planes`] followed by a plane marking the racer's position and
+    /// two planes filled with its horizontal and vertical velocity, over the max velocity, so a
+    /// convolution sees them wherever it looks.
+    pub fn get_planes(&self) -> Vec<f64> {
+        let width = self.track.get_width();
+        let cells = self.track.get_height() * width;
+        let mut position_plane = vec![0.0; cells];
+        let (x, y) = self.position;
+        if x >= 0 && (x as usize) < width && y >= 0 && (y as usize) < self.track.get_height() {
+            position_plane[y as usize * width + x as usize] = 1.0;
+        }
+
+        let mut planes = self.track.get_planes();
+        planes.extend(position_plane);
+        planes.extend(vec![
+            self.velocity.0 as f64 / self.max_velocity as f64;
+            cells
+        ]);
+        planes.extend(vec![
+            self.velocity.1 as f64 / self.max_velocity as f64;
+            cells
+        ]);
+        planes
+    }
 }
 
 impl State for Racer<'_> {
@@ -134,3 +162,48 @@ impl State for Racer<'_> {
         (reward, new_state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        Conv2DShape, LayerBuilder, LayerType, ModelBuilder,
+    };
+    use crate::chapter_05::race_track::track_parser::parse_track_from_string;
+
+    #[test]
+    fn planes_feed_a_convolution() {
+        let track = parse_track_from_string("XXFF\nX  X\nSS X").unwrap();
+        let mut racer = Racer::new((1, 0), &track);
+        racer.increase_vertical_velocity();
+        let planes = racer.get_planes();
+        let cells = track.get_height() * track.get_width();
+        assert_eq!(planes.len(), Racer::PLANE_COUNT * cells);
+        // the bottom row of the string is the first row of the track
+        assert_eq!(
+            &planes[..cells],
+            &[1.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0]
+        );
+        assert_eq!(planes[3 * cells + 1], 1.0);
+        assert_eq!(planes[5 * cells], 0.2);
+
+        let shape = Conv2DShape::new(
+            Racer::PLANE_COUNT,
+            track.get_height(),
+            track.get_width(),
+            2,
+            2,
+        );
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(planes.len())
+            .set_seed(2)
+            .add_layer(LayerBuilder::new_conv_2d(LayerType::RELU, shape))
+            .add_layer(LayerBuilder::new_flatten())
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        let model = builder.build().unwrap();
+        assert_eq!(model.predict(planes).len(), 1);
+    }
+}
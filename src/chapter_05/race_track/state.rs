@@ -1,6 +1,4 @@
-pub trait State {
-    fn get_id(&self) -> String;
-    fn get_actions(&self) -> Vec<String>;
-    fn is_terminal(&self) -> bool;
-    fn take_action(&self, action: &str) -> (f64, Self);
-}
+/// The racetrack used to carry its own copy of the v1 `State` trait, it now shares the framework
+/// one so a `Racer` is also an
+/// [`Environment`](crate::attempts_at_framework::environment::Environment).
+pub use crate::attempts_at_framework::v1::state::State;
@@ -40,6 +40,45 @@ impl RaceTrack {
         &self.start_positions
     }
 
+    pub fn get_height(&self) -> usize {
+        self.track.len()
+    }
+
+    /// the longest row, shorter rows are out of bounds past their end
+    pub fn get_width(&self) -> usize {
+        self.track.iter().map(|row| row.len()).max().unwrap_or(0)
+    }
+
+    /// where the car can drive, the start line and the finish line
+    pub const PLANE_COUNT: usize = 3;
+
+    /// The track as [`RaceTrack::PLANE_COUNT`] planes of [`RaceTrack::get_height`] by
+    /// [`RaceTrack::get_width`], one after the other and each row by row, for a model starting
+    /// with a `Conv2DShape` convolution.
+    pub fn get_planes(&self) -> Vec<f64> {
+        let width = self.get_width();
+        let cells = self.get_height() * width;
+        let mut planes = vec![0.0; Self::PLANE_COUNT * cells];
+        self.track.iter().enumerate().for_each(|(y, row)| {
+            row.iter().enumerate().for_each(|(x, element)| {
+                let index = y * width + x;
+                match element {
+                    TrackElement::Track => planes[index] = 1.0,
+                    TrackElement::Start => {
+                        planes[index] = 1.0;
+                        planes[cells + index] = 1.0;
+                    }
+                    TrackElement::Finish => {
+                        planes[index] = 1.0;
+                        planes[2 * cells + index] = 1.0;
+                    }
+                    TrackElement::OutOfBounds => {}
+                }
+            })
+        });
+        planes
+    }
+
     pub fn check_for_intersections(
         &self,
         start_position: (i32, i32),
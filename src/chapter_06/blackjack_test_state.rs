@@ -1,6 +1,7 @@
 use crate::chapter_05::cards::{CardProvider, RandomCardProvider, Value};
 use crate::chapter_05::race_track::state::State;
 
+#[derive(Clone)]
 pub struct BlackJackTestState {
     player_count: u8,
     dealer_showing: u8,
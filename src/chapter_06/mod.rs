@@ -74,6 +74,7 @@ mod tests {
         builder.create_chart().unwrap();
     }
 
+    #[derive(Clone)]
     struct MrpState {
         id: u8,
         is_terminal: bool,
@@ -40,6 +40,16 @@ impl WindyGridworld {
         Self { height, width }
     }
 
+    /// rows go from 0 up to and including the height
+    pub fn get_row_count(&self) -> usize {
+        self.height as usize + 1
+    }
+
+    /// columns go from 0 up to and including the width
+    pub fn get_col_count(&self) -> usize {
+        self.width as usize + 1
+    }
+
     pub fn make_state_for_row_col(&self, row: u8, col: u8) -> WindyGridworldState {
         let mut wind_map_by_col: HashMap<u8, (u8, Direction)> = HashMap::new();
         wind_map_by_col.insert(3, (1, Direction::North));
@@ -82,6 +92,45 @@ impl<'a> WindyGridworldState<'a> {
             wind,
         }
     }
+
+    /// where the agent is, where the goal is and how far the wind pushes north in each cell
+    pub const PLANE_COUNT: usize = 3;
+
+    /// The world as [`WindyGridworldState::PLANE_COUNT`] planes of
+    /// [`WindyGridworld::get_row_count`] by [`WindyGridworld::get_col_count`], one after the
+    /// other and each row by row, for a model starting with a `Conv2DShape` convolution.
+    pub fn get_planes(&self) -> Vec<f64> {
+        let rows = self.world.get_row_count();
+        let cols = self.world.get_col_count();
+        let mut agent_plane = vec![0.0; rows * cols];
+        let mut goal_plane = vec![0.0; rows * cols];
+        let mut wind_plane = vec![0.0; rows * cols];
+
+        agent_plane[self.row as usize * cols + self.col as usize] = 1.0;
+        for row in 0..rows {
+            for col in 0..cols {
+                let cell = self.world.make_state_for_row_col(row as u8, col as u8);
+                let index = row * cols + col;
+                if cell.is_terminal {
+                    goal_plane[index] = 1.0;
+                }
+                wind_plane[index] = match cell.wind {
+                    Some((strength, Direction::North))
+                    | Some((strength, Direction::NorthEast))
+                    | Some((strength, Direction::NorthWest)) => strength as f64,
+                    Some((strength, Direction::South))
+                    | Some((strength, Direction::SouthEast))
+                    | Some((strength, Direction::SouthWest)) => -(strength as f64),
+                    _ => 0.0,
+                };
+            }
+        }
+
+        let mut planes = agent_plane;
+        planes.extend(goal_plane);
+        planes.extend(wind_plane);
+        planes
+    }
 }
 
 impl State for WindyGridworldState<'_> {
@@ -184,6 +233,41 @@ mod tests {
     use super::*;
     use crate::attempts_at_framework::v1::agent::{QLearning, SarsaZero};
     use crate::attempts_at_framework::v1::policy::Policy;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        Conv2DShape, LayerBuilder, LayerType, ModelBuilder,
+    };
+
+    #[test]
+    fn test_windy_gridworld_planes_feed_a_convolution() {
+        let world = WindyGridworld::new(6, 9);
+        let state = world.make_state_for_row_col(3, 0);
+        let planes = state.get_planes();
+        let cells = world.get_row_count() * world.get_col_count();
+        assert_eq!(planes.len(), WindyGridworldState::PLANE_COUNT * cells);
+        // the agent at row 3 column 0, the goal at row 3 column 7
+        assert_eq!(planes[3 * 10], 1.0);
+        assert_eq!(planes[cells + 3 * 10 + 7], 1.0);
+        assert_eq!(planes[2 * cells + 6], 2.0);
+
+        let shape = Conv2DShape::new(
+            WindyGridworldState::PLANE_COUNT,
+            world.get_row_count(),
+            world.get_col_count(),
+            2,
+            3,
+        );
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(planes.len())
+            .set_seed(4)
+            .add_layer(LayerBuilder::new_conv_2d(LayerType::TANH, shape))
+            .add_layer(LayerBuilder::new_flatten())
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        let model = builder.build().unwrap();
+        assert_eq!(model.predict(planes).len(), 1);
+    }
 
     #[test]
     fn test_windy_gridworld_sarsa() {
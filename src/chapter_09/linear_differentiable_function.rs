@@ -220,6 +220,7 @@ pub fn n_step_semi_gradient_td_my_refactor<S: State, P: Policy>(
 mod tests {
     use super::*;
     use crate::attempts_at_framework::v1::policy::RandomPolicy;
+    use crate::attempts_at_framework::v1::state::State as _;
     use crate::service::x_state_walk_environment::{WalkState, WalkStateFactory};
     use crate::service::{LineChartBuilder, LineChartData};
     use plotters::prelude::{ShapeStyle, RED};
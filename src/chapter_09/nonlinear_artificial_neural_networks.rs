@@ -104,6 +104,7 @@ fn select_action<S: State, P: Policy>(starting_state: &S, policy: &P) -> String
 #[cfg(test)]
 mod tests {
     use crate::attempts_at_framework::v1::policy::RandomPolicy;
+    use crate::attempts_at_framework::v1::state::State as _;
     use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
         LayerBuilder, LayerType,
     };
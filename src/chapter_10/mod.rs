@@ -1,2 +1,2 @@
-mod mountain_car;
+pub mod mountain_car;
 mod semi_gradient_sarsa_mountian_car;
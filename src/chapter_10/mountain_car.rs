@@ -1,5 +1,8 @@
-use crate::attempts_at_framework::v2::state::State;
+use crate::attempts_at_framework::environment::StateActionFeatureVector;
+use crate::attempts_at_framework::v1::state::State;
+use crate::attempts_at_framework::v2;
 use std::fmt::Display;
+use std::str::FromStr;
 
 pub const VELOCITY_LOWER_BOUND: f64 = -0.07;
 pub const VELOCITY_UPPER_BOUND: f64 = 0.07;
@@ -62,6 +65,19 @@ impl Display for CarAction {
     }
 }
 
+impl FromStr for CarAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forward" => Ok(CarAction::Forward),
+            "neutral" => Ok(CarAction::Neutral),
+            "reverse" => Ok(CarAction::Reverse),
+            _ => Err(format!("invalid action {}", s)),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct MountainCar {
     x_position: f64,
@@ -129,7 +145,9 @@ impl State for MountainCar {
         let reward = if terminal { 0.0 } else { -1.0 };
         (reward, new_state)
     }
+}
 
+impl v2::state::State for MountainCar {
     fn get_values(&self) -> Vec<f64> {
         let mut velocity_vec = vec![0.0; TILES];
         let mut position_vec = vec![0.0; TILES];
@@ -142,6 +160,13 @@ impl State for MountainCar {
     }
 }
 
+impl StateActionFeatureVector for MountainCar {
+    fn get_state_action_values(&self, action: &String) -> Vec<f64> {
+        let action = action.parse::<CarAction>().unwrap();
+        feature_vector(self.x_position, self.velocity, action)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -151,6 +176,8 @@ mod tests {
     use std::path::PathBuf;
     use egui::Key::L;
     use rand::prelude::IndexedRandom;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
     use crate::attempts_at_framework::v2::agent::n_step_sarsa::NStepSarsa;
     use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
     use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{LayerBuilder, ModelBuilder};
@@ -235,9 +262,12 @@ mod tests {
         // model_builder.add_layer(LayerBuilder::new(LINEAR, 1));
         // model_builder.add_layer(LayerBuilder::new(RELU, 5));
         model_builder.add_layer(LayerBuilder::new(LINEAR, 1));
+        model_builder.set_seed(7);
         let model = model_builder.build().unwrap();
 
         let mut agent = NStepSarsa::new(2, discount_factor, learning_rate, exploration_rate, model);
+        agent.set_seed(7);
+        let mut rng = StdRng::seed_from_u64(7);
 
         let starting_x_positions: Vec<f64> = (0..100)
             .map(|i| {
@@ -247,7 +277,7 @@ mod tests {
             .collect();
 
         (0..episodes).for_each(|episode| {
-            let starting_x_position = starting_x_positions.choose(&mut rand::rng()).unwrap();
+            let starting_x_position = starting_x_positions.choose(&mut rng).unwrap();
             let starting_state = MountainCar::new(*starting_x_position, 0.0);
             agent.learn_from_episode(starting_state);
         });
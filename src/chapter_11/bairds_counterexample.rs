@@ -1,5 +1,7 @@
 use crate::attempts_at_framework::v1::policy::{DeterministicPolicy, StochasticPolicy};
-use crate::attempts_at_framework::v2::state::State;
+use crate::attempts_at_framework::v1::state::State;
+use crate::attempts_at_framework::v2;
+use crate::attempts_at_framework::v2::state::State as _;
 use rand::prelude::IteratorRandom;
 
 #[derive(Clone)]
@@ -39,7 +41,9 @@ impl State for TestState {
             (0.0, Self::new(next_id))
         }
     }
+}
 
+impl v2::state::State for TestState {
     fn get_values(&self) -> Vec<f64> {
         let mut values = vec![0.0; 8];
         if self.id == 7 {
@@ -77,6 +77,7 @@ impl<S: State> TdTraceDecay<S> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::attempts_at_framework::v1::state::State as _;
     use crate::service::x_state_walk_environment::{WalkState, WalkStateFactory};
 
     #[test]
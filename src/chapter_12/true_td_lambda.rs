@@ -107,6 +107,7 @@ impl<P: Policy, S: State> TrueTdLambda<P, S> {
 mod tests {
     use super::*;
     use crate::attempts_at_framework::v1::policy::RandomPolicy;
+    use crate::attempts_at_framework::v1::state::State as _;
     use crate::service::x_state_walk_environment::{WalkState, WalkStateFactory};
     fn generate_simple_value_function(total_states: usize) -> impl Fn(WalkState) -> Vec<f64> {
         move |state| {
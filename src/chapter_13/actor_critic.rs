@@ -1 +1,332 @@
+use crate::attempts_at_framework::environment::{FeatureVector, StateActionFeatureVector};
+use crate::attempts_at_framework::v2::policy::soft_max;
+use crate::chapter_13::value_function::ValueFunction;
+use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
+/// # Softmax Actor
+///
+/// A softmax policy over linear action preferences, `h(s, a) = θ · x(s, a)` where `x(s, a)` comes
+/// from [`StateActionFeatureVector::get_state_action_values`].
+pub struct SoftmaxActor {
+    weights: Vec<f64>,
+}
+
+impl SoftmaxActor {
+    pub fn new(value_count: usize) -> Self {
+        Self {
+            weights: vec![0.0; value_count],
+        }
+    }
+
+    pub fn get_weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    /// The chance of picking each of the state's actions, in the order of `get_actions`.
+    pub fn get_probabilities<E: StateActionFeatureVector>(&self, state: &E) -> Vec<f64> {
+        let preferences = state
+            .get_actions()
+            .iter()
+            .map(|action| {
+                state
+                    .get_state_action_values(action)
+                    .iter()
+                    .zip(self.weights.iter())
+                    .map(|(x, w)| x * w)
+                    .sum()
+            })
+            .collect::<Vec<f64>>();
+        soft_max(&preferences)
+    }
+
+    pub fn select_action<E: StateActionFeatureVector>(
+        &self,
+        state: &E,
+        rng: &mut StdRng,
+    ) -> E::Action {
+        let actions = state.get_actions();
+        let probabilities = self.get_probabilities(state);
+        let indexes = (0..actions.len()).collect::<Vec<usize>>();
+        let index = indexes
+            .choose_weighted(rng, |index| probabilities[*index])
+            .unwrap();
+        actions[*index].clone()
+    }
+
+    /// `∇ ln π(a|s)`, which for linear preferences is `x(s, a) - Σ π(b|s) x(s, b)`.
+    pub fn get_log_gradient<E: StateActionFeatureVector>(
+        &self,
+        state: &E,
+        action: &E::Action,
+    ) -> Vec<f64> {
+        let mut gradient = state.get_state_action_values(action);
+        state
+            .get_actions()
+            .iter()
+            .zip(self.get_probabilities(state))
+            .for_each(|(b, probability)| {
+                gradient
+                    .iter_mut()
+                    .zip(state.get_state_action_values(b))
+                    .for_each(|(g, x)| *g -= probability * x);
+            });
+        gradient
+    }
+
+    fn add_to_weights(&mut self, step: &[f64]) {
+        self.weights.iter_mut().zip(step).for_each(|(w, s)| *w += s);
+    }
+}
+
+/// # Actor Critic
+///
+/// Actor critic with eligibility traces, as in section 13.5 of the book. The actor picks actions
+/// and the critic, any [`ValueFunction`] of the state's values, judges them. After every step
+/// both are moved along their traces by the TD error `δ = R + γv(S') - v(S)`.
+///
+/// The trace decay rates default to 0.0, which is the one step actor critic. Use
+/// [`ActorCritic::set_trace_decay`] to use eligibility traces.
+pub struct ActorCritic<C: ValueFunction> {
+    actor: SoftmaxActor,
+    critic: C,
+    actor_step_size: f64,
+    critic_step_size: f64,
+    discount_rate: f64,
+    actor_trace_decay: f64,
+    critic_trace_decay: f64,
+    episodes_learned_for: usize,
+    rng: StdRng,
+}
+
+impl<C: ValueFunction> ActorCritic<C> {
+    /// `action_value_count` is the number of values each state action pair is described by.
+    pub fn new(
+        critic: C,
+        action_value_count: usize,
+        actor_step_size: f64,
+        critic_step_size: f64,
+        discount_rate: f64,
+    ) -> Self {
+        if !(0.0..=1.0).contains(&discount_rate) {
+            panic!("Discount rate must be between 0.0 and 1.0")
+        }
+
+        Self {
+            actor: SoftmaxActor::new(action_value_count),
+            critic,
+            actor_step_size,
+            critic_step_size,
+            discount_rate,
+            actor_trace_decay: 0.0,
+            critic_trace_decay: 0.0,
+            episodes_learned_for: 0,
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// λ for the actor's and the critic's traces, 0.0 for both is the one step actor critic.
+    pub fn set_trace_decay(&mut self, actor_trace_decay: f64, critic_trace_decay: f64) {
+        if !(0.0..=1.0).contains(&actor_trace_decay) || !(0.0..=1.0).contains(&critic_trace_decay) {
+            panic!("Trace decay rates must be between 0.0 and 1.0")
+        }
+        self.actor_trace_decay = actor_trace_decay;
+        self.critic_trace_decay = critic_trace_decay;
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn get_actor(&self) -> &SoftmaxActor {
+        &self.actor
+    }
+
+    pub fn get_critic(&self) -> &C {
+        &self.critic
+    }
+
+    pub fn get_episodes_learned_for(&self) -> usize {
+        self.episodes_learned_for
+    }
+
+    pub fn select_action_for_state<E: StateActionFeatureVector>(&mut self, state: &E) -> E::Action {
+        self.actor.select_action(state, &mut self.rng)
+    }
+
+    /// Returns the total reward of each episode.
+    pub fn learn_for_episode_count<E: FeatureVector + StateActionFeatureVector>(
+        &mut self,
+        episode_count: usize,
+        starting_states: Vec<E>,
+    ) -> Vec<f64> {
+        (0..episode_count)
+            .map(|_| {
+                let starting_state = starting_states.choose(&mut self.rng).unwrap().clone();
+                self.learn_from_episode(starting_state)
+            })
+            .collect()
+    }
+
+    /// Returns the total reward of the episode.
+    pub fn learn_from_episode<E: FeatureVector + StateActionFeatureVector>(
+        &mut self,
+        starting_state: E,
+    ) -> f64 {
+        let mut state = starting_state;
+        let mut actor_trace = vec![0.0; self.actor.weights.len()];
+        let mut critic_trace: Vec<f64> = Vec::new();
+        let mut discount = 1.0;
+        let mut total_reward = 0.0;
+
+        while !state.is_terminal() {
+            let action = self.select_action_for_state(&state);
+            let (reward, next_state) = state.take_action(&action);
+            total_reward += reward;
+
+            let values = state.get_values();
+            let next_value = if next_state.is_terminal() {
+                0.0
+            } else {
+                self.critic.get_value(&next_state.get_values())
+            };
+            let td_error =
+                reward + self.discount_rate * next_value - self.critic.get_value(&values);
+
+            let critic_gradient = self.critic.get_gradient(&values);
+            if critic_trace.is_empty() {
+                critic_trace = vec![0.0; critic_gradient.len()];
+            }
+            critic_trace
+                .iter_mut()
+                .zip(critic_gradient)
+                .for_each(|(z, g)| *z = self.discount_rate * self.critic_trace_decay * *z + g);
+            actor_trace
+                .iter_mut()
+                .zip(self.actor.get_log_gradient(&state, &action))
+                .for_each(|(z, g)| {
+                    *z = self.discount_rate * self.actor_trace_decay * *z + discount * g
+                });
+
+            let critic_step = critic_trace
+                .iter()
+                .map(|z| self.critic_step_size * td_error * z)
+                .collect::<Vec<f64>>();
+            self.critic.add_to_parameters(&critic_step);
+            let actor_step = actor_trace
+                .iter()
+                .map(|z| self.actor_step_size * td_error * z)
+                .collect::<Vec<f64>>();
+            self.actor.add_to_weights(&actor_step);
+
+            discount *= self.discount_rate;
+            state = next_state;
+        }
+
+        self.episodes_learned_for += 1;
+        total_reward
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::environment::Environment;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+    use crate::chapter_10::mountain_car::{CarAction, MountainCar, TILES};
+    use crate::chapter_13::example_13_1::generate_left_state;
+    use crate::chapter_13::value_function::LinearValueFunction;
+
+    /// The exact value of the start state of the short corridor when right is picked with
+    /// `probability`, the uniform random policy gets -12.
+    fn short_corridor_value(probability: f64) -> f64 {
+        (2.0 * probability - 4.0) / (probability * (1.0 - probability))
+    }
+
+    fn probability_of_right<C: ValueFunction>(agent: &ActorCritic<C>) -> f64 {
+        let state = generate_left_state();
+        let actions = Environment::get_actions(&state);
+        let index = actions.iter().position(|a| a == "r").unwrap();
+        agent.get_actor().get_probabilities(&state)[index]
+    }
+
+    #[test]
+    fn beats_random_on_the_short_corridor() {
+        [(0.0, 0.0), (0.8, 0.8)]
+            .into_iter()
+            .for_each(|(actor_trace_decay, critic_trace_decay)| {
+                let mut agent = ActorCritic::new(LinearValueFunction::new(3), 2, 0.002, 0.02, 1.0);
+                agent.set_seed(13);
+                agent.set_trace_decay(actor_trace_decay, critic_trace_decay);
+                agent.learn_for_episode_count(3000, vec![generate_left_state()]);
+
+                let probability = probability_of_right(&agent);
+                assert!(
+                    short_corridor_value(probability) > short_corridor_value(0.5),
+                    "picking right {} of the time is no better than random",
+                    probability
+                );
+            });
+    }
+
+    /// Runs an episode picking actions with `pick_action`, gives up after `max_steps`.
+    fn steps_to_finish(
+        mut pick_action: impl FnMut(&MountainCar) -> String,
+        max_steps: usize,
+    ) -> usize {
+        let mut state = MountainCar::new(-0.5, 0.0);
+        let mut steps = 0;
+        while !Environment::is_terminal(&state) && steps < max_steps {
+            let action = pick_action(&state);
+            state = Environment::take_action(&state, &action).1;
+            steps += 1;
+        }
+        steps
+    }
+
+    #[test]
+    fn beats_random_on_mountain_car() {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(TILES * 2)
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        let critic = builder.build().unwrap();
+
+        let mut agent = ActorCritic::new(critic, TILES * 2 * CarAction::COUNT, 0.001, 0.03, 1.0);
+        agent.set_seed(21);
+        agent.set_trace_decay(0.9, 0.9);
+        agent.learn_for_episode_count(30, vec![MountainCar::new(-0.5, 0.0)]);
+
+        let max_steps = 5000;
+        let learned = (0..5)
+            .map(|_| steps_to_finish(|state| agent.select_action_for_state(state), max_steps))
+            .sum::<usize>();
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let random = (0..5)
+            .map(|_| {
+                steps_to_finish(
+                    |state| {
+                        Environment::get_actions(state)
+                            .choose(&mut rng)
+                            .unwrap()
+                            .clone()
+                    },
+                    max_steps,
+                )
+            })
+            .sum::<usize>();
+
+        assert!(
+            learned < random,
+            "took {} steps over 5 episodes while random took {}",
+            learned,
+            random
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use crate::attempts_at_framework::v2::state::State;
+use crate::attempts_at_framework::environment::StateActionFeatureVector;
+use crate::attempts_at_framework::v1::state::State;
+use crate::attempts_at_framework::v2;
 
 #[derive(Clone)]
 pub struct GridworldState {
@@ -43,18 +45,34 @@ impl State for GridworldState {
             _ => panic!("Invalid id"),
         }
     }
+}
 
+/// The state values only matter to value functions, which can tell the states apart, it is
+/// the policy that only gets to see [`StateActionFeatureVector::get_state_action_values`].
+impl v2::state::State for GridworldState {
     fn get_values(&self) -> Vec<f64> {
         self.values.clone()
     }
 }
 
+/// As in example 13.1 every state looks the same, the only features are which action is taken,
+/// `[1, 0]` for right and `[0, 1]` for left.
+impl StateActionFeatureVector for GridworldState {
+    fn get_state_action_values(&self, action: &String) -> Vec<f64> {
+        match action.as_str() {
+            "r" => vec![1.0, 0.0],
+            "l" => vec![0.0, 1.0],
+            _ => panic!("Invalid action {}", action),
+        }
+    }
+}
+
 pub fn generate_left_state() -> GridworldState {
     GridworldState {
         id: "left".to_string(),
         actions: vec!["l".to_string(), "r".to_string()],
         is_terminal: false,
-        values: vec![0.0, 0.0],
+        values: vec![1.0, 0.0, 0.0],
     }
 }
 
@@ -63,7 +81,7 @@ pub fn generate_center_state() -> GridworldState {
         id: "center".to_string(),
         actions: vec!["l".to_string(), "r".to_string()],
         is_terminal: false,
-        values: vec![0.0, 0.0],
+        values: vec![0.0, 1.0, 0.0],
     }
 }
 
@@ -72,7 +90,7 @@ pub fn generate_right_state() -> GridworldState {
         id: "right".to_string(),
         actions: vec!["l".to_string(), "r".to_string()],
         is_terminal: false,
-        values: vec![0.0, 0.0],
+        values: vec![0.0, 0.0, 1.0],
     }
 }
 
@@ -81,6 +99,6 @@ pub fn generate_terminal_state() -> GridworldState {
         id: "terminal".to_string(),
         actions: Vec::new(),
         is_terminal: true,
-        values: vec![0.0, 0.0],
+        values: vec![0.0, 0.0, 0.0],
     }
 }
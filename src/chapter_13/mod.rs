@@ -1,4 +1,6 @@
-mod actor_critic;
-mod example_13_1;
+pub mod actor_critic;
+pub mod example_13_1;
 mod mucking_around;
+pub mod reinforce;
 mod reinforce_monte_carlo;
+pub mod value_function;
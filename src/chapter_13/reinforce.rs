@@ -0,0 +1,336 @@
+use crate::attempts_at_framework::environment::FeatureVector;
+use crate::attempts_at_framework::v2::policy::soft_max;
+use crate::chapter_13::value_function::ValueFunction;
+use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// How the values of a state are turned into the input of the preference function, one input per
+/// action.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreferenceInput {
+    /// The state's values copied into the block for the action's position in `get_actions`, every
+    /// other block is left at 0.0. For environments like mountain car where every state has the
+    /// same actions, the preference function needs `action_count` times as many inputs.
+    ActionBlocks { action_count: usize },
+    /// The values of the state each action leads to. For environments like chess where the
+    /// actions differ from state to state, but only makes sense when actions lead to noticeably
+    /// different states.
+    AfterStates,
+}
+
+impl PreferenceInput {
+    fn get_inputs<E: FeatureVector>(&self, state: &E, actions: &[E::Action]) -> Vec<Vec<f64>> {
+        match self {
+            PreferenceInput::ActionBlocks { action_count } => {
+                if actions.len() > *action_count {
+                    panic!(
+                        "state has {} actions but only {} blocks",
+                        actions.len(),
+                        action_count
+                    );
+                }
+                let values = state.get_values();
+                (0..actions.len())
+                    .map(|index| {
+                        let mut input = vec![0.0; values.len() * action_count];
+                        input[index * values.len()..(index + 1) * values.len()]
+                            .copy_from_slice(&values);
+                        input
+                    })
+                    .collect()
+            }
+            PreferenceInput::AfterStates => actions
+                .iter()
+                .map(|action| state.take_action(action).1.get_values())
+                .collect(),
+        }
+    }
+}
+
+/// A step of an episode, kept until the episode is over and the return is known.
+struct Step {
+    /// the input of the preference function for every action
+    inputs: Vec<Vec<f64>>,
+    action_index: usize,
+    values: Vec<f64>,
+    reward: f64,
+}
+
+/// # Reinforce
+///
+/// Monte carlo policy gradient, sections 13.3 and 13.4 of the book. Unlike
+/// [`ReinforceMonteCarlo`](super::reinforce_monte_carlo) the action preferences are worked out
+/// from [`FeatureVector::get_values`] by any [`ValueFunction`], either linear weights or a
+/// [`Model`](crate::attempts_at_framework::v2::artificial_neural_network::model::Model), so it
+/// scales to environments with too many states to keep a preference for each.
+///
+/// Without a baseline every update is scaled by the full return, use
+/// [`Reinforce::set_baseline`] to learn a state value to subtract from it, which usually learns a
+/// lot faster.
+pub struct Reinforce {
+    preferences: Box<dyn ValueFunction>,
+    preference_input: PreferenceInput,
+    step_size: f64,
+    baseline: Option<Box<dyn ValueFunction>>,
+    baseline_step_size: f64,
+    discount_rate: f64,
+    episodes_learned_for: usize,
+    rng: StdRng,
+}
+
+impl Reinforce {
+    pub fn new(
+        preferences: Box<dyn ValueFunction>,
+        preference_input: PreferenceInput,
+        step_size: f64,
+        discount_rate: f64,
+    ) -> Self {
+        if !(0.0..=1.0).contains(&discount_rate) {
+            panic!("Discount rate must be between 0.0 and 1.0")
+        }
+
+        Self {
+            preferences,
+            preference_input,
+            step_size,
+            baseline: None,
+            baseline_step_size: 0.0,
+            discount_rate,
+            episodes_learned_for: 0,
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// Learns the value of each state from its values, and subtracts it from the return before
+    /// updating the preferences.
+    pub fn set_baseline(&mut self, baseline: Box<dyn ValueFunction>, step_size: f64) {
+        self.baseline = Some(baseline);
+        self.baseline_step_size = step_size;
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn get_episodes_learned_for(&self) -> usize {
+        self.episodes_learned_for
+    }
+
+    /// The chance of picking each of the state's actions, in the order of `get_actions`.
+    pub fn get_probabilities<E: FeatureVector>(&self, state: &E) -> Vec<f64> {
+        let actions = state.get_actions();
+        let inputs = self.preference_input.get_inputs(state, &actions);
+        self.get_probabilities_for_inputs(&inputs)
+    }
+
+    fn get_probabilities_for_inputs(&self, inputs: &[Vec<f64>]) -> Vec<f64> {
+        let preferences = inputs
+            .iter()
+            .map(|input| self.preferences.get_value(input))
+            .collect::<Vec<f64>>();
+        soft_max(&preferences)
+    }
+
+    pub fn select_action_for_state<E: FeatureVector>(&mut self, state: &E) -> E::Action {
+        let actions = state.get_actions();
+        let inputs = self.preference_input.get_inputs(state, &actions);
+        let index = self.pick_index(&inputs);
+        actions[index].clone()
+    }
+
+    fn pick_index(&mut self, inputs: &[Vec<f64>]) -> usize {
+        let probabilities = self.get_probabilities_for_inputs(inputs);
+        let indexes = (0..inputs.len()).collect::<Vec<usize>>();
+        *indexes
+            .choose_weighted(&mut self.rng, |index| probabilities[*index])
+            .unwrap()
+    }
+
+    /// Returns the total reward of each episode.
+    pub fn learn_for_episode_count<E: FeatureVector>(
+        &mut self,
+        episode_count: usize,
+        starting_states: Vec<E>,
+    ) -> Vec<f64> {
+        (0..episode_count)
+            .map(|_| {
+                let starting_state = starting_states.choose(&mut self.rng).unwrap().clone();
+                self.learn_from_episode(starting_state)
+            })
+            .collect()
+    }
+
+    /// Plays out a whole episode with the current policy, then learns from every step of it.
+    /// Returns the total reward of the episode.
+    pub fn learn_from_episode<E: FeatureVector>(&mut self, starting_state: E) -> f64 {
+        let mut steps = Vec::new();
+        let mut state = starting_state;
+        while !state.is_terminal() {
+            let actions = state.get_actions();
+            let inputs = self.preference_input.get_inputs(&state, &actions);
+            let action_index = self.pick_index(&inputs);
+            let (reward, next_state) = state.take_action(&actions[action_index]);
+            steps.push(Step {
+                inputs,
+                action_index,
+                values: state.get_values(),
+                reward,
+            });
+            state = next_state;
+        }
+
+        let mut episode_return = 0.0;
+        let mut returns = steps
+            .iter()
+            .rev()
+            .map(|step| {
+                episode_return = step.reward + self.discount_rate * episode_return;
+                episode_return
+            })
+            .collect::<Vec<f64>>();
+        returns.reverse();
+
+        let mut discount = 1.0;
+        steps
+            .iter()
+            .zip(returns)
+            .for_each(|(step, episode_return)| {
+                let error = match self.baseline.as_mut() {
+                    None => episode_return,
+                    Some(baseline) => {
+                        let error = episode_return - baseline.get_value(&step.values);
+                        let baseline_step = baseline
+                            .get_gradient(&step.values)
+                            .iter()
+                            .map(|g| self.baseline_step_size * error * g)
+                            .collect::<Vec<f64>>();
+                        baseline.add_to_parameters(&baseline_step);
+                        error
+                    }
+                };
+
+                let log_gradient = self.get_log_gradient(&step.inputs, step.action_index);
+                let preference_step = log_gradient
+                    .iter()
+                    .map(|g| self.step_size * discount * error * g)
+                    .collect::<Vec<f64>>();
+                self.preferences.add_to_parameters(&preference_step);
+                discount *= self.discount_rate;
+            });
+
+        self.episodes_learned_for += 1;
+        steps.iter().map(|step| step.reward).sum()
+    }
+
+    /// `∇ ln π(a|s) = ∇h(s, a) - Σ π(b|s) ∇h(s, b)`
+    fn get_log_gradient(&self, inputs: &[Vec<f64>], action_index: usize) -> Vec<f64> {
+        let probabilities = self.get_probabilities_for_inputs(inputs);
+        let mut gradient = self.preferences.get_gradient(&inputs[action_index]);
+        inputs
+            .iter()
+            .zip(probabilities)
+            .for_each(|(input, probability)| {
+                gradient
+                    .iter_mut()
+                    .zip(self.preferences.get_gradient(input))
+                    .for_each(|(g, h)| *g -= probability * h);
+            });
+        gradient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::environment::Environment;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+    use crate::chapter_13::example_13_1::generate_left_state;
+    use crate::chapter_13::value_function::LinearValueFunction;
+    use crate::chess_state_v2::ChessStateV2;
+    use simple_chess::chess_game_state_analyzer::GameState;
+    use simple_chess::{ChessGame, ChessMoveType};
+
+    fn average_return<E: FeatureVector>(agent: &mut Reinforce, state: E, episodes: usize) -> f64 {
+        (0..episodes)
+            .map(|_| {
+                let mut state = state.clone();
+                let mut total_reward = 0.0;
+                while !state.is_terminal() {
+                    let action = agent.select_action_for_state(&state);
+                    let (reward, next_state) = state.take_action(&action);
+                    total_reward += reward;
+                    state = next_state;
+                }
+                total_reward
+            })
+            .sum::<f64>()
+            / episodes as f64
+    }
+
+    #[test]
+    fn beats_random_on_the_short_corridor() {
+        [false, true].into_iter().for_each(|with_baseline| {
+            let mut agent = Reinforce::new(
+                Box::new(LinearValueFunction::new(3 * 2)),
+                PreferenceInput::ActionBlocks { action_count: 2 },
+                0.01,
+                1.0,
+            );
+            agent.set_seed(4);
+            if with_baseline {
+                agent.set_baseline(Box::new(LinearValueFunction::new(3)), 0.1);
+            }
+            agent.learn_for_episode_count(300, vec![generate_left_state()]);
+
+            // the uniform random policy averages -12
+            let average = average_return(&mut agent, generate_left_state(), 200);
+            assert!(
+                average > -8.0,
+                "averaged {} with a baseline: {}",
+                average,
+                with_baseline
+            );
+        });
+    }
+
+    #[test]
+    fn learns_from_chess_games() {
+        let pick_first_move = |game: &mut ChessGame| -> ChessMoveType {
+            match game.get_game_state() {
+                GameState::InProgress { legal_moves, .. } => legal_moves[0],
+                GameState::Check { legal_moves, .. } => legal_moves[0],
+                _ => panic!("no moves to make"),
+            }
+        };
+        // white to move with a queen and king against a lone king
+        let state = ChessStateV2::new(
+            "7k/8/5K2/8/8/8/8/6Q1 w - - 0 1".to_string(),
+            pick_first_move,
+        );
+
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(state.get_values().len())
+            .set_seed(2)
+            .add_layer(LayerBuilder::new(LayerType::RELU, 8))
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        let mut agent = Reinforce::new(
+            Box::new(builder.build().unwrap()),
+            PreferenceInput::AfterStates,
+            0.001,
+            0.9,
+        );
+        agent.set_seed(1);
+        agent.learn_for_episode_count(2, vec![state.clone()]);
+
+        assert_eq!(agent.get_episodes_learned_for(), 2);
+        let probabilities = agent.get_probabilities(&state);
+        assert_eq!(probabilities.len(), Environment::get_actions(&state).len());
+        assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+}
@@ -1,6 +1,3 @@
-use rand::Rng;
-use std::collections::HashMap;
-
 /// # Reinforce Monte Carlo
 ///
 /// The first policy gradient control method discussed in the book, but I still have some
@@ -29,7 +26,7 @@ struct ReinforceMonteCarlo {
      * That still does not work well for traditional reinforce learning and having a preference
      * over the entire action space, or does it?
      */
-    preferences: HashMap<(String, String), f64>,
+    policy: PolicyGradient,
     learning_rate: f64,
     discount_rate: f64,
 }
@@ -37,55 +34,14 @@ struct ReinforceMonteCarlo {
 impl ReinforceMonteCarlo {
     pub fn new(learning_rate: f64, discount_rate: f64) -> Self {
         Self {
-            preferences: HashMap::new(),
+            policy: PolicyGradient::new(),
             learning_rate,
             discount_rate,
         }
     }
 
     fn get_preference(&self, state_id: &str, action: &str) -> f64 {
-        *self
-            .preferences
-            .get(&(state_id.to_string(), action.to_string()))
-            .unwrap_or(&0.0)
-    }
-
-    fn softmax_probabilities(&self, state_id: &str, actions: &[String]) -> Vec<f64> {
-        let preferences: Vec<f64> = actions
-            .iter()
-            .map(|a| self.get_preference(state_id, a))
-            .collect();
-        let max_pref = preferences
-            .iter()
-            .cloned()
-            .fold(f64::NEG_INFINITY, f64::max);
-        let exp_prefs: Vec<f64> = preferences.iter().map(|&p| (p - max_pref).exp()).collect();
-        let sum_exp: f64 = exp_prefs.iter().cloned().sum();
-        exp_prefs.iter().map(|&p| p / sum_exp).collect()
-    }
-
-    /// Calculate the policy gradient for softmax policy
-    /// ∇ ln π(a|s,θ) for softmax policy
-    fn policy_gradient(
-        &self,
-        state_id: &str,
-        actions: &[String],
-        selected_action: &str,
-    ) -> HashMap<String, f64> {
-        let probabilities = self.softmax_probabilities(state_id, actions);
-        let mut gradients = HashMap::new();
-
-        for (i, action) in actions.iter().enumerate() {
-            let prob = probabilities[i];
-            let gradient = if action == selected_action {
-                1.0 - prob // ∇ ln π(a|s,θ) = 1 - π(a|s,θ) for selected action
-            } else {
-                -prob // ∇ ln π(a|s,θ) = -π(a|s,θ) for non-selected actions
-            };
-            gradients.insert(action.clone(), gradient);
-        }
-
-        gradients
+        self.policy.get_preference(state_id, &action.to_string())
     }
 
     /// Generate an episode following the current policy
@@ -144,16 +100,13 @@ impl ReinforceMonteCarlo {
                 _ => panic!("Unknown state: {}", state_id),
             };
 
-            // Calculate policy gradient ∇ ln π(A_t|S_t, θ)
-            let gradients = self.policy_gradient(state_id, &actions, action);
-
             // Update preferences: θ ← θ + α * G_t * ∇ ln π(A_t|S_t, θ)
-            for (act, gradient) in gradients {
-                let key = (state_id.clone(), act.clone());
-                let current_preference = self.preferences.get(&key).unwrap_or(&0.0);
-                let update = self.learning_rate * (g_t - estimated_value) * gradient;
-                self.preferences.insert(key, current_preference + update);
-            }
+            self.policy.update(
+                state_id,
+                &actions,
+                action,
+                self.learning_rate * (g_t - estimated_value),
+            );
         }
     }
 
@@ -195,24 +148,16 @@ impl Policy for ReinforceMonteCarlo {
         };
 
         let actions = state.get_actions();
-        let probabilities = self.softmax_probabilities(state_id, &actions);
-
-        let mut cum_prob = 0.0;
-        let sample: f64 = rand::rng().random();
-
-        for (action, &prob) in actions.iter().zip(probabilities.iter()) {
-            cum_prob += prob;
-            if sample <= cum_prob {
-                return Ok(action.to_string());
-            }
-        }
-
-        Ok(actions.last().unwrap().to_string())
+        Ok(self
+            .policy
+            .select_action(state_id, &actions, &mut rand::rng())
+            .unwrap())
     }
 }
 
 use crate::attempts_at_framework::v1::policy::{Policy, PolicyError};
-use crate::attempts_at_framework::v2::state::State;
+use crate::attempts_at_framework::v1::state::State;
+use crate::attempts_at_framework::v2::policy::policy_gradient::PolicyGradient;
 use crate::chapter_13::example_13_1::{
     generate_center_state, generate_left_state, generate_right_state,
 };
@@ -226,11 +171,11 @@ mod tests {
     fn test_select_action_for_state() {
         let mut policy = ReinforceMonteCarlo::new(0.0, 0.0);
         policy
-            .preferences
-            .insert(("center".to_string(), "l".to_string()), 0.9);
+            .policy
+            .set_preference("center".to_string(), "l".to_string(), 0.9);
         policy
-            .preferences
-            .insert(("center".to_string(), "r".to_string()), 0.1);
+            .policy
+            .set_preference("center".to_string(), "r".to_string(), 0.1);
         let state = generate_center_state();
         let action = policy.select_action_for_state("center").unwrap();
         assert_eq!(action, "l");
@@ -0,0 +1,73 @@
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+
+/// # Value Function
+///
+/// A function of a list of values that can be learnt with its gradient, used as the critic and
+/// baseline of the policy gradient methods, and for action preferences. The gradient is with
+/// respect to the function's own parameters, and is what eligibility traces are built up from.
+pub trait ValueFunction {
+    fn get_value(&self, values: &[f64]) -> f64;
+
+    fn get_gradient(&self, values: &[f64]) -> Vec<f64>;
+
+    /// Adds `step` to the parameters, laid out the same way as [`ValueFunction::get_gradient`].
+    fn add_to_parameters(&mut self, step: &[f64]);
+}
+
+/// A linear value function with a bias, `v(s) = w · x(s) + b`.
+pub struct LinearValueFunction {
+    /// one weight per value, followed by the bias
+    weights: Vec<f64>,
+}
+
+impl LinearValueFunction {
+    pub fn new(value_count: usize) -> Self {
+        Self {
+            weights: vec![0.0; value_count + 1],
+        }
+    }
+
+    pub fn get_weights(&self) -> &[f64] {
+        &self.weights
+    }
+}
+
+impl ValueFunction for LinearValueFunction {
+    fn get_value(&self, values: &[f64]) -> f64 {
+        self.get_gradient(values)
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(x, w)| x * w)
+            .sum()
+    }
+
+    fn get_gradient(&self, values: &[f64]) -> Vec<f64> {
+        assert_eq!(
+            values.len() + 1,
+            self.weights.len(),
+            "expected {} values",
+            self.weights.len() - 1
+        );
+        values.iter().copied().chain([1.0]).collect()
+    }
+
+    fn add_to_parameters(&mut self, step: &[f64]) {
+        self.weights.iter_mut().zip(step).for_each(|(w, s)| *w += s);
+    }
+}
+
+/// Any model with a single output can be a value function, the model's optimizer is not used as
+/// the updates come from the policy gradient methods rather than a loss.
+impl ValueFunction for Model {
+    fn get_value(&self, values: &[f64]) -> f64 {
+        self.predict(values.to_vec())[0]
+    }
+
+    fn get_gradient(&self, values: &[f64]) -> Vec<f64> {
+        self.get_parameter_gradients(values.to_vec())
+    }
+
+    fn add_to_parameters(&mut self, step: &[f64]) {
+        Model::add_to_parameters(self, step);
+    }
+}
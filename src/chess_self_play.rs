@@ -0,0 +1,507 @@
+use crate::attempts_at_framework::v1::agent::{ChessEvaluation, MaterialEvaluation};
+use crate::attempts_at_framework::v1::state::State;
+use crate::attempts_at_framework::v2;
+use crate::attempts_at_framework::v2::agent::n_step_td::NStepTD;
+use crate::attempts_at_framework::v2::artificial_neural_network::model::Model;
+use crate::chess_state::get_state_id_from_fen_string;
+use crate::chess_state_v2::get_values_from_game;
+use simple_chess::chess_game_state_analyzer::GameState;
+use simple_chess::codec::forsyth_edwards_notation::{
+    build_game_from_string, encode_game_as_string,
+};
+use simple_chess::codec::long_algebraic_notation::encode_move_as_long_algebraic_notation;
+use simple_chess::{ChessGame, ChessMoveType, Color};
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+const NEW_GAME_FEN_STRING: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// How a game went for the learning agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOutcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Who the learning agent plays against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Opponent {
+    /// Itself, the opponent is a copy of the agent's model taken at the start of every game.
+    SelfPlay,
+    /// A copy of the agent's model that's only brought up to date every `refresh_every` games,
+    /// or never when it's 0, so the agent has to beat an older version of itself.
+    FrozenSnapshot { refresh_every: usize },
+}
+
+/// How [`train_by_self_play`] plays its games.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfPlayConfig {
+    pub episode_count: usize,
+    /// Games are adjudicated once this many plies, moves by either player, have been played.
+    pub max_plies: usize,
+    /// games per [`WindowStatistics`]
+    pub window_size: usize,
+    pub opponent: Opponent,
+    /// End games as draws as soon as one could be claimed, by the fifty move rule, repetition or
+    /// insufficient material.
+    pub adjudicate_claimable_draws: bool,
+    /// Games that reach `max_plies` with a player this many pawns of material ahead are a win for
+    /// them, every other game that reaches it is a draw.
+    pub material_margin: Option<f64>,
+    /// every game starts from here
+    pub starting_fen: String,
+}
+
+impl SelfPlayConfig {
+    /// From the usual starting position, draws can be claimed, 200 plies a game, statistics
+    /// every 100 games.
+    pub fn new(episode_count: usize) -> Self {
+        Self {
+            episode_count,
+            max_plies: 200,
+            window_size: 100,
+            opponent: Opponent::SelfPlay,
+            adjudicate_claimable_draws: true,
+            material_margin: None,
+            starting_fen: NEW_GAME_FEN_STRING.to_string(),
+        }
+    }
+}
+
+/// How the learning agent did over a run of games.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowStatistics {
+    /// the number of games played before this window
+    pub first_game: usize,
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// games that didn't end in checkmate or stalemate
+    pub adjudicated: usize,
+    pub average_plies: f64,
+}
+
+impl WindowStatistics {
+    fn new(first_game: usize) -> Self {
+        Self {
+            first_game,
+            games: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            adjudicated: 0,
+            average_plies: 0.0,
+        }
+    }
+
+    fn add_game(&mut self, outcome: GameOutcome, adjudicated: bool, plies: usize) {
+        self.average_plies =
+            (self.average_plies * self.games as f64 + plies as f64) / (self.games + 1) as f64;
+        self.games += 1;
+        match outcome {
+            GameOutcome::Win => self.wins += 1,
+            GameOutcome::Draw => self.draws += 1,
+            GameOutcome::Loss => self.losses += 1,
+        }
+        if adjudicated {
+            self.adjudicated += 1;
+        }
+    }
+}
+
+impl Display for WindowStatistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "games {}-{}: {} wins, {} draws, {} losses ({} adjudicated), {:.1} plies on average",
+            self.first_game + 1,
+            self.first_game + self.games,
+            self.wins,
+            self.draws,
+            self.losses,
+            self.adjudicated,
+            self.average_plies
+        )
+    }
+}
+
+pub struct SelfPlayError {
+    message: String,
+}
+
+impl Debug for SelfPlayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SelfPlayError: {}", self.message)
+    }
+}
+
+impl Display for SelfPlayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SelfPlayError: {}", self.message)
+    }
+}
+
+impl Error for SelfPlayError {}
+
+/// What every state of a game shares.
+struct SelfPlayRules {
+    opponent_model: Model,
+    learner: Color,
+    max_plies: usize,
+    adjudicate_claimable_draws: bool,
+    material_margin: Option<f64>,
+}
+
+/// # Self Play State
+///
+/// A chess game from the learning agent's side of the board, it's always the agent's move unless
+/// the game is over. Taking an action makes the agent's move then the opponent's reply. The
+/// reward is 1.0 when the agent wins, -1.0 when it loses and 0.0 otherwise.
+///
+/// The opponent plays the move that leaves the agent in the position its model values least,
+/// as the model values positions for the player to move.
+#[derive(Clone)]
+pub struct SelfPlayState {
+    game: ChessGame,
+    id: String,
+    moves: Vec<ChessMoveType>,
+    plies: usize,
+    /// `None` until the game is over
+    outcome: Option<GameOutcome>,
+    adjudicated: bool,
+    rules: Rc<SelfPlayRules>,
+}
+
+impl SelfPlayState {
+    fn new(mut game: ChessGame, plies: usize, rules: Rc<SelfPlayRules>) -> Self {
+        let to_move = game.get_current_players_turn();
+        // from the point of view of the player to move
+        let (outcome, adjudicated, moves) = match game.get_game_state() {
+            GameState::Checkmate { .. } => (Some(GameOutcome::Loss), false, Vec::new()),
+            GameState::Stalemate => (Some(GameOutcome::Draw), false, Vec::new()),
+            GameState::InProgress { legal_moves, .. } | GameState::Check { legal_moves, .. } => {
+                if rules.adjudicate_claimable_draws && game.can_claim_draw().is_some() {
+                    (Some(GameOutcome::Draw), true, Vec::new())
+                } else if plies >= rules.max_plies {
+                    let material = MaterialEvaluation.evaluate(&game);
+                    let outcome = match rules.material_margin {
+                        Some(margin) if material >= margin => GameOutcome::Win,
+                        Some(margin) if material <= -margin => GameOutcome::Loss,
+                        _ => GameOutcome::Draw,
+                    };
+                    (Some(outcome), true, Vec::new())
+                } else {
+                    (None, false, legal_moves)
+                }
+            }
+        };
+        let outcome = outcome.map(|outcome| match (to_move == rules.learner, outcome) {
+            (false, GameOutcome::Win) => GameOutcome::Loss,
+            (false, GameOutcome::Loss) => GameOutcome::Win,
+            (_, outcome) => outcome,
+        });
+
+        Self {
+            id: get_state_id_from_fen_string(&encode_game_as_string(&game)),
+            game,
+            moves,
+            plies,
+            outcome,
+            adjudicated,
+            rules,
+        }
+    }
+
+    /// The state to start a game from, the opponent moves first when it's their move.
+    fn start(game: ChessGame, rules: Rc<SelfPlayRules>) -> Self {
+        let state = Self::new(game, 0, rules);
+        if state.outcome.is_some() || state.game.get_current_players_turn() == state.rules.learner {
+            return state;
+        }
+        state.make_opponent_move()
+    }
+
+    pub fn get_game(&self) -> &ChessGame {
+        &self.game
+    }
+
+    /// `None` until the game is over.
+    pub fn get_outcome(&self) -> Option<GameOutcome> {
+        self.outcome
+    }
+
+    /// Did the game end some other way than checkmate or stalemate.
+    pub fn was_adjudicated(&self) -> bool {
+        self.adjudicated
+    }
+
+    /// moves by either player since the game started
+    pub fn get_plies(&self) -> usize {
+        self.plies
+    }
+
+    fn get_reward(&self) -> f64 {
+        match self.outcome {
+            Some(GameOutcome::Win) => 1.0,
+            Some(GameOutcome::Loss) => -1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn make_move(&self, chess_move: ChessMoveType) -> Self {
+        let mut game = self.game.clone();
+        game.make_move(chess_move);
+        Self::new(game, self.plies + 1, self.rules.clone())
+    }
+
+    fn make_opponent_move(&self) -> Self {
+        let replies = self
+            .moves
+            .iter()
+            .map(|chess_move| self.make_move(*chess_move))
+            .collect::<Vec<Self>>();
+        let values = self.rules.opponent_model.predict_batch(
+            &replies
+                .iter()
+                .filter(|reply| reply.outcome.is_none())
+                .map(|reply| get_values_from_game(&reply.game))
+                .collect::<Vec<Vec<f64>>>(),
+        );
+
+        let mut values = values.into_iter();
+        replies
+            .into_iter()
+            .map(|reply| {
+                let value = match reply.outcome {
+                    Some(_) => reply.get_reward(),
+                    None => values.next().unwrap()[0],
+                };
+                (reply, value)
+            })
+            .fold(
+                None,
+                |best: Option<(Self, f64)>, (reply, value)| match best {
+                    Some((_, best_value)) if best_value <= value => best,
+                    _ => Some((reply, value)),
+                },
+            )
+            .map(|(reply, _)| reply)
+            .expect("the opponent has no moves but the game isn't over")
+    }
+}
+
+impl State for SelfPlayState {
+    fn get_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn get_actions(&self) -> Vec<String> {
+        self.moves
+            .iter()
+            .map(encode_move_as_long_algebraic_notation)
+            .collect()
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    fn take_action(&self, action: &str) -> (f64, Self) {
+        let chess_move = self
+            .moves
+            .iter()
+            .find(|m| encode_move_as_long_algebraic_notation(m) == action)
+            .unwrap();
+        let next_state = self.make_move(*chess_move);
+        if next_state.is_terminal() {
+            return (next_state.get_reward(), next_state);
+        }
+
+        let next_state = next_state.make_opponent_move();
+        (next_state.get_reward(), next_state)
+    }
+}
+
+impl v2::state::State for SelfPlayState {
+    fn get_values(&self) -> Vec<f64> {
+        get_values_from_game(&self.game)
+    }
+}
+
+/// # Train By Self Play
+///
+/// Trains `agent` headless, one game per episode against the [`Opponent`] in `config`. The agent
+/// plays white in the first game then swaps sides every game, so it learns to value positions
+/// for both players. Its model has to value states from [`get_values_from_game`], like the
+/// chess app's [`NStepTD`] model.
+///
+/// Returns how the agent did every `window_size` games, the last window has whatever games are
+/// left over.
+pub fn train_by_self_play(
+    agent: &mut NStepTD,
+    config: &SelfPlayConfig,
+) -> Result<Vec<WindowStatistics>, SelfPlayError> {
+    if config.window_size == 0 {
+        return Err(SelfPlayError {
+            message: "window size must be at least 1".to_string(),
+        });
+    }
+    let starting_game =
+        build_game_from_string(&config.starting_fen).map_err(|_| SelfPlayError {
+            message: format!("{} is not a valid FEN string", config.starting_fen),
+        })?;
+
+    let mut windows = Vec::new();
+    let mut window = WindowStatistics::new(0);
+    let mut snapshot = agent.get_model().clone_weights();
+    for episode in 0..config.episode_count {
+        let refresh = match config.opponent {
+            Opponent::SelfPlay => true,
+            Opponent::FrozenSnapshot { refresh_every } => {
+                refresh_every > 0 && episode % refresh_every == 0
+            }
+        };
+        if refresh {
+            snapshot = agent.get_model().clone_weights();
+        }
+
+        let rules = Rc::new(SelfPlayRules {
+            opponent_model: snapshot.clone_weights(),
+            learner: if episode % 2 == 0 {
+                Color::White
+            } else {
+                Color::Black
+            },
+            max_plies: config.max_plies,
+            adjudicate_claimable_draws: config.adjudicate_claimable_draws,
+            material_margin: config.material_margin,
+        });
+        let final_state =
+            agent.learn_from_episode(SelfPlayState::start(starting_game.clone(), rules));
+
+        window.add_game(
+            final_state.outcome.unwrap_or(GameOutcome::Draw),
+            final_state.adjudicated,
+            final_state.plies,
+        );
+        if window.games == config.window_size {
+            windows.push(window);
+            window = WindowStatistics::new(episode + 1);
+        }
+    }
+    if window.games > 0 {
+        windows.push(window);
+    }
+
+    Ok(windows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::initialization::Initialization;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        LayerBuilder, LayerType, ModelBuilder,
+    };
+
+    fn build_model() -> Model {
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(790)
+            .add_layer(
+                LayerBuilder::new(LayerType::LINEAR, 1).set_initialization(Initialization::Zeros),
+            );
+        builder.build().unwrap()
+    }
+
+    fn build_rules(
+        learner: Color,
+        max_plies: usize,
+        material_margin: Option<f64>,
+    ) -> Rc<SelfPlayRules> {
+        Rc::new(SelfPlayRules {
+            opponent_model: build_model(),
+            learner,
+            max_plies,
+            adjudicate_claimable_draws: true,
+            material_margin,
+        })
+    }
+
+    #[test]
+    fn opponent_takes_the_mate() {
+        // black can mate on the back rank, whatever the model thinks
+        let game = build_game_from_string("r5k1/8/8/8/8/8/5PPP/6K1 b - - 0 1").unwrap();
+        let state = SelfPlayState::start(game, build_rules(Color::White, 100, None));
+        assert_eq!(state.get_outcome(), Some(GameOutcome::Loss));
+        assert!(!state.was_adjudicated());
+        assert_eq!(state.get_plies(), 1);
+    }
+
+    #[test]
+    fn adjudicates_by_material() {
+        let fen = "4k3/8/8/8/8/8/8/R3K3 b - - 0 1";
+        let state = SelfPlayState::new(
+            build_game_from_string(fen).unwrap(),
+            10,
+            build_rules(Color::White, 10, Some(3.0)),
+        );
+        assert_eq!(state.get_outcome(), Some(GameOutcome::Win));
+        assert!(state.was_adjudicated());
+        assert!(state.get_actions().is_empty());
+
+        let state = SelfPlayState::new(
+            build_game_from_string(fen).unwrap(),
+            10,
+            build_rules(Color::White, 10, None),
+        );
+        assert_eq!(state.get_outcome(), Some(GameOutcome::Draw));
+    }
+
+    #[test]
+    fn keeps_statistics_per_window() {
+        let mut agent = NStepTD::new(4, build_model(), 0.001);
+        agent.set_seed(3);
+        agent.set_explore_rate(1.0);
+        let mut config = SelfPlayConfig::new(5);
+        config.starting_fen = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1".to_string();
+        config.max_plies = 6;
+        config.window_size = 2;
+        config.material_margin = Some(3.0);
+        config.opponent = Opponent::FrozenSnapshot { refresh_every: 0 };
+
+        let windows = train_by_self_play(&mut agent, &config).unwrap();
+        assert_eq!(windows.len(), 3);
+        assert_eq!(
+            windows.iter().map(|w| w.games).collect::<Vec<usize>>(),
+            vec![2, 2, 1]
+        );
+        assert_eq!(windows[2].first_game, 4);
+        // nobody can be mated in 6 plies with a lone rook from here, so the agent wins as white
+        // and loses as black on material
+        assert_eq!(windows[0].wins, 1);
+        assert_eq!(windows[0].losses, 1);
+        assert_eq!(windows[0].adjudicated, 2);
+        assert_eq!(windows[0].average_plies, 6.0);
+        assert_eq!(
+            windows[0].to_string(),
+            "games 1-2: 1 wins, 0 draws, 1 losses (2 adjudicated), 6.0 plies on average"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_configs() {
+        let mut agent = NStepTD::new(4, build_model(), 0.001);
+        let mut config = SelfPlayConfig::new(1);
+        config.starting_fen = "not a position".to_string();
+        assert!(train_by_self_play(&mut agent, &config).is_err());
+        config.starting_fen = NEW_GAME_FEN_STRING.to_string();
+        config.window_size = 0;
+        assert!(train_by_self_play(&mut agent, &config).is_err());
+    }
+}
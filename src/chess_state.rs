@@ -1,6 +1,10 @@
 use crate::attempts_at_framework::v1::state::State;
 use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use simple_chess::chess_game_state_analyzer::GameState;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub struct ChessState {
@@ -8,6 +12,8 @@ pub struct ChessState {
     fen_string: String,
     moves: Vec<String>,
     is_terminal: bool,
+    /// picks the opponent's replies, shared with every state reached from this one
+    opponent_rng: Option<Rc<RefCell<StdRng>>>,
 }
 
 pub fn get_state_id_from_fen_string(game_as_fen_string: &String) -> String {
@@ -51,6 +57,29 @@ impl ChessState {
             fen_string: game_as_fen_string,
             moves,
             is_terminal,
+            opponent_rng: None,
+        }
+    }
+
+    /// Same as [`ChessState::new`] but the opponent's random replies are seeded. The successor
+    /// states share the one rng, so the same seed and the same sequence of calls reproduce the
+    /// same games.
+    pub fn with_seed(game_as_fen_string: String, seed: u64) -> Self {
+        let mut state = Self::new(game_as_fen_string);
+        state.opponent_rng = Some(Rc::new(RefCell::new(StdRng::seed_from_u64(seed))));
+        state
+    }
+
+    fn next_state(&self, game_as_fen_string: String) -> Self {
+        let mut state = Self::new(game_as_fen_string);
+        state.opponent_rng = self.opponent_rng.clone();
+        state
+    }
+
+    fn pick_opponent_move<'a, T>(&self, moves: &'a [T]) -> Option<&'a T> {
+        match &self.opponent_rng {
+            Some(rng) => moves.choose(&mut *rng.borrow_mut()),
+            None => moves.choose(&mut rand::rng()),
         }
     }
 }
@@ -69,7 +98,6 @@ impl State for ChessState {
     }
 
     fn take_action(&self, action: &str) -> (f64, Self) {
-        let mut rng = rand::rng();
         let mut game =
             simple_chess::codec::forsyth_edwards_notation::build_game_from_string(&self.fen_string)
                 .unwrap();
@@ -93,15 +121,15 @@ impl State for ChessState {
                     GameState::Checkmate { .. } => Vec::new(),
                     GameState::Stalemate => Vec::new(),
                 };
-                let next_move = next_possible_moves.choose(&mut rng).unwrap();
+                let next_move = self.pick_opponent_move(&next_possible_moves).unwrap();
                 game.make_move(next_move.clone());
                 let new_fen_string =
                     simple_chess::codec::forsyth_edwards_notation::encode_game_as_string(&game);
                 match game.get_game_state() {
-                    GameState::InProgress { .. } => (0.0, ChessState::new(new_fen_string)),
-                    GameState::Check { .. } => (0.0, ChessState::new(new_fen_string)),
-                    GameState::Checkmate { .. } => (-1.0, ChessState::new(new_fen_string)),
-                    GameState::Stalemate => (0.0, ChessState::new(new_fen_string)),
+                    GameState::InProgress { .. } => (0.0, self.next_state(new_fen_string)),
+                    GameState::Check { .. } => (0.0, self.next_state(new_fen_string)),
+                    GameState::Checkmate { .. } => (-1.0, self.next_state(new_fen_string)),
+                    GameState::Stalemate => (0.0, self.next_state(new_fen_string)),
                 }
             }
             GameState::Check { .. } => {
@@ -111,26 +139,26 @@ impl State for ChessState {
                     GameState::Checkmate { .. } => Vec::new(),
                     GameState::Stalemate => Vec::new(),
                 };
-                let next_move = next_possible_moves.choose(&mut rng).unwrap();
+                let next_move = self.pick_opponent_move(&next_possible_moves).unwrap();
                 game.make_move(next_move.clone());
                 let new_fen_string =
                     simple_chess::codec::forsyth_edwards_notation::encode_game_as_string(&game);
                 match game.get_game_state() {
-                    GameState::InProgress { .. } => (0.0, ChessState::new(new_fen_string)),
-                    GameState::Check { .. } => (0.0, ChessState::new(new_fen_string)),
-                    GameState::Checkmate { .. } => (-1.0, ChessState::new(new_fen_string)),
-                    GameState::Stalemate => (0.0, ChessState::new(new_fen_string)),
+                    GameState::InProgress { .. } => (0.0, self.next_state(new_fen_string)),
+                    GameState::Check { .. } => (0.0, self.next_state(new_fen_string)),
+                    GameState::Checkmate { .. } => (-1.0, self.next_state(new_fen_string)),
+                    GameState::Stalemate => (0.0, self.next_state(new_fen_string)),
                 }
             }
             GameState::Checkmate { .. } => {
                 let fen_string =
                     simple_chess::codec::forsyth_edwards_notation::encode_game_as_string(&game);
-                (1.0, ChessState::new(fen_string))
+                (1.0, self.next_state(fen_string))
             }
             GameState::Stalemate => {
                 let fen_string =
                     simple_chess::codec::forsyth_edwards_notation::encode_game_as_string(&game);
-                (0.0, ChessState::new(fen_string))
+                (0.0, self.next_state(fen_string))
             }
         }
     }
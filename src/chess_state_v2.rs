@@ -1,8 +1,14 @@
-use crate::attempts_at_framework::v2::state::State;
+use crate::attempts_at_framework::environment::StateActionFeatureVector;
+use crate::attempts_at_framework::v1::state::State;
+use crate::attempts_at_framework::v2;
+use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
 use simple_chess::chess_game_state_analyzer::GameState;
 use simple_chess::game_board::{get_column_and_row_from_square_name, Board};
 use simple_chess::piece::{ChessPiece, PieceType};
 use simple_chess::{ChessGame, ChessMoveType, Color};
+use std::cell::RefCell;
+use std::rc::Rc;
 
 #[derive(Clone)]
 pub struct ChessStateV2 {
@@ -11,7 +17,15 @@ pub struct ChessStateV2 {
     moves: Vec<String>,
     is_terminal: bool,
     board: Board<ChessPiece>,
-    select_other_player_moves_fn: fn(&mut ChessGame) -> ChessMoveType,
+    other_player: OtherPlayer,
+}
+
+/// How the other player's replies are picked.
+#[derive(Clone)]
+enum OtherPlayer {
+    Function(fn(&mut ChessGame) -> ChessMoveType),
+    /// any legal move, the rng is shared with every state reached from this one
+    Random(Rc<RefCell<StdRng>>),
 }
 
 pub fn get_state_id_from_fen_string(game_as_fen_string: &String) -> String {
@@ -27,10 +41,29 @@ pub fn get_state_id_from_fen_string(game_as_fen_string: &String) -> String {
 }
 
 impl ChessStateV2 {
+    /// black then white pawns, knights, bishops, rooks, queens and kings
+    pub const PIECE_PLANE_COUNT: usize = 12;
+
     pub fn new(
         game_as_fen_string: String,
         select_other_player_moves_fn: fn(&mut ChessGame) -> ChessMoveType,
     ) -> Self {
+        Self::with_other_player(
+            game_as_fen_string,
+            OtherPlayer::Function(select_other_player_moves_fn),
+        )
+    }
+
+    /// Same as [`ChessStateV2::new`] but the other player replies with random legal moves picked
+    /// by `opponent_rng`, so a seeded rng gives the same replies every time.
+    pub fn with_opponent_rng(
+        game_as_fen_string: String,
+        opponent_rng: Rc<RefCell<StdRng>>,
+    ) -> Self {
+        Self::with_other_player(game_as_fen_string, OtherPlayer::Random(opponent_rng))
+    }
+
+    fn with_other_player(game_as_fen_string: String, other_player: OtherPlayer) -> Self {
         let mut game = simple_chess::codec::forsyth_edwards_notation::build_game_from_string(
             &game_as_fen_string,
         )
@@ -59,9 +92,38 @@ impl ChessStateV2 {
             moves,
             is_terminal,
             board: game.get_board().clone(),
-            select_other_player_moves_fn,
+            other_player,
         }
     }
+
+    fn next_state(&self, game_as_fen_string: String) -> Self {
+        Self::with_other_player(game_as_fen_string, self.other_player.clone())
+    }
+
+    fn pick_other_player_move(&self, game: &mut ChessGame) -> ChessMoveType {
+        match &self.other_player {
+            OtherPlayer::Function(select_other_player_moves_fn) => {
+                select_other_player_moves_fn(game)
+            }
+            OtherPlayer::Random(rng) => match game.get_game_state() {
+                GameState::InProgress { legal_moves, .. }
+                | GameState::Check { legal_moves, .. } => {
+                    *legal_moves.choose(&mut *rng.borrow_mut()).unwrap()
+                }
+                GameState::Checkmate { .. } | GameState::Stalemate => {
+                    panic!("the other player has no moves")
+                }
+            },
+        }
+    }
+
+    /// The first 768 values from [`v2::state::State::get_values`], one 8x8 plane per piece type
+    /// and color, one after the other and each row by row. Made to be the input of a model starting
+    /// with a `Conv2DShape::new(ChessStateV2::PIECE_PLANE_COUNT, 8, 8, ..)` convolution, the
+    /// castling, en passant and turn values aren't board shaped so they're left out.
+    pub fn get_piece_planes(&self) -> Vec<f64> {
+        get_piece_planes(&self.board)
+    }
 }
 
 impl State for ChessStateV2 {
@@ -95,165 +157,242 @@ impl State for ChessStateV2 {
         game.make_move(move_to_take.clone());
         match game.get_game_state() {
             GameState::InProgress { .. } => {
-                let next_move = (self.select_other_player_moves_fn)(&mut game);
+                let next_move = self.pick_other_player_move(&mut game);
                 game.make_move(next_move);
                 let new_fen_string =
                     simple_chess::codec::forsyth_edwards_notation::encode_game_as_string(&game);
                 match game.get_game_state() {
-                    GameState::InProgress { .. } => (
-                        0.0,
-                        ChessStateV2::new(new_fen_string, self.select_other_player_moves_fn),
-                    ),
-                    GameState::Check { .. } => (
-                        0.0,
-                        ChessStateV2::new(new_fen_string, self.select_other_player_moves_fn),
-                    ),
-                    GameState::Checkmate { .. } => (
-                        -1.0,
-                        ChessStateV2::new(new_fen_string, self.select_other_player_moves_fn),
-                    ),
-                    GameState::Stalemate => (
-                        0.0,
-                        ChessStateV2::new(new_fen_string, self.select_other_player_moves_fn),
-                    ),
+                    GameState::InProgress { .. } => (0.0, self.next_state(new_fen_string)),
+                    GameState::Check { .. } => (0.0, self.next_state(new_fen_string)),
+                    GameState::Checkmate { .. } => (-1.0, self.next_state(new_fen_string)),
+                    GameState::Stalemate => (0.0, self.next_state(new_fen_string)),
                 }
             }
             GameState::Check { .. } => {
-                let next_move = (self.select_other_player_moves_fn)(&mut game);
+                let next_move = self.pick_other_player_move(&mut game);
                 game.make_move(next_move);
                 let new_fen_string =
                     simple_chess::codec::forsyth_edwards_notation::encode_game_as_string(&game);
                 match game.get_game_state() {
-                    GameState::InProgress { .. } => (
-                        0.0,
-                        ChessStateV2::new(new_fen_string, self.select_other_player_moves_fn),
-                    ),
-                    GameState::Check { .. } => (
-                        0.0,
-                        ChessStateV2::new(new_fen_string, self.select_other_player_moves_fn),
-                    ),
-                    GameState::Checkmate { .. } => (
-                        -1.0,
-                        ChessStateV2::new(new_fen_string, self.select_other_player_moves_fn),
-                    ),
-                    GameState::Stalemate => (
-                        0.0,
-                        ChessStateV2::new(new_fen_string, self.select_other_player_moves_fn),
-                    ),
+                    GameState::InProgress { .. } => (0.0, self.next_state(new_fen_string)),
+                    GameState::Check { .. } => (0.0, self.next_state(new_fen_string)),
+                    GameState::Checkmate { .. } => (-1.0, self.next_state(new_fen_string)),
+                    GameState::Stalemate => (0.0, self.next_state(new_fen_string)),
                 }
             }
             GameState::Checkmate { .. } => {
                 let fen_string =
                     simple_chess::codec::forsyth_edwards_notation::encode_game_as_string(&game);
-                (
-                    1.0,
-                    ChessStateV2::new(fen_string, self.select_other_player_moves_fn),
-                )
+                (1.0, self.next_state(fen_string))
             }
             GameState::Stalemate => {
                 let fen_string =
                     simple_chess::codec::forsyth_edwards_notation::encode_game_as_string(&game);
-                (
-                    0.0,
-                    ChessStateV2::new(fen_string, self.select_other_player_moves_fn),
-                )
+                (0.0, self.next_state(fen_string))
             }
         }
     }
+}
 
+impl v2::state::State for ChessStateV2 {
     fn get_values(&self) -> Vec<f64> {
-        let regex = regex::Regex::new(r"^(.*) (.) (.*) (.*) (.*) (.*)").unwrap();
-        let captures = regex.captures(&self.fen_string).unwrap();
-        let parts: Vec<String> = captures
-            .iter()
-            .skip(1)
-            .map(|m| m.unwrap().as_str().to_string())
-            .collect();
+        get_board_values(&self.fen_string, &self.board)
+    }
+}
 
-        let width = self.board.get_width();
-        let height = self.board.get_height();
-        let square_count = width * height;
-
-        let mut black_pawn_map = vec![0.0; square_count];
-        let mut white_pawn_map = vec![0.0; square_count];
-        let mut black_knight_map = vec![0.0; square_count];
-        let mut white_knight_map = vec![0.0; square_count];
-        let mut black_bishop_map = vec![0.0; square_count];
-        let mut white_bishop_map = vec![0.0; square_count];
-        let mut black_rook_map = vec![0.0; square_count];
-        let mut white_rook_map = vec![0.0; square_count];
-        let mut black_queen_map = vec![0.0; square_count];
-        let mut white_queen_map = vec![0.0; square_count];
-        let mut black_king_map = vec![0.0; square_count];
-        let mut white_king_map = vec![0.0; square_count];
-
-        for x in 0..height {
-            for y in 0..width {
-                if let Some(piece) = self.board.get_piece_at_space(x, y) {
-                    let (b_map, w_map) = match piece.get_piece_type() {
-                        PieceType::Pawn => (&mut black_pawn_map, &mut white_pawn_map),
-                        PieceType::Rook => (&mut black_rook_map, &mut white_rook_map),
-                        PieceType::Knight => (&mut black_knight_map, &mut white_knight_map),
-                        PieceType::Bishop => (&mut black_bishop_map, &mut white_bishop_map),
-                        PieceType::Queen => (&mut black_queen_map, &mut white_queen_map),
-                        PieceType::King => (&mut black_king_map, &mut white_king_map),
-                    };
-                    let index = x * width + y;
-                    match piece.get_color() {
-                        Color::White => {
-                            w_map[index] = 1.0;
-                        }
-                        Color::Black => {
-                            b_map[index] = 1.0;
-                        }
+/// The same 790 values as a [`ChessStateV2`] of the game, without working out its moves.
+pub fn get_values_from_game(game: &ChessGame) -> Vec<f64> {
+    get_board_values(
+        &simple_chess::codec::forsyth_edwards_notation::encode_game_as_string(game),
+        game.get_board(),
+    )
+}
+
+fn get_board_values(fen_string: &str, board: &Board<ChessPiece>) -> Vec<f64> {
+    let regex = regex::Regex::new(r"^(.*) (.) (.*) (.*) (.*) (.*)").unwrap();
+    let captures = regex.captures(fen_string).unwrap();
+    let parts: Vec<String> = captures
+        .iter()
+        .skip(1)
+        .map(|m| m.unwrap().as_str().to_string())
+        .collect();
+
+    let mut castling_rights_map = vec![0.0; 4];
+    if parts[2].contains("K") {
+        castling_rights_map[0] = 1.0;
+    }
+    if parts[3].contains("Q") {
+        castling_rights_map[1] = 1.0;
+    }
+    if parts[3].contains("k") {
+        castling_rights_map[2] = 1.0;
+    }
+    if parts[3].contains("q") {
+        castling_rights_map[3] = 1.0;
+    }
+
+    let mut en_passant_squares_map = vec![0.0; 16];
+    if parts[3] != "-" {
+        let (col, row) = get_column_and_row_from_square_name(&parts[3]).unwrap();
+        let buffer = if row == 5 { 8 } else { 0 };
+        en_passant_squares_map[col + buffer] = 1.0;
+    }
+
+    let player_turn_map = match parts[1].as_str() {
+        "w" => vec![1.0, 0.0],
+        "b" => vec![0.0, 1.0],
+        _ => vec![0.0, 0.0],
+    };
+
+    let mut values = get_piece_planes(board); // 12 * 64
+    values.extend(castling_rights_map); // 4
+    values.extend(en_passant_squares_map); // 16
+    values.extend(player_turn_map); // 2
+    values
+}
+
+fn get_piece_planes(board: &Board<ChessPiece>) -> Vec<f64> {
+    let width = board.get_width();
+    let height = board.get_height();
+    let square_count = width * height;
+
+    let mut black_pawn_map = vec![0.0; square_count];
+    let mut white_pawn_map = vec![0.0; square_count];
+    let mut black_knight_map = vec![0.0; square_count];
+    let mut white_knight_map = vec![0.0; square_count];
+    let mut black_bishop_map = vec![0.0; square_count];
+    let mut white_bishop_map = vec![0.0; square_count];
+    let mut black_rook_map = vec![0.0; square_count];
+    let mut white_rook_map = vec![0.0; square_count];
+    let mut black_queen_map = vec![0.0; square_count];
+    let mut white_queen_map = vec![0.0; square_count];
+    let mut black_king_map = vec![0.0; square_count];
+    let mut white_king_map = vec![0.0; square_count];
+
+    for row in 0..height {
+        for col in 0..width {
+            if let Some(piece) = board.get_piece_at_space(col, row) {
+                let (b_map, w_map) = match piece.get_piece_type() {
+                    PieceType::Pawn => (&mut black_pawn_map, &mut white_pawn_map),
+                    PieceType::Rook => (&mut black_rook_map, &mut white_rook_map),
+                    PieceType::Knight => (&mut black_knight_map, &mut white_knight_map),
+                    PieceType::Bishop => (&mut black_bishop_map, &mut white_bishop_map),
+                    PieceType::Queen => (&mut black_queen_map, &mut white_queen_map),
+                    PieceType::King => (&mut black_king_map, &mut white_king_map),
+                };
+                let index = row * width + col;
+                match piece.get_color() {
+                    Color::White => {
+                        w_map[index] = 1.0;
+                    }
+                    Color::Black => {
+                        b_map[index] = 1.0;
                     }
                 }
             }
         }
+    }
 
-        let mut castling_rights_map = vec![0.0; 4];
-        if parts[2].contains("K") {
-            castling_rights_map[0] = 1.0;
-        }
-        if parts[3].contains("Q") {
-            castling_rights_map[1] = 1.0;
-        }
-        if parts[3].contains("k") {
-            castling_rights_map[2] = 1.0;
-        }
-        if parts[3].contains("q") {
-            castling_rights_map[3] = 1.0;
+    let mut values = Vec::with_capacity(ChessStateV2::PIECE_PLANE_COUNT * square_count);
+    values.extend(black_pawn_map); // 64
+    values.extend(white_pawn_map); // 64
+    values.extend(black_knight_map); // 64
+    values.extend(white_knight_map); // 64
+    values.extend(black_bishop_map); // 64
+    values.extend(white_bishop_map); // 64
+    values.extend(black_rook_map); // 64
+    values.extend(white_rook_map); // 64
+    values.extend(black_queen_map); // 64
+    values.extend(white_queen_map); // 64
+    values.extend(black_king_map); // 64
+    values.extend(white_king_map); // 64
+    values
+}
+
+impl StateActionFeatureVector for ChessStateV2 {
+    /// The 790 values of the board followed by the square the move starts on (64), the square it
+    /// ends on (64) and whether it is a king or queen side castle (2).
+    fn get_state_action_values(&self, action: &String) -> Vec<f64> {
+        let mut values = v2::state::State::get_values(self);
+        let width = self.board.get_width();
+        let square_count = width * self.board.get_height();
+
+        let mut from_square_map = vec![0.0; square_count];
+        let mut to_square_map = vec![0.0; square_count];
+        let mut castle_map = vec![0.0; 2];
+
+        match action.as_str() {
+            "O-O" => castle_map[0] = 1.0,
+            "O-O-O" => castle_map[1] = 1.0,
+            _ => {
+                // long algebraic notation always names the starting square before the ending one
+                let square_regex = regex::Regex::new(r"[a-h][1-8]").unwrap();
+                let squares = square_regex
+                    .find_iter(action)
+                    .map(|m| get_column_and_row_from_square_name(m.as_str()).unwrap())
+                    .map(|(col, row)| row * width + col)
+                    .collect::<Vec<usize>>();
+                if let (Some(from), Some(to)) = (squares.first(), squares.last()) {
+                    from_square_map[*from] = 1.0;
+                    to_square_map[*to] = 1.0;
+                }
+            }
         }
 
-        let mut en_passant_squares_map = vec![0.0; 16];
-        if parts[3] != "-" {
-            let (col, row) = get_column_and_row_from_square_name(&parts[3]).unwrap();
-            let buffer = if row == 5 { 8 } else { 0 };
-            en_passant_squares_map[col + buffer] = 1.0;
+        values.extend(from_square_map); // 64
+        values.extend(to_square_map); // 64
+        values.extend(castle_map); // 2
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+    use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+        Conv2DShape, LayerBuilder, LayerType, ModelBuilder,
+    };
+    use crate::attempts_at_framework::v2::state::State as _;
+
+    fn first_move(game: &mut ChessGame) -> ChessMoveType {
+        match game.get_game_state() {
+            GameState::InProgress { legal_moves, .. } | GameState::Check { legal_moves, .. } => {
+                legal_moves[0]
+            }
+            _ => panic!("no moves left"),
         }
+    }
 
-        let player_turn_map = match parts[1].as_str() {
-            "w" => vec![1.0, 0.0],
-            "b" => vec![0.0, 1.0],
-            _ => vec![0.0, 0.0],
-        };
+    #[test]
+    fn piece_planes_feed_a_convolution() {
+        let state = ChessStateV2::new(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".to_string(),
+            first_move,
+        );
+        let planes = state.get_piece_planes();
+        assert_eq!(planes, state.get_values()[..planes.len()]);
+        assert_eq!(planes.iter().sum::<f64>(), 32.0);
+        // the white pawn plane is the second, e4 is column 4 of row 3
+        assert_eq!(planes[64 + 3 * 8 + 4], 1.0);
+        assert_eq!(planes[64 + 8 + 4], 0.0);
 
-        let mut values = Vec::new();
-        values.extend(black_pawn_map); // 64
-        values.extend(white_pawn_map); // 64
-        values.extend(black_knight_map); // 64
-        values.extend(white_knight_map); // 64
-        values.extend(black_bishop_map); // 64
-        values.extend(white_bishop_map); // 64
-        values.extend(black_rook_map); // 64
-        values.extend(white_rook_map); // 64
-        values.extend(black_queen_map); // 64
-        values.extend(white_queen_map); // 64
-        values.extend(black_king_map); // 64
-        values.extend(white_king_map); // 64
-        values.extend(castling_rights_map); // 4
-        values.extend(en_passant_squares_map); // 16
-        values.extend(player_turn_map); // 2
-        values
+        let shape = Conv2DShape::new(ChessStateV2::PIECE_PLANE_COUNT, 8, 8, 4, 3);
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(planes.len())
+            .set_seed(3)
+            .add_layer(LayerBuilder::new_conv_2d(LayerType::RELU, shape))
+            .add_layer(LayerBuilder::new_flatten())
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        let mut model = builder.build().unwrap();
+
+        let before = model.predict(planes.clone())[0];
+        (0..50).for_each(|_| {
+            model.train(planes.clone(), vec![1.0], 0.01);
+        });
+        let after = model.predict(planes)[0];
+        assert!((after - 1.0).abs() < (before - 1.0).abs());
     }
 }
@@ -0,0 +1,679 @@
+use crate::attempts_at_framework::v1::agent::{HeuristicSearch, MonteCarloTreeSearch};
+use crate::attempts_at_framework::v1::policy::{DeterministicPolicy, Policy};
+use crate::attempts_at_framework::v2::agent::n_step_td::NStepTD;
+use crate::chess_state::{get_state_id_from_fen_string, ChessState};
+use crate::chess_state_v2::ChessStateV2;
+use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use simple_chess::chess_game_state_analyzer::GameState;
+use simple_chess::codec::forsyth_edwards_notation::{
+    build_game_from_string, encode_game_as_string,
+};
+use simple_chess::codec::long_algebraic_notation::encode_move_as_long_algebraic_notation;
+use simple_chess::{ChessGame, ChessMoveType, Color};
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+const NEW_GAME_FEN_STRING: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+/// what every player's Elo is pulled towards
+const AVERAGE_ELO: f64 = 1500.0;
+/// Draws every player is given against an [`AVERAGE_ELO`] player, without them a player that
+/// won or lost every game would have an Elo of plus or minus infinity.
+const PRIOR_DRAWS: f64 = 2.0;
+
+/// # Chess Player
+///
+/// Anything that can pick a move in a chess game, so it can play in a tournament.
+pub trait ChessPlayer {
+    fn get_name(&self) -> String;
+
+    /// The move to make in long algebraic notation, `game` always has at least one legal move.
+    fn select_move(&mut self, game: &ChessGame) -> String;
+}
+
+/// Plays any legal move.
+pub struct RandomPlayer {
+    rng: StdRng,
+}
+
+impl RandomPlayer {
+    pub fn new() -> Self {
+        Self {
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// a player that plays the same moves, in the same order, for the same seed
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for RandomPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChessPlayer for RandomPlayer {
+    fn get_name(&self) -> String {
+        "random".to_string()
+    }
+
+    fn select_move(&mut self, game: &ChessGame) -> String {
+        encode_move_as_long_algebraic_notation(get_legal_moves(game).choose(&mut self.rng).unwrap())
+    }
+}
+
+impl ChessPlayer for HeuristicSearch {
+    fn get_name(&self) -> String {
+        format!("heuristic search depth {}", self.get_budget().max_depth)
+    }
+
+    fn select_move(&mut self, game: &ChessGame) -> String {
+        self.search(game).unwrap().best_move
+    }
+}
+
+/// # Deterministic Policy Player
+///
+/// Plays a policy learnt on [`ChessState`]s, like the n-step SARSA agent's. Positions the policy
+/// has never seen get a random move from the player's own rng, so a policy only learnt for one
+/// colour plays the other at random.
+pub struct DeterministicPolicyPlayer<'a> {
+    policy: &'a DeterministicPolicy,
+    rng: StdRng,
+}
+
+impl<'a> DeterministicPolicyPlayer<'a> {
+    pub fn new(policy: &'a DeterministicPolicy) -> Self {
+        Self {
+            policy,
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// a player that falls back to the same random moves, in the same order, for the same seed
+    pub fn with_seed(policy: &'a DeterministicPolicy, seed: u64) -> Self {
+        Self {
+            policy,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl ChessPlayer for DeterministicPolicyPlayer<'_> {
+    fn get_name(&self) -> String {
+        "deterministic policy".to_string()
+    }
+
+    fn select_move(&mut self, game: &ChessGame) -> String {
+        let state_id = get_state_id_from_fen_string(&encode_game_as_string(game));
+        match self.policy.select_action_for_state(&state_id) {
+            Ok(action) => action,
+            Err(_) => encode_move_as_long_algebraic_notation(
+                get_legal_moves(game).choose(&mut self.rng).unwrap(),
+            ),
+        }
+    }
+}
+
+/// # N-Step TD Player
+///
+/// Plays an [`NStepTD`] network's moves the way the chess app picks them, by the value of the
+/// position after each move and a random reply. The replies come from the player's own rng.
+pub struct NStepTDPlayer<'a> {
+    agent: &'a NStepTD,
+    opponent_rng: Rc<RefCell<StdRng>>,
+}
+
+impl<'a> NStepTDPlayer<'a> {
+    pub fn new(agent: &'a NStepTD) -> Self {
+        Self {
+            agent,
+            opponent_rng: Rc::new(RefCell::new(StdRng::from_os_rng())),
+        }
+    }
+
+    /// a player that imagines the same replies, so picks the same moves, for the same seed
+    pub fn with_seed(agent: &'a NStepTD, seed: u64) -> Self {
+        Self {
+            agent,
+            opponent_rng: Rc::new(RefCell::new(StdRng::seed_from_u64(seed))),
+        }
+    }
+}
+
+impl ChessPlayer for NStepTDPlayer<'_> {
+    fn get_name(&self) -> String {
+        "n-step TD network".to_string()
+    }
+
+    fn select_move(&mut self, game: &ChessGame) -> String {
+        let state =
+            ChessStateV2::with_opponent_rng(encode_game_as_string(game), self.opponent_rng.clone());
+        self.agent.select_best_action_for_state(&state)
+    }
+}
+
+impl ChessPlayer for MonteCarloTreeSearch<ChessState> {
+    fn get_name(&self) -> String {
+        format!(
+            "monte carlo tree search {} simulations",
+            self.get_simulation_count()
+        )
+    }
+
+    fn select_move(&mut self, game: &ChessGame) -> String {
+        self.select_action(&ChessState::new(encode_game_as_string(game)))
+            .unwrap()
+    }
+}
+
+/// How [`run_tournament`] plays its games.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TournamentConfig {
+    /// Games every pair of players plays, colours swap every game.
+    pub games_per_pairing: usize,
+    /// Games start from each of these in turn, each opening is played twice in a row so both
+    /// players get both colours.
+    pub openings: Vec<String>,
+    /// Games still going after this many plies, moves by either player, are draws.
+    pub max_plies: usize,
+}
+
+impl TournamentConfig {
+    /// From the usual starting position, 200 plies a game.
+    pub fn new(games_per_pairing: usize) -> Self {
+        Self {
+            games_per_pairing,
+            openings: vec![NEW_GAME_FEN_STRING.to_string()],
+            max_plies: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    /// indexes into the players
+    pub white: usize,
+    pub black: usize,
+    pub opening: String,
+    pub result: GameResult,
+    /// in long algebraic notation
+    pub moves: Vec<String>,
+}
+
+/// One player's results against another.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PairingScore {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl PairingScore {
+    pub fn get_games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// a point a win and half a point a draw
+    pub fn get_points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+}
+
+/// # Tournament Result
+///
+/// Every game played, the crosstable and each player's Elo. Displaying it gives the crosstable,
+/// each cell is the row player's points against the column player:
+///
+/// ```text
+/// #  player                     1      2      3      points  elo
+/// 1  heuristic search depth 2   -      3.5/4  4/4    7.5/8   1761
+/// 2  heuristic search depth 1   0.5/4  -      3/4    3.5/8   1468
+/// 3  random                     0/4    1/4    -      1/8     1271
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TournamentResult {
+    pub names: Vec<String>,
+    pub games: Vec<GameRecord>,
+    /// `crosstable[a][b]` is how player `a` did against player `b`
+    pub crosstable: Vec<Vec<PairingScore>>,
+    pub elo: Vec<f64>,
+}
+
+impl TournamentResult {
+    pub fn get_total(&self, player: usize) -> PairingScore {
+        self.crosstable[player]
+            .iter()
+            .fold(PairingScore::default(), |total, score| PairingScore {
+                wins: total.wins + score.wins,
+                draws: total.draws + score.draws,
+                losses: total.losses + score.losses,
+            })
+    }
+}
+
+impl Display for TournamentResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let score_text =
+            |score: &PairingScore| format!("{}/{}", score.get_points(), score.get_games());
+        let name_width = self.names.iter().map(|name| name.len()).max().unwrap_or(0) + 3;
+        let player_count = self.names.len();
+
+        write!(f, "{:<3}{:<name_width$}", "#", "player")?;
+        for column in 0..player_count {
+            write!(f, "{:<7}", column + 1)?;
+        }
+        writeln!(f, "{:<8}elo", "points")?;
+
+        for row in 0..player_count {
+            write!(f, "{:<3}{:<name_width$}", row + 1, self.names[row])?;
+            for column in 0..player_count {
+                if row == column {
+                    write!(f, "{:<7}", "-")?;
+                } else {
+                    write!(f, "{:<7}", score_text(&self.crosstable[row][column]))?;
+                }
+            }
+            write!(
+                f,
+                "{:<8}{:.0}",
+                score_text(&self.get_total(row)),
+                self.elo[row]
+            )?;
+            if row + 1 < player_count {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct TournamentError {
+    message: String,
+}
+
+impl Debug for TournamentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TournamentError: {}", self.message)
+    }
+}
+
+impl Display for TournamentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TournamentError: {}", self.message)
+    }
+}
+
+impl Error for TournamentError {}
+
+/// # Run Tournament
+///
+/// Every player plays `games_per_pairing` games against every other player, headless. Fails if
+/// there are fewer than two players, an opening isn't a valid FEN string or is already over, or
+/// a player picks a move that isn't legal.
+pub fn run_tournament(
+    players: &mut [&mut dyn ChessPlayer],
+    config: &TournamentConfig,
+) -> Result<TournamentResult, TournamentError> {
+    if players.len() < 2 {
+        return Err(TournamentError {
+            message: "a tournament needs at least two players".to_string(),
+        });
+    }
+    if config.openings.is_empty() {
+        return Err(TournamentError {
+            message: "there must be at least one opening".to_string(),
+        });
+    }
+    let openings = config
+        .openings
+        .iter()
+        .map(|opening| {
+            let mut game = build_game_from_string(opening).map_err(|_| TournamentError {
+                message: format!("{} is not a valid FEN string", opening),
+            })?;
+            match game.get_game_state() {
+                GameState::InProgress { .. } | GameState::Check { .. } => Ok(game),
+                _ => Err(TournamentError {
+                    message: format!("{} is already over", opening),
+                }),
+            }
+        })
+        .collect::<Result<Vec<ChessGame>, TournamentError>>()?;
+
+    let player_count = players.len();
+    let mut games = Vec::new();
+    let mut crosstable = vec![vec![PairingScore::default(); player_count]; player_count];
+    for first in 0..player_count {
+        for second in first + 1..player_count {
+            for game_index in 0..config.games_per_pairing {
+                let (white, black) = if game_index % 2 == 0 {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                let opening_index = (game_index / 2) % openings.len();
+                let (result, moves) = play_game(
+                    players,
+                    white,
+                    black,
+                    openings[opening_index].clone(),
+                    config.max_plies,
+                )?;
+
+                match result {
+                    GameResult::WhiteWins => {
+                        crosstable[white][black].wins += 1;
+                        crosstable[black][white].losses += 1;
+                    }
+                    GameResult::BlackWins => {
+                        crosstable[white][black].losses += 1;
+                        crosstable[black][white].wins += 1;
+                    }
+                    GameResult::Draw => {
+                        crosstable[white][black].draws += 1;
+                        crosstable[black][white].draws += 1;
+                    }
+                }
+                games.push(GameRecord {
+                    white,
+                    black,
+                    opening: config.openings[opening_index].clone(),
+                    result,
+                    moves,
+                });
+            }
+        }
+    }
+
+    Ok(TournamentResult {
+        names: players.iter().map(|player| player.get_name()).collect(),
+        elo: estimate_elo(&crosstable),
+        games,
+        crosstable,
+    })
+}
+
+fn play_game(
+    players: &mut [&mut dyn ChessPlayer],
+    white: usize,
+    black: usize,
+    mut game: ChessGame,
+    max_plies: usize,
+) -> Result<(GameResult, Vec<String>), TournamentError> {
+    let mut moves = Vec::new();
+    loop {
+        let legal_moves = match game.get_game_state() {
+            GameState::InProgress { legal_moves, .. } => legal_moves,
+            GameState::Check { legal_moves, .. } => legal_moves,
+            GameState::Checkmate { winner } => {
+                let result = match winner {
+                    Color::White => GameResult::WhiteWins,
+                    Color::Black => GameResult::BlackWins,
+                };
+                return Ok((result, moves));
+            }
+            GameState::Stalemate => return Ok((GameResult::Draw, moves)),
+        };
+        if game.can_claim_draw().is_some() || moves.len() >= max_plies {
+            return Ok((GameResult::Draw, moves));
+        }
+
+        let player = match game.get_current_players_turn() {
+            Color::White => white,
+            Color::Black => black,
+        };
+        let chosen_move = players[player].select_move(&game);
+        let chess_move = legal_moves
+            .into_iter()
+            .find(|m| encode_move_as_long_algebraic_notation(m) == chosen_move)
+            .ok_or_else(|| TournamentError {
+                message: format!(
+                    "{} played {} which isn't legal in {}",
+                    players[player].get_name(),
+                    chosen_move,
+                    encode_game_as_string(&game)
+                ),
+            })?;
+        game.make_move(chess_move);
+        moves.push(chosen_move);
+    }
+}
+
+/// The Elo ratings that best explain the crosstable, found one player at a time with Newton's
+/// method. Every player also gets [`PRIOR_DRAWS`] draws against an [`AVERAGE_ELO`] player, which
+/// keeps ratings finite and pulls players with few games towards the average.
+pub fn estimate_elo(crosstable: &[Vec<PairingScore>]) -> Vec<f64> {
+    let player_count = crosstable.len();
+    let expected_score = |rating: f64, other_rating: f64| {
+        1.0 / (1.0 + 10.0_f64.powf((other_rating - rating) / 400.0))
+    };
+    let slope = 10.0_f64.ln() / 400.0;
+
+    let mut elo = vec![AVERAGE_ELO; player_count];
+    for _ in 0..100 {
+        let mut largest_change: f64 = 0.0;
+        for player in 0..player_count {
+            let mut points = PRIOR_DRAWS / 2.0;
+            let mut expected = PRIOR_DRAWS * expected_score(elo[player], AVERAGE_ELO);
+            let mut derivative = PRIOR_DRAWS
+                * slope
+                * expected_score(elo[player], AVERAGE_ELO)
+                * (1.0 - expected_score(elo[player], AVERAGE_ELO));
+            for other in 0..player_count {
+                let score = &crosstable[player][other];
+                if other == player || score.get_games() == 0 {
+                    continue;
+                }
+                let games = score.get_games() as f64;
+                let expected_per_game = expected_score(elo[player], elo[other]);
+                points += score.get_points();
+                expected += games * expected_per_game;
+                derivative += games * slope * expected_per_game * (1.0 - expected_per_game);
+            }
+
+            let change = (points - expected) / derivative;
+            elo[player] += change;
+            largest_change = largest_change.max(change.abs());
+        }
+        if largest_change < 1e-6 {
+            break;
+        }
+    }
+    elo
+}
+
+fn get_legal_moves(game: &ChessGame) -> Vec<ChessMoveType> {
+    match game.clone().get_game_state() {
+        GameState::InProgress { legal_moves, .. } => legal_moves,
+        GameState::Check { legal_moves, .. } => legal_moves,
+        GameState::Checkmate { .. } | GameState::Stalemate => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attempts_at_framework::v1::agent::{MaterialEvaluation, SearchBudget};
+
+    /// Always plays the first legal move it's given, or `illegal_move` if set.
+    struct FirstMovePlayer {
+        illegal_move: Option<String>,
+    }
+
+    impl ChessPlayer for FirstMovePlayer {
+        fn get_name(&self) -> String {
+            "first move".to_string()
+        }
+
+        fn select_move(&mut self, game: &ChessGame) -> String {
+            match &self.illegal_move {
+                Some(illegal_move) => illegal_move.clone(),
+                None => encode_move_as_long_algebraic_notation(&get_legal_moves(game)[0]),
+            }
+        }
+    }
+
+    #[test]
+    fn search_beats_a_player_that_ignores_the_board() {
+        // white mates on the back rank in one, black has a queen to lose on the other board
+        let mut config = TournamentConfig::new(4);
+        config.openings = vec![
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string(),
+            "4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1".to_string(),
+        ];
+        config.max_plies = 20;
+        let mut search = HeuristicSearch::new(SearchBudget::new(2));
+        search.set_seed(1);
+        search.set_evaluation(Box::new(MaterialEvaluation));
+        let mut first_move = FirstMovePlayer { illegal_move: None };
+
+        let result = run_tournament(&mut [&mut search, &mut first_move], &config).unwrap();
+        assert_eq!(result.games.len(), 4);
+        assert_eq!(
+            result
+                .games
+                .iter()
+                .map(|game| (game.white, game.black))
+                .collect::<Vec<(usize, usize)>>(),
+            vec![(0, 1), (1, 0), (0, 1), (1, 0)]
+        );
+        assert_eq!(result.games[0].result, GameResult::WhiteWins);
+        assert_eq!(result.games[0].moves, vec!["Ra1a8".to_string()]);
+        assert_eq!(result.games[2].opening, config.openings[1]);
+
+        let search_score = result.crosstable[0][1];
+        assert_eq!(search_score.get_games(), 4);
+        assert_eq!(search_score.wins, result.crosstable[1][0].losses);
+        assert_eq!(search_score.draws, result.crosstable[1][0].draws);
+        assert!(search_score.get_points() > 2.0);
+        assert!(result.elo[0] > result.elo[1]);
+
+        let table = result.to_string();
+        assert!(table.starts_with("#  player"));
+        assert!(table.contains("heuristic search depth 2"));
+        assert_eq!(table.lines().count(), 3);
+    }
+
+    #[test]
+    fn seeded_players_replay_the_same_games() {
+        use crate::attempts_at_framework::v2::artificial_neural_network::loss_functions::mean_squared_error::MeanSquaredError;
+        use crate::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{
+            LayerBuilder, LayerType, ModelBuilder,
+        };
+
+        let mut config = TournamentConfig::new(2);
+        config.max_plies = 30;
+        let play = |seed: u64| {
+            let mut white = RandomPlayer::with_seed(seed);
+            let mut black = RandomPlayer::with_seed(seed + 1);
+            run_tournament(&mut [&mut white, &mut black], &config)
+                .unwrap()
+                .games
+                .into_iter()
+                .map(|game| game.moves)
+                .collect::<Vec<Vec<String>>>()
+        };
+        assert_eq!(play(1), play(1));
+        assert_ne!(play(1), play(3));
+
+        let mut builder = ModelBuilder::new();
+        builder
+            .set_loss_function(Box::new(MeanSquaredError))
+            .set_input_size(790)
+            .set_seed(2)
+            .add_layer(LayerBuilder::new(LayerType::LINEAR, 1));
+        let agent = NStepTD::new(2, builder.build().unwrap(), 0.01);
+        let play = |seed: u64| {
+            let mut network = NStepTDPlayer::with_seed(&agent, seed);
+            let mut random = RandomPlayer::with_seed(seed);
+            run_tournament(&mut [&mut network, &mut random], &config)
+                .unwrap()
+                .games
+                .into_iter()
+                .map(|game| game.moves)
+                .collect::<Vec<Vec<String>>>()
+        };
+        assert_eq!(play(4), play(4));
+
+        // the policy knows the opening move, everything after falls back to the rng
+        let mut policy = DeterministicPolicy::new();
+        policy.set_actions_for_state(
+            get_state_id_from_fen_string(&NEW_GAME_FEN_STRING.to_string()),
+            "e2e4".to_string(),
+        );
+        let play = |seed: u64| {
+            let mut learnt = DeterministicPolicyPlayer::with_seed(&policy, seed);
+            let mut random = RandomPlayer::with_seed(seed);
+            run_tournament(&mut [&mut learnt, &mut random], &config)
+                .unwrap()
+                .games
+                .into_iter()
+                .map(|game| game.moves)
+                .collect::<Vec<Vec<String>>>()
+        };
+        let games = play(5);
+        assert_eq!(games[0][0], "e2e4");
+        assert_eq!(games, play(5));
+    }
+
+    #[test]
+    fn estimates_elo_from_the_crosstable() {
+        let score = |wins, draws, losses| PairingScore {
+            wins,
+            draws,
+            losses,
+        };
+        // even results, even ratings
+        let elo = estimate_elo(&[
+            vec![score(0, 0, 0), score(5, 0, 5)],
+            vec![score(5, 0, 5), score(0, 0, 0)],
+        ]);
+        assert!((elo[0] - AVERAGE_ELO).abs() < 1e-6);
+        assert!((elo[1] - AVERAGE_ELO).abs() < 1e-6);
+
+        // a clean sweep is finite, and the ratings stay centred on the average
+        let elo = estimate_elo(&[
+            vec![score(0, 0, 0), score(10, 0, 0)],
+            vec![score(0, 0, 10), score(0, 0, 0)],
+        ]);
+        assert!(elo[0].is_finite() && elo[0] > AVERAGE_ELO + 200.0);
+        assert!(((elo[0] + elo[1]) / 2.0 - AVERAGE_ELO).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rejects_illegal_moves_and_bad_setups() {
+        let config = TournamentConfig::new(1);
+        let mut first_move = FirstMovePlayer { illegal_move: None };
+        assert!(run_tournament(&mut [&mut first_move], &config).is_err());
+
+        let mut cheat = FirstMovePlayer {
+            illegal_move: Some("Ke1e8".to_string()),
+        };
+        let error = run_tournament(&mut [&mut cheat, &mut first_move], &config).unwrap_err();
+        assert!(error.to_string().contains("Ke1e8"));
+
+        let mut config = TournamentConfig::new(1);
+        config.openings = vec!["R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1".to_string()];
+        assert!(run_tournament(
+            &mut [&mut RandomPlayer::with_seed(1), &mut first_move],
+            &config
+        )
+        .is_err());
+    }
+}
@@ -10,9 +10,11 @@ mod chapter_09;
 mod chapter_10;
 mod chapter_11;
 mod chapter_12;
-mod chapter_13;
+pub mod chapter_13;
+pub mod chess_self_play;
 pub mod chess_state;
 pub mod chess_state_v2;
+pub mod chess_tournament;
 pub mod service;
 
 pub fn add(left: u64, right: u64) -> u64 {
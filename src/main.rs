@@ -7,8 +7,10 @@ use simple_chess::codec::forsyth_edwards_notation::{
 };
 use simple_chess::codec::long_algebraic_notation::encode_move_as_long_algebraic_notation;
 use simple_chess::{ChessGame, ChessMoveType};
+use std::path::PathBuf;
 use ReinforcementLearning::attempts_at_framework::v1::agent::{
-    get_best_action_heuristic_search, NStepSarsa,
+    get_best_action_heuristic_search, HeuristicSearch, MonteCarloTreeSearch, NStepSarsa,
+    SearchBudget,
 };
 use ReinforcementLearning::attempts_at_framework::v1::policy::{DeterministicPolicy, Policy};
 use ReinforcementLearning::attempts_at_framework::v2::agent::n_step_td::NStepTD;
@@ -16,8 +18,12 @@ use ReinforcementLearning::attempts_at_framework::v2::artificial_neural_network:
 use ReinforcementLearning::attempts_at_framework::v2::artificial_neural_network::model::Model;
 use ReinforcementLearning::attempts_at_framework::v2::artificial_neural_network::model::model_builder::{LayerBuilder, ModelBuilder};
 use ReinforcementLearning::attempts_at_framework::v2::artificial_neural_network::model::model_builder::LayerType::{LINEAR, RELU};
+use ReinforcementLearning::chess_self_play::{train_by_self_play, SelfPlayConfig};
 use ReinforcementLearning::chess_state::{get_state_id_from_fen_string, ChessState};
 use ReinforcementLearning::chess_state_v2::ChessStateV2;
+use ReinforcementLearning::chess_tournament::{
+    run_tournament, DeterministicPolicyPlayer, NStepTDPlayer, TournamentConfig,
+};
 
 fn main() {
     let options = eframe::NativeOptions {
@@ -45,14 +51,21 @@ fn generate_model() -> Model {
     // builder.add_layer(LayerBuilder::new(RELU, 10));
     builder.add_layer(LayerBuilder::new(LINEAR, 1));
 
-    let model = builder.build();
-    model.unwrap()
+    let mut model = builder.build().unwrap();
+    // every legal move is valued in one batch when picking a move
+    model.set_prediction_threads(
+        std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1),
+    );
+    model
 }
 
 enum LearningMethod {
     NStepSarsa,
     HeuristicSearch,
     NeuralNetwork,
+    MonteCarloTreeSearch,
 }
 
 struct MyApp {
@@ -66,9 +79,12 @@ struct MyApp {
     agent: Box<NStepSarsa>,
     num_episodes_to_learn_for: usize,
     heuristic_depth: usize,
+    tournament_games: usize,
     fen_string_input: String,
     learning_method: LearningMethod,
     n_step_td_ann_agent: NStepTD,
+    model_path_input: String,
+    monte_carlo_tree_search: MonteCarloTreeSearch<ChessState>,
 }
 
 const NEW_GAME_FEN_STRING: &'static str =
@@ -94,9 +110,12 @@ impl MyApp {
             agent: Box::new(NStepSarsa::new(100, 0.5, 0.2, 1.0)),
             num_episodes_to_learn_for: 0,
             heuristic_depth: 1,
+            tournament_games: 2,
             fen_string_input: String::from(""),
             learning_method: LearningMethod::HeuristicSearch,
             n_step_td_ann_agent: agent,
+            model_path_input: String::from("models/chess_value.model"),
+            monte_carlo_tree_search: MonteCarloTreeSearch::new(500),
         }
     }
 
@@ -127,6 +146,24 @@ impl MyApp {
         println!("Finished Learning");
     }
 
+    fn do_self_play_learning(&mut self) {
+        println!(
+            "starting {} games of self play",
+            self.num_episodes_to_learn_for
+        );
+        let mut config = SelfPlayConfig::new(self.num_episodes_to_learn_for);
+        if build_game_from_string(&self.fen_string_input).is_ok() {
+            config.starting_fen = self.fen_string_input.clone();
+        }
+        config.window_size = (self.num_episodes_to_learn_for / 10).max(1);
+
+        match train_by_self_play(&mut self.n_step_td_ann_agent, &config) {
+            Ok(windows) => windows.iter().for_each(|window| println!("{}", window)),
+            Err(e) => println!("{}", e),
+        }
+        println!("Finished self play");
+    }
+
     fn do_n_step_td_learning(&mut self) {
         println!("starting training the neural network");
         let mut game = build_game_from_string(&self.fen_string_input).unwrap_or(ChessGame::new());
@@ -279,6 +316,13 @@ impl MyApp {
             .select_best_action_for_state(&state)
     }
 
+    pub fn get_best_action_monte_carlo_tree_search(&mut self, game: &mut ChessGame) -> String {
+        let state = ChessState::new(encode_game_as_string(game));
+        self.monte_carlo_tree_search
+            .select_action(&state)
+            .expect("the game is already over")
+    }
+
     fn select_and_make_move(&mut self, legal_moves: &Vec<ChessMoveType>) -> ChessMoveType {
         let mut game = self.chess_game.clone();
         let next_move = match self.learning_method {
@@ -287,6 +331,9 @@ impl MyApp {
                 get_best_action_heuristic_search(&mut game, self.heuristic_depth)
             }
             LearningMethod::NeuralNetwork => self.get_best_action_neural_network(&mut game),
+            LearningMethod::MonteCarloTreeSearch => {
+                self.get_best_action_monte_carlo_tree_search(&mut game)
+            }
         };
 
         let nm = legal_moves
@@ -473,20 +520,70 @@ impl MyApp {
         });
     }
 
+    fn simulation_count_display(&mut self, ui: &mut Ui) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Simulations:");
+                let mut shown_count = self
+                    .monte_carlo_tree_search
+                    .get_simulation_count()
+                    .to_string();
+                if ui.text_edit_singleline(&mut shown_count).changed() {
+                    if let Ok(num) = shown_count.parse::<u32>() {
+                        if num > 0 {
+                            self.monte_carlo_tree_search
+                                .set_simulation_count(num as usize);
+                        }
+                    }
+                }
+            })
+        });
+    }
+
     fn ann_learn_display(&mut self, ui: &mut Ui) {
-        ui.horizontal(|ui| {
-            if ui.button("Learn").clicked() {
-                self.do_n_step_td_learning();
-            };
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Learn").clicked() {
+                    self.do_n_step_td_learning();
+                };
+                if ui.button("Self Play").clicked() {
+                    self.do_self_play_learning();
+                };
 
+                ui.horizontal(|ui| {
+                    ui.label("Number of Episodes:");
+                    let mut num_episodes_string = self.num_episodes_to_learn_for.to_string();
+                    if ui.text_edit_singleline(&mut num_episodes_string).changed() {
+                        if let Ok(num) = num_episodes_string.parse::<u32>() {
+                            self.num_episodes_to_learn_for = num as usize;
+                        }
+                    }
+                });
+            });
             ui.horizontal(|ui| {
-                ui.label("Number of Episodes:");
-                let mut num_episodes_string = self.num_episodes_to_learn_for.to_string();
-                if ui.text_edit_singleline(&mut num_episodes_string).changed() {
-                    if let Ok(num) = num_episodes_string.parse::<u32>() {
-                        self.num_episodes_to_learn_for = num as usize;
+                if ui.button("Save Model").clicked() {
+                    let path = PathBuf::from(&self.model_path_input);
+                    match self.n_step_td_ann_agent.get_model().save(&path) {
+                        Ok(_) => println!("saved model to {}", path.display()),
+                        Err(e) => println!("{}", e),
                     }
                 }
+                if ui.button("Load Model").clicked() {
+                    let path = PathBuf::from(&self.model_path_input);
+                    match Model::load(&path) {
+                        Ok(mut model) => {
+                            model.set_prediction_threads(
+                                self.n_step_td_ann_agent
+                                    .get_model()
+                                    .get_prediction_threads(),
+                            );
+                            self.n_step_td_ann_agent.set_model(model);
+                            println!("loaded model from {}", path.display());
+                        }
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                ui.text_edit_singleline(&mut self.model_path_input);
             });
         });
     }
@@ -540,6 +637,50 @@ impl MyApp {
         });
     }
 
+    /// Plays the heuristic search at the chosen depth, the n-step SARSA policy and the neural
+    /// network against each other and prints the crosstable. The policy was only learnt for
+    /// black, so it plays white at random.
+    fn run_tournament(&mut self) {
+        println!(
+            "starting a tournament of {} games a pairing",
+            self.tournament_games
+        );
+        let mut config = TournamentConfig::new(self.tournament_games);
+        if build_game_from_string(&self.fen_string_input).is_ok() {
+            config.openings = vec![self.fen_string_input.clone()];
+        }
+        let mut heuristic_search = HeuristicSearch::new(SearchBudget::new(self.heuristic_depth));
+        let mut policy_player = DeterministicPolicyPlayer::new(&self.policy_for_black);
+        let mut n_step_td_player = NStepTDPlayer::new(&self.n_step_td_ann_agent);
+
+        match run_tournament(
+            &mut [
+                &mut heuristic_search,
+                &mut policy_player,
+                &mut n_step_td_player,
+            ],
+            &config,
+        ) {
+            Ok(result) => println!("{}", result),
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    fn tournament_display(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Run Tournament").clicked() {
+                self.run_tournament();
+            }
+            ui.label("Games per Pairing:");
+            let mut shown_games = self.tournament_games.to_string();
+            if ui.text_edit_singleline(&mut shown_games).changed() {
+                if let Ok(num) = shown_games.parse::<u32>() {
+                    self.tournament_games = num as usize;
+                }
+            }
+        });
+    }
+
     fn learning_method_select(&mut self, ui: &mut Ui) {
         ui.vertical(|ui| {
             ui.horizontal(|ui| {
@@ -555,6 +696,9 @@ impl MyApp {
                 ui.button("Neural Network").clicked().then(|| {
                     self.learning_method = LearningMethod::NeuralNetwork;
                 });
+                ui.button("Monte Carlo Tree Search").clicked().then(|| {
+                    self.learning_method = LearningMethod::MonteCarloTreeSearch;
+                });
             })
         });
     }
@@ -589,6 +733,8 @@ impl eframe::App for MyApp {
                     ui.add_space(10.0);
                     self.learning_method_select(ui);
                     ui.add_space(10.0);
+                    self.tournament_display(ui);
+                    ui.add_space(10.0);
                     match self.learning_method {
                         LearningMethod::NStepSarsa => {
                             self.learn_button(ui);
@@ -597,6 +743,9 @@ impl eframe::App for MyApp {
                             self.depth_display(ui);
                         }
                         LearningMethod::NeuralNetwork => self.ann_learn_display(ui),
+                        LearningMethod::MonteCarloTreeSearch => {
+                            self.simulation_count_display(ui);
+                        }
                     };
                     ui.add_space(10.0);
                     self.previous_moves(ui);
@@ -1,4 +1,5 @@
-use crate::attempts_at_framework::v2::state::State;
+use crate::attempts_at_framework::v1::state::State;
+use crate::attempts_at_framework::v2;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 
@@ -45,7 +46,9 @@ impl State for WalkState<'_> {
         let new_id = self.id as i32 + action.parse::<i32>().unwrap();
         self.factory.generate_state_and_reward_for_id(new_id)
     }
+}
 
+impl v2::state::State for WalkState<'_> {
     fn get_values(&self) -> Vec<f64> {
         self.values.clone()
     }